@@ -2,6 +2,7 @@ use std::path::{PathBuf, Path};
 use super::wallpaper_error::WallpaperError;
 use crate::constants::{DESKTOP_ENTRY, NAME};
 use std::cmp::Ordering;
+use freedesktop::locale;
 const METADATA_FILE: &str = "metadata.desktop";
 
 #[derive(Debug, Clone, Default, PartialEq, PartialOrd, Eq)]
@@ -26,7 +27,7 @@ impl WallpaperItem {
                 if metadata.exists() {
                     let entry = freedesktop_entry_parser::parse_entry(metadata)?;
                     let desktop_entry = entry.section(DESKTOP_ENTRY);
-                    let name = desktop_entry.attr(NAME).map(ToString::to_string);
+                    let name = locale::localized_attr(&desktop_entry, NAME).map(ToString::to_string);
 
                     Ok(Self {
                         is_local, name, path: path.to_path_buf()
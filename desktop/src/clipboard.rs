@@ -0,0 +1,9 @@
+mod clipboard_error;
+mod copied_files;
+mod drop_target;
+mod file_clipboard;
+
+pub use clipboard_error::ClipboardError;
+pub use copied_files::CopiedFiles;
+pub use drop_target::accept_drops;
+pub use file_clipboard::FileClipboard;
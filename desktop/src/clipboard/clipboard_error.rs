@@ -0,0 +1,16 @@
+use thiserror::Error;
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError};
+
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+    #[error(transparent)]
+    ConnectError(#[from] ConnectError),
+    #[error(transparent)]
+    ConnectionError(#[from] ConnectionError),
+    #[error(transparent)]
+    ReplyError(#[from] ReplyError),
+    #[error(transparent)]
+    ReplyOrIdError(#[from] ReplyOrIdError),
+    #[error("the application owning the clipboard did not answer")]
+    Timeout,
+}
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use url::Url;
+
+const COPY: &str = "copy";
+const CUT: &str = "cut";
+// Nautilus puts its file list in plain text after this line.
+const NAUTILUS_HEADER: &str = "x-special/nautilus-clipboard";
+
+/// Files copied or cut to the clipboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopiedFiles {
+    pub paths: Vec<PathBuf>,
+    pub is_cut: bool,
+}
+
+impl CopiedFiles {
+    pub fn new(paths: Vec<PathBuf>, is_cut: bool) -> Self {
+        Self { paths, is_cut }
+    }
+
+    /// `x-special/gnome-copied-files`: the operation, then a URI per line.
+    pub fn to_gnome_copied_files(&self) -> String {
+        let operation = if self.is_cut { CUT } else { COPY };
+
+        std::iter::once(operation.to_string())
+            .chain(self.uris())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// `text/uri-list`: a URI per line, each ended by CRLF.
+    pub fn to_uri_list(&self) -> String {
+        self.uris().map(|uri| uri + "\r\n").collect()
+    }
+
+    /// Plain text: a path per line.
+    pub fn to_text(&self) -> String {
+        self.paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn from_gnome_copied_files(data: &str) -> Option<Self> {
+        let mut lines = data.lines();
+        let is_cut = match lines.next()?.trim() {
+            COPY => false,
+            CUT => true,
+            _ => return None,
+        };
+
+        Self::from_uris(lines, is_cut)
+    }
+
+    pub fn from_uri_list(data: &str, is_cut: bool) -> Option<Self> {
+        Self::from_uris(data.lines().filter(|line| !line.starts_with('#')), is_cut)
+    }
+
+    /// Plain text made only of absolute paths or file URIs, one per line.
+    pub fn from_text(data: &str) -> Option<Self> {
+        if let Some(files) = data.strip_prefix(NAUTILUS_HEADER) {
+            return Self::from_gnome_copied_files(files.trim_start());
+        }
+
+        let paths = data
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| match line.strip_prefix('/') {
+                Some(_) => Some(PathBuf::from(line)),
+                None => Url::parse(line).ok()?.to_file_path().ok(),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Self::with_paths(paths, false)
+    }
+
+    fn from_uris<'a, I: Iterator<Item = &'a str>>(lines: I, is_cut: bool) -> Option<Self> {
+        let paths = lines
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| Url::parse(line).ok()?.to_file_path().ok())
+            .collect::<Option<Vec<_>>>()?;
+        Self::with_paths(paths, is_cut)
+    }
+
+    fn with_paths(paths: Vec<PathBuf>, is_cut: bool) -> Option<Self> {
+        if paths.is_empty() {
+            None
+        } else {
+            Some(Self::new(paths, is_cut))
+        }
+    }
+
+    fn uris(&self) -> impl Iterator<Item = String> + '_ {
+        self.paths
+            .iter()
+            .filter_map(|path| Url::from_file_path(path).ok())
+            .map(String::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn copied(paths: &[&str], is_cut: bool) -> CopiedFiles {
+        CopiedFiles::new(paths.iter().map(PathBuf::from).collect(), is_cut)
+    }
+
+    #[test]
+    fn writes_gnome_copied_files() {
+        assert_eq!(
+            copied(&["/home/me/a b.txt", "/home/me/ខ"], true).to_gnome_copied_files(),
+            "cut\nfile:///home/me/a%20b.txt\nfile:///home/me/%E1%9E%81"
+        );
+        assert_eq!(
+            copied(&["/tmp/a"], false).to_gnome_copied_files(),
+            "copy\nfile:///tmp/a"
+        );
+    }
+
+    #[test]
+    fn reads_gnome_copied_files_back() {
+        let files = copied(&["/home/me/a b.txt", "/home/me/100%.txt"], true);
+        assert_eq!(
+            CopiedFiles::from_gnome_copied_files(&files.to_gnome_copied_files()),
+            Some(files)
+        );
+        assert_eq!(
+            CopiedFiles::from_gnome_copied_files("copy\nfile:///tmp/a\n"),
+            Some(copied(&["/tmp/a"], false))
+        );
+        assert_eq!(
+            CopiedFiles::from_gnome_copied_files("move\nfile:///tmp/a"),
+            None
+        );
+        assert_eq!(CopiedFiles::from_gnome_copied_files("copy\n"), None);
+    }
+
+    #[test]
+    fn reads_uri_lists_with_comments_and_crlf() {
+        let files = copied(&["/tmp/a b", "/tmp/c"], false);
+        assert_eq!(
+            files.to_uri_list(),
+            "file:///tmp/a%20b\r\nfile:///tmp/c\r\n"
+        );
+        assert_eq!(
+            CopiedFiles::from_uri_list(&files.to_uri_list(), false),
+            Some(files)
+        );
+        assert_eq!(
+            CopiedFiles::from_uri_list("# dragged\r\nfile:///tmp/a\r\n", true),
+            Some(copied(&["/tmp/a"], true))
+        );
+        // Remote files cannot be pasted.
+        assert_eq!(
+            CopiedFiles::from_uri_list("https://koompi.com/a.png\r\n", false),
+            None
+        );
+    }
+
+    #[test]
+    fn reads_paths_and_nautilus_text() {
+        assert_eq!(
+            CopiedFiles::from_text("/tmp/a b\n file:///tmp/c \n\n"),
+            Some(copied(&["/tmp/a b", "/tmp/c"], false))
+        );
+        assert_eq!(
+            CopiedFiles::from_text("x-special/nautilus-clipboard\ncut\nfile:///tmp/a\n"),
+            Some(copied(&["/tmp/a"], true))
+        );
+        // Text which is not only files is left to other applications.
+        assert_eq!(CopiedFiles::from_text("/tmp/a\nhello"), None);
+        assert_eq!(CopiedFiles::from_text(""), None);
+    }
+}
@@ -0,0 +1,285 @@
+use super::ClipboardError;
+use crate::proxy_message::ProxyMessage;
+use iced_winit::winit::event_loop::EventLoopProxy;
+use std::thread;
+use url::Url;
+use x11rb::atom_manager;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    self, Atom, AtomEnum, ClientMessageEvent, ConnectionExt as _, CreateWindowAux, EventMask,
+    PropMode, SelectionNotifyEvent, Window, WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, NONE};
+
+/// Version of the XDND protocol spoken.
+const XDND_VERSION: u32 = 5;
+
+atom_manager! {
+    Atoms: AtomsCookie {
+        XdndAware,
+        XdndProxy,
+        XdndEnter,
+        XdndPosition,
+        XdndStatus,
+        XdndLeave,
+        XdndDrop,
+        XdndFinished,
+        XdndSelection,
+        XdndTypeList,
+        XdndActionCopy,
+        URI_LIST: b"text/uri-list",
+        NETSCAPE_URL: b"_NETSCAPE_URL",
+        // Property the dropped data is received in.
+        KOOMPI_DROP,
+    }
+}
+
+/// Drag in progress over the desktop.
+#[derive(Debug)]
+struct Drag {
+    source: Window,
+    // Window the source drags over, the desktop rather than the proxy.
+    target: Window,
+    // Type to receive the data as, none when no URL is offered.
+    data_type: Option<Atom>,
+    // Position of the pointer on the screen.
+    position: (i32, i32),
+}
+
+/// Receive the URLs and files dropped on the desktop `window` in the background, as winit only
+/// reports dropped files. Drag sources are sent to a window of our own through `XdndProxy`.
+pub fn accept_drops(
+    window: Window,
+    proxy: EventLoopProxy<ProxyMessage>,
+) -> Result<(), ClipboardError> {
+    let (conn, screen_num) = RustConnection::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let drop_window = conn.generate_id()?;
+    let atoms = Atoms::new(&conn)?.reply()?;
+
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        drop_window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )?;
+    conn.change_property32(
+        PropMode::REPLACE,
+        drop_window,
+        atoms.XdndAware,
+        AtomEnum::ATOM,
+        &[XDND_VERSION],
+    )?;
+    // The proxy names itself, which tells it from a stale property.
+    for proxied in &[drop_window, window] {
+        conn.change_property32(
+            PropMode::REPLACE,
+            *proxied,
+            atoms.XdndProxy,
+            AtomEnum::WINDOW,
+            &[drop_window],
+        )?;
+    }
+    conn.flush()?;
+
+    thread::spawn(move || {
+        if let Err(err) = serve(&conn, drop_window, &atoms, &proxy) {
+            eprintln!("drop target error: {}", err);
+        }
+    });
+    Ok(())
+}
+
+/// Follow the drags over the desktop as long as the event loop lives.
+fn serve(
+    conn: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+    proxy: &EventLoopProxy<ProxyMessage>,
+) -> Result<(), ClipboardError> {
+    let mut drag: Option<Drag> = None;
+
+    loop {
+        match conn.wait_for_event()? {
+            Event::ClientMessage(event) if event.format == 32 => {
+                let data = event.data.as_data32();
+                let is_source = |drag: &Drag| drag.source == data[0];
+
+                if event.type_ == atoms.XdndEnter {
+                    let types = if data[1] & 1 != 0 {
+                        offered_types(conn, atoms, data[0])?
+                    } else {
+                        data[2..].to_vec()
+                    };
+                    drag = Some(Drag {
+                        source: data[0],
+                        target: event.window,
+                        data_type: [atoms.URI_LIST, atoms.NETSCAPE_URL]
+                            .iter()
+                            .copied()
+                            .find(|data_type| types.contains(data_type)),
+                        position: (0, 0),
+                    });
+                } else if event.type_ == atoms.XdndPosition {
+                    if let Some(drag) = drag.as_mut().filter(|drag| is_source(drag)) {
+                        drag.position =
+                            (i32::from((data[2] >> 16) as i16), i32::from(data[2] as i16));
+                        let (flags, action) = match drag.data_type {
+                            // Positions keep coming to tell the item under the pointer.
+                            Some(_) => (0b11, atoms.XdndActionCopy),
+                            None => (0, NONE),
+                        };
+                        send_message(
+                            conn,
+                            drag.source,
+                            atoms.XdndStatus,
+                            [drag.target, flags, 0, 0, action],
+                        )?;
+                    }
+                } else if event.type_ == atoms.XdndLeave {
+                    drag = drag.filter(|drag| !is_source(drag));
+                } else if event.type_ == atoms.XdndDrop {
+                    if let Some(dropped) = drag.take().filter(|drag| is_source(drag)) {
+                        match dropped.data_type {
+                            Some(data_type) => {
+                                conn.convert_selection(
+                                    window,
+                                    atoms.XdndSelection,
+                                    data_type,
+                                    atoms.KOOMPI_DROP,
+                                    data[2],
+                                )?;
+                                conn.flush()?;
+                                // The drop is finished once its data is received.
+                                drag = Some(dropped);
+                            }
+                            None => send_message(
+                                conn,
+                                dropped.source,
+                                atoms.XdndFinished,
+                                [dropped.target, 0, NONE, 0, 0],
+                            )?,
+                        }
+                    }
+                }
+            }
+            Event::SelectionNotify(event) if event.requestor == window => {
+                if let Some(drag) = drag.take() {
+                    let urls = read_urls(conn, atoms, &event, drag.data_type)?;
+                    let action = if urls.is_empty() {
+                        NONE
+                    } else {
+                        atoms.XdndActionCopy
+                    };
+                    send_message(
+                        conn,
+                        drag.source,
+                        atoms.XdndFinished,
+                        [drag.target, (action != NONE) as u32, action, 0, 0],
+                    )?;
+
+                    if !urls.is_empty()
+                        && proxy
+                            .send_event(ProxyMessage::UrlsDropped(urls, drag.position))
+                            .is_err()
+                    {
+                        return Ok(());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Types of the data offered by `source` when it offers more than the XdndEnter message holds,
+/// none once the source is gone.
+fn offered_types(
+    conn: &RustConnection,
+    atoms: &Atoms,
+    source: Window,
+) -> Result<Vec<Atom>, ClipboardError> {
+    let types = conn
+        .get_property(
+            false,
+            source,
+            atoms.XdndTypeList,
+            AtomEnum::ATOM,
+            0,
+            u32::MAX,
+        )?
+        .reply()
+        .ok()
+        .and_then(|reply| Some(reply.value32()?.collect()));
+
+    Ok(types.unwrap_or_default())
+}
+
+/// URLs of the data received for a drop, none when the source failed to send it.
+fn read_urls(
+    conn: &RustConnection,
+    atoms: &Atoms,
+    event: &SelectionNotifyEvent,
+    data_type: Option<Atom>,
+) -> Result<Vec<Url>, ClipboardError> {
+    if event.property == NONE {
+        return Ok(Vec::new());
+    }
+
+    let reply = conn
+        .get_property(
+            true,
+            event.requestor,
+            event.property,
+            AtomEnum::ANY,
+            0,
+            u32::MAX,
+        )?
+        .reply()?;
+    let data = String::from_utf8_lossy(&reply.value);
+    let lines = data
+        .lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    // `_NETSCAPE_URL` is a URL followed by the title of the page.
+    let count = if data_type == Some(atoms.NETSCAPE_URL) {
+        1
+    } else {
+        usize::MAX
+    };
+    Ok(lines
+        .take(count)
+        .filter_map(|line| Url::parse(line).ok())
+        .collect())
+}
+
+fn send_message(
+    conn: &RustConnection,
+    destination: Window,
+    type_: Atom,
+    data: [u32; 5],
+) -> Result<(), ClipboardError> {
+    let event = ClientMessageEvent {
+        response_type: xproto::CLIENT_MESSAGE_EVENT,
+        format: 32,
+        sequence: 0,
+        window: destination,
+        type_,
+        data: data.into(),
+    };
+
+    conn.send_event(false, destination, EventMask::NO_EVENT, event)?;
+    conn.flush()?;
+    Ok(())
+}
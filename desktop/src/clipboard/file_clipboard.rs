@@ -0,0 +1,306 @@
+use super::{ClipboardError, CopiedFiles};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use x11rb::atom_manager;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    self, Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode,
+    SelectionNotifyEvent, SelectionRequestEvent, Window, WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+/// Time the owner of the clipboard has to hand over its content.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        INCR,
+        UTF8_STRING,
+        TEXT_PLAIN: b"text/plain;charset=utf-8",
+        URI_LIST: b"text/uri-list",
+        GNOME_COPIED_FILES: b"x-special/gnome-copied-files",
+        KDE_CUT_SELECTION: b"application/x-kde-cutselection",
+        // Property the content of the clipboard is received in.
+        KOOMPI_CLIPBOARD,
+    }
+}
+
+/// Files on the X11 clipboard, in the formats Nautilus, Dolphin and Thunar exchange them.
+pub struct FileClipboard {
+    conn: Arc<RustConnection>,
+    window: Window,
+    atoms: Atoms,
+    files: Arc<Mutex<Option<CopiedFiles>>>,
+}
+
+impl FileClipboard {
+    /// Connect to the X server and answer the requests for the files copied from the desktop
+    /// in the background.
+    pub fn new() -> Result<Self, ClipboardError> {
+        let (conn, window, atoms) = connect()?;
+        let conn = Arc::new(conn);
+        let files = Arc::new(Mutex::new(None));
+
+        {
+            let conn = Arc::clone(&conn);
+            let files = Arc::clone(&files);
+            thread::spawn(move || {
+                if let Err(err) = serve(&conn, &atoms, &files) {
+                    eprintln!("clipboard error: {}", err);
+                }
+            });
+        }
+
+        Ok(Self {
+            conn,
+            window,
+            atoms,
+            files,
+        })
+    }
+
+    /// Put `files` on the clipboard.
+    pub fn set_files(&self, files: CopiedFiles) -> Result<(), ClipboardError> {
+        *self.files.lock().unwrap() = Some(files);
+        self.conn
+            .set_selection_owner(self.window, self.atoms.CLIPBOARD, CURRENT_TIME)?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    /// Files on the clipboard, whichever application put them there.
+    pub fn files(&self) -> Result<Option<CopiedFiles>, ClipboardError> {
+        let owner = self
+            .conn
+            .get_selection_owner(self.atoms.CLIPBOARD)?
+            .reply()?
+            .owner;
+
+        if owner == self.window {
+            return Ok(self.files.lock().unwrap().to_owned());
+        } else if owner == NONE {
+            return Ok(None);
+        }
+
+        // Replies come as events, which the connection of the background thread would take.
+        let (conn, window, atoms) = connect()?;
+        let files = if let Some(data) = convert(&conn, window, &atoms, atoms.GNOME_COPIED_FILES)? {
+            CopiedFiles::from_gnome_copied_files(&data)
+        } else if let Some(data) = convert(&conn, window, &atoms, atoms.URI_LIST)? {
+            // Dolphin tells cut files apart with a separate target.
+            let is_cut = convert(&conn, window, &atoms, atoms.KDE_CUT_SELECTION)?
+                .map_or(false, |data| data.starts_with('1'));
+            CopiedFiles::from_uri_list(&data, is_cut)
+        } else if let Some(data) = convert(&conn, window, &atoms, atoms.UTF8_STRING)? {
+            CopiedFiles::from_text(&data)
+        } else {
+            None
+        };
+
+        conn.destroy_window(window)?;
+        conn.flush()?;
+        Ok(files)
+    }
+
+    /// Text on the clipboard, e.g. a URL copied from a browser.
+    pub fn text(&self) -> Result<Option<String>, ClipboardError> {
+        let owner = self
+            .conn
+            .get_selection_owner(self.atoms.CLIPBOARD)?
+            .reply()?
+            .owner;
+
+        if owner == self.window {
+            return Ok(self
+                .files
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(CopiedFiles::to_text));
+        } else if owner == NONE {
+            return Ok(None);
+        }
+
+        let (conn, window, atoms) = connect()?;
+        let text = convert(&conn, window, &atoms, atoms.UTF8_STRING)?;
+
+        conn.destroy_window(window)?;
+        conn.flush()?;
+        Ok(text)
+    }
+
+    /// Empty the clipboard, e.g. once cut files were moved.
+    pub fn clear(&self) -> Result<(), ClipboardError> {
+        *self.files.lock().unwrap() = None;
+        self.conn
+            .set_selection_owner(NONE, self.atoms.CLIPBOARD, CURRENT_TIME)?;
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+/// Open a connection with a hidden window to own or receive the clipboard.
+fn connect() -> Result<(RustConnection, Window, Atoms), ClipboardError> {
+    let (conn, screen_num) = RustConnection::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let window = conn.generate_id()?;
+    let atoms = Atoms::new(&conn)?;
+
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        COPY_FROM_PARENT,
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )?;
+    let atoms = atoms.reply()?;
+    conn.flush()?;
+
+    Ok((conn, window, atoms))
+}
+
+/// Ask the owner of the clipboard for its content as `target`. Content sent in increments
+/// is left out, a list of files is never that large.
+fn convert(
+    conn: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+    target: Atom,
+) -> Result<Option<String>, ClipboardError> {
+    conn.convert_selection(
+        window,
+        atoms.CLIPBOARD,
+        target,
+        atoms.KOOMPI_CLIPBOARD,
+        CURRENT_TIME,
+    )?;
+    conn.flush()?;
+
+    let deadline = Instant::now() + READ_TIMEOUT;
+    loop {
+        match conn.poll_for_event()? {
+            Some(Event::SelectionNotify(event)) if event.requestor == window => {
+                if event.property == NONE {
+                    return Ok(None);
+                }
+
+                let reply = conn
+                    .get_property(true, window, event.property, AtomEnum::ANY, 0, u32::MAX)?
+                    .reply()?;
+                return Ok(Some(&reply.value)
+                    .filter(|_| reply.type_ != atoms.INCR)
+                    .map(|value| String::from_utf8_lossy(value).into_owned()));
+            }
+            Some(_) => {}
+            None if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            None => return Err(ClipboardError::Timeout),
+        }
+    }
+}
+
+/// Answer the requests for the files on the clipboard as long as the connection lives.
+fn serve(
+    conn: &RustConnection,
+    atoms: &Atoms,
+    files: &Mutex<Option<CopiedFiles>>,
+) -> Result<(), ClipboardError> {
+    loop {
+        match conn.wait_for_event()? {
+            Event::SelectionRequest(request) => {
+                let property = match files.lock().unwrap().as_ref() {
+                    Some(files) => answer(conn, atoms, &request, files)?,
+                    None => NONE,
+                };
+                let event = SelectionNotifyEvent {
+                    response_type: xproto::SELECTION_NOTIFY_EVENT,
+                    sequence: 0,
+                    time: request.time,
+                    requestor: request.requestor,
+                    selection: request.selection,
+                    target: request.target,
+                    property,
+                };
+
+                conn.send_event(false, request.requestor, EventMask::NO_EVENT, &event)?;
+                conn.flush()?;
+            }
+            Event::SelectionClear(event) if event.selection == atoms.CLIPBOARD => {
+                *files.lock().unwrap() = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Write `files` in the requested format to the property of the requestor, returning the
+/// property or `NONE` for a format not offered.
+fn answer(
+    conn: &RustConnection,
+    atoms: &Atoms,
+    request: &SelectionRequestEvent,
+    files: &CopiedFiles,
+) -> Result<Atom, ClipboardError> {
+    // Obsolete clients leave the property out and expect the target to be used instead.
+    let property = if request.property == NONE {
+        request.target
+    } else {
+        request.property
+    };
+    let target = request.target;
+    let string = AtomEnum::STRING.into();
+
+    if target == atoms.TARGETS {
+        let mut targets = vec![
+            atoms.TARGETS,
+            atoms.GNOME_COPIED_FILES,
+            atoms.URI_LIST,
+            atoms.UTF8_STRING,
+            atoms.TEXT_PLAIN,
+            string,
+        ];
+        if files.is_cut {
+            targets.push(atoms.KDE_CUT_SELECTION);
+        }
+        conn.change_property32(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            AtomEnum::ATOM,
+            &targets,
+        )?;
+        return Ok(property);
+    }
+
+    let data = if target == atoms.GNOME_COPIED_FILES {
+        files.to_gnome_copied_files()
+    } else if target == atoms.URI_LIST {
+        files.to_uri_list()
+    } else if target == atoms.UTF8_STRING || target == atoms.TEXT_PLAIN || target == string {
+        files.to_text()
+    } else if target == atoms.KDE_CUT_SELECTION && files.is_cut {
+        String::from("1")
+    } else {
+        return Ok(NONE);
+    };
+    conn.change_property8(
+        PropMode::REPLACE,
+        request.requestor,
+        property,
+        target,
+        data.as_bytes(),
+    )?;
+
+    Ok(property)
+}
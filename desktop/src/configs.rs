@@ -1,11 +1,14 @@
 pub mod background_conf;
 pub mod desktop_item_conf;
+pub mod desktop_layout;
 mod persistent_data;
+pub mod terminal_conf;
 pub mod wallpaper_conf;
 
 use background_conf::BackgroundConf;
 use desktop_item_conf::DesktopItemConf;
 pub use persistent_data::PersistentData;
+use terminal_conf::TerminalConf;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -17,6 +20,8 @@ pub struct DesktopConf {
     pub background_conf: BackgroundConf,
     #[serde(rename = "Desktop_Entry")]
     pub desktop_item_conf: DesktopItemConf,
+    #[serde(rename = "Terminal", default)]
+    pub terminal_conf: TerminalConf,
 }
 
 impl PersistentData for DesktopConf {
@@ -33,4 +38,8 @@ impl DesktopConf {
     pub fn desktop_item_conf(&self) -> &DesktopItemConf {
         &self.desktop_item_conf
     }
+
+    pub fn terminal_conf(&self) -> &TerminalConf {
+        &self.terminal_conf
+    }
 }
@@ -0,0 +1,101 @@
+use super::PersistentData;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const DESKTOP_LAYOUT: &str = "layout.toml";
+
+/// A cell of the desktop grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridCell {
+    pub column: usize,
+    pub row: usize,
+}
+
+/// Grid cells of the desktop items placed by hand, by screen resolution then by file path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DesktopLayout {
+    #[serde(default)]
+    screens: BTreeMap<String, BTreeMap<String, GridCell>>,
+}
+
+impl PersistentData for DesktopLayout {
+    fn relative_path() -> PathBuf {
+        PathBuf::from("desktop").join(DESKTOP_LAYOUT)
+    }
+}
+
+impl DesktopLayout {
+    /// Saved cell of `path`. A resolution without a layout yet uses the one of the nearest
+    /// known resolution.
+    pub fn cell<P: AsRef<Path>>(&self, resolution: (u32, u32), path: P) -> Option<GridCell> {
+        self.screen(resolution)?.get(&path_key(path)).copied()
+    }
+
+    pub fn set_cell<P: AsRef<Path>>(&mut self, resolution: (u32, u32), path: P, cell: GridCell) {
+        self.screen_mut(resolution).insert(path_key(path), cell);
+    }
+
+    /// Keep the cell of a renamed or moved item, for every resolution.
+    pub fn rename<P: AsRef<Path>>(&mut self, from: P, to: P) -> bool {
+        let (from, to) = (path_key(from), path_key(to));
+        let mut is_changed = false;
+
+        for cells in self.screens.values_mut() {
+            if let Some(cell) = cells.remove(&from) {
+                cells.insert(to.clone(), cell);
+                is_changed = true;
+            }
+        }
+        is_changed
+    }
+
+    /// Forget the cells of the items not kept, for every resolution.
+    pub fn retain<F: Fn(&Path) -> bool>(&mut self, is_kept: F) -> bool {
+        let mut is_changed = false;
+
+        for cells in self.screens.values_mut() {
+            let len = cells.len();
+            cells.retain(|path, _| is_kept(Path::new(path)));
+            is_changed |= len != cells.len();
+        }
+        is_changed
+    }
+
+    fn screen(&self, resolution: (u32, u32)) -> Option<&BTreeMap<String, GridCell>> {
+        self.screens.get(&screen_key(resolution)).or_else(|| {
+            self.screens
+                .iter()
+                .filter_map(|(key, cells)| Some((parse_screen_key(key)?, cells)))
+                .min_by_key(|((width, height), _)| {
+                    (i64::from(*width) - i64::from(resolution.0)).abs()
+                        + (i64::from(*height) - i64::from(resolution.1)).abs()
+                })
+                .map(|(_, cells)| cells)
+        })
+    }
+
+    fn screen_mut(&mut self, resolution: (u32, u32)) -> &mut BTreeMap<String, GridCell> {
+        let key = screen_key(resolution);
+
+        if !self.screens.contains_key(&key) {
+            // Start from the nearest layout so the other items stay where they were.
+            let cells = self.screen(resolution).cloned().unwrap_or_default();
+            self.screens.insert(key.clone(), cells);
+        }
+        self.screens.entry(key).or_default()
+    }
+}
+
+fn path_key<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref().to_string_lossy().into_owned()
+}
+
+fn screen_key(resolution: (u32, u32)) -> String {
+    format!("{}x{}", resolution.0, resolution.1)
+}
+
+fn parse_screen_key(key: &str) -> Option<(u32, u32)> {
+    let mut size = key.splitn(2, 'x');
+    Some((size.next()?.parse().ok()?, size.next()?.parse().ok()?))
+}
@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalConf {
+    /// Program name or path of the terminal emulator used for `Terminal=true` entries.
+    pub preferred_terminal: Option<String>,
+}
@@ -9,23 +9,14 @@ pub const FILE: &str = "File";
 /// Desktop Entry Keys
 pub const DESKTOP_ENTRY: &str = "Desktop Entry";
 pub const NAME: &str =		"Name";
-// pub const GENERIC_NAME: &str =	"GenericName";
+pub const GENERIC_NAME: &str =	"GenericName";
 pub const TYPE: &str =		"Type";
-pub const TRY_EXEC: &str =	"TryExec";
-// pub const NO_DISPLAY: &str =	"NoDisplay";
 pub const COMMENT: &str =	"Comment";
-pub const EXEC: &str =		"Exec";
-// pub const ACTIONS: &str =	"Actions";
+pub const URL: &str =		"URL";
 pub const ICON: &str =		"Icon";
-// pub const HIDDEN: &str =	"Hidden";
-// pub const PATH: &str =		"Path";
-pub const TERMINAL: &str =	"Terminal";
-// pub const CATEGORIES: &str =	"Categories";
-// pub const ONLY_SHOW_IN: &str =	"OnlyShowIn";
 
 /// Mimetype
-pub const MIME_FILE: &str = "mimeapps.list";
-pub const DEFAULT_APPS: &str = "Default Applications";
-pub const MIME_INFO_CACHE: &str = "mimeinfo.cache";
-pub const MIME_CACHE: &str = "MIME Cache";
 pub const INODE_DIR: &str = "inode/directory";
+pub const TRASH_URI: &str = "trash:///";
+pub const SCHEME_HANDLER: &str = "x-scheme-handler";
+pub const FILE_SCHEME: &str = "file";
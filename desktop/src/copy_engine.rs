@@ -0,0 +1,540 @@
+mod copy_error;
+
+pub use copy_error::CopyError;
+
+use crate::proxy_message::ProxyMessage;
+use iced_winit::winit::event_loop::EventLoopProxy;
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// Error of a rename across file systems on Linux.
+const EXDEV: i32 = 18;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Copy,
+    Move,
+}
+
+/// Items to copy or move into a folder.
+#[derive(Debug, Clone)]
+pub struct CopyJob {
+    pub sources: Vec<PathBuf>,
+    pub dest: PathBuf,
+    pub transfer: Transfer,
+}
+
+/// What to do with an item whose name is taken in the destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictAction {
+    Skip,
+    Replace,
+    Rename,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolution {
+    pub action: ConflictAction,
+    pub apply_to_all: bool,
+}
+
+/// Progress of the running job sent to the event loop.
+#[derive(Debug, Clone)]
+pub enum CopyMsg {
+    Started {
+        transfer: Transfer,
+        total_bytes: u64,
+    },
+    Progress {
+        current: PathBuf,
+        done_bytes: u64,
+    },
+    // Waits for a `Resolution`.
+    Conflict {
+        source: PathBuf,
+        existing: PathBuf,
+        suggestion: String,
+    },
+    Finished {
+        errors: Vec<String>,
+    },
+}
+
+/// Progress of the running job as last reported.
+#[derive(Debug, Clone)]
+pub struct CopyStatus {
+    pub transfer: Transfer,
+    pub total_bytes: u64,
+    pub done_bytes: u64,
+    pub current: Option<PathBuf>,
+    pub conflict: Option<(PathBuf, PathBuf, String)>,
+    pub started: Instant,
+}
+
+impl CopyStatus {
+    pub fn new(transfer: Transfer, total_bytes: u64) -> Self {
+        Self {
+            transfer,
+            total_bytes,
+            done_bytes: 0,
+            current: None,
+            conflict: None,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self, msg: CopyMsg) {
+        match msg {
+            CopyMsg::Started {
+                transfer,
+                total_bytes,
+            } => *self = Self::new(transfer, total_bytes),
+            CopyMsg::Progress {
+                current,
+                done_bytes,
+            } => {
+                self.current = Some(current);
+                self.done_bytes = done_bytes;
+            }
+            CopyMsg::Conflict {
+                source,
+                existing,
+                suggestion,
+            } => self.conflict = Some((source, existing, suggestion)),
+            CopyMsg::Finished { .. } => self.done_bytes = self.total_bytes,
+        }
+    }
+}
+
+/// Runs the copy jobs one after the other in the background.
+pub struct CopyEngine {
+    jobs: mpsc::Sender<CopyJob>,
+    resolutions: mpsc::Sender<Resolution>,
+    is_cancelled: Arc<AtomicBool>,
+}
+
+impl CopyEngine {
+    pub fn start(proxy: EventLoopProxy<ProxyMessage>) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<CopyJob>();
+        let (resolutions, resolution_receiver) = mpsc::channel();
+        let is_cancelled = Arc::new(AtomicBool::new(false));
+
+        {
+            let is_cancelled = Arc::clone(&is_cancelled);
+            thread::spawn(move || {
+                for job in job_receiver {
+                    // Answers left by a cancelled job.
+                    while resolution_receiver.try_recv().is_ok() {}
+                    is_cancelled.store(false, Ordering::SeqCst);
+
+                    let mut worker = Worker::new(&proxy, &resolution_receiver, &is_cancelled);
+                    let errors = worker.run(job);
+                    if proxy
+                        .send_event(ProxyMessage::Copy(CopyMsg::Finished { errors }))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+
+        Self {
+            jobs,
+            resolutions,
+            is_cancelled,
+        }
+    }
+
+    pub fn queue(&self, job: CopyJob) {
+        let _ = self.jobs.send(job);
+    }
+
+    /// Answer the conflict the running job waits on.
+    pub fn resolve(&self, resolution: Resolution) {
+        let _ = self.resolutions.send(resolution);
+    }
+
+    /// Stop the running job, keeping the items already done.
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::SeqCst);
+        // Wake the job up in case it waits on a conflict.
+        self.resolve(Resolution {
+            action: ConflictAction::Skip,
+            apply_to_all: true,
+        });
+    }
+}
+
+struct Worker<'a> {
+    proxy: &'a EventLoopProxy<ProxyMessage>,
+    resolutions: &'a mpsc::Receiver<Resolution>,
+    is_cancelled: &'a AtomicBool,
+    done_bytes: u64,
+    last_progress: Instant,
+    // Answer given once for every further conflict.
+    action_for_all: Option<ConflictAction>,
+}
+
+impl<'a> Worker<'a> {
+    fn new(
+        proxy: &'a EventLoopProxy<ProxyMessage>,
+        resolutions: &'a mpsc::Receiver<Resolution>,
+        is_cancelled: &'a AtomicBool,
+    ) -> Self {
+        Self {
+            proxy,
+            resolutions,
+            is_cancelled,
+            done_bytes: 0,
+            last_progress: Instant::now(),
+            action_for_all: None,
+        }
+    }
+
+    /// Transfer every source of `job`, returning the errors of the items which failed.
+    fn run(&mut self, job: CopyJob) -> Vec<String> {
+        let total_bytes = job.sources.iter().map(|source| tree_size(source)).sum();
+        let mut errors = Vec::new();
+        self.send(CopyMsg::Started {
+            transfer: job.transfer,
+            total_bytes,
+        });
+
+        for source in &job.sources {
+            match self.transfer(source, &job.dest, job.transfer) {
+                Ok(()) => {}
+                Err(CopyError::Cancelled) => break,
+                Err(err) => errors.push(format!("{}: {}", source.display(), err)),
+            }
+        }
+        errors
+    }
+
+    fn transfer(
+        &mut self,
+        source: &Path,
+        dest: &Path,
+        transfer: Transfer,
+    ) -> Result<(), CopyError> {
+        let file_name = source
+            .file_name()
+            .ok_or_else(|| CopyError::NoFilename(source.display().to_string()))?;
+        let mut target = dest.join(file_name);
+        let size = tree_size(source);
+        // Item given up once its replacement is complete.
+        let mut replaced = None;
+
+        if self.is_cancelled.load(Ordering::SeqCst) {
+            return Err(CopyError::Cancelled);
+        } else if dest.starts_with(source) {
+            return Err(CopyError::IntoItself);
+        }
+
+        if target == source {
+            match transfer {
+                // Moving an item where it already is does nothing.
+                Transfer::Move => {
+                    self.advance(source, size);
+                    return Ok(());
+                }
+                // A copy next to its original gets a free name.
+                Transfer::Copy => target = dest.join(suggest_name(&target, source.is_dir())),
+            }
+        } else if fs::symlink_metadata(&target).is_ok() {
+            let suggestion = suggest_name(&target, source.is_dir());
+            match self.resolve(source, &target, &suggestion)? {
+                ConflictAction::Skip => {
+                    self.advance(source, size);
+                    return Ok(());
+                }
+                ConflictAction::Rename => target = dest.join(suggestion),
+                ConflictAction::Replace if source.starts_with(&target) => {
+                    return Err(CopyError::ReplaceParent)
+                }
+                ConflictAction::Replace => {
+                    replaced = Some(target);
+                    target = temp_path(dest, file_name);
+                }
+            }
+        }
+
+        let is_moved = match transfer {
+            Transfer::Copy => false,
+            Transfer::Move => match fs::rename(source, &target) {
+                Ok(()) => {
+                    self.advance(source, size);
+                    true
+                }
+                Err(err) if err.raw_os_error() == Some(EXDEV) => false,
+                Err(err) => return Err(err.into()),
+            },
+        };
+        if !is_moved {
+            if let Err(err) = self.copy_tree(source, &target) {
+                if replaced.is_some() {
+                    let _ = remove(&target);
+                }
+                return Err(err);
+            }
+        }
+
+        if let Some(replaced) = replaced {
+            if let Err(err) = replace(&target, &replaced) {
+                // The item is left as it was.
+                let _ = if is_moved {
+                    fs::rename(&target, source)
+                } else {
+                    remove(&target)
+                };
+                return Err(err.into());
+            }
+        }
+        if transfer == Transfer::Move && !is_moved {
+            remove(source)?;
+        }
+        Ok(())
+    }
+
+    /// Ask what to do with `source` as `existing` has its name, unless told for all already.
+    fn resolve(
+        &mut self,
+        source: &Path,
+        existing: &Path,
+        suggestion: &str,
+    ) -> Result<ConflictAction, CopyError> {
+        if let Some(action) = self.action_for_all {
+            return Ok(action);
+        }
+
+        self.send(CopyMsg::Conflict {
+            source: source.to_path_buf(),
+            existing: existing.to_path_buf(),
+            suggestion: suggestion.to_string(),
+        });
+        let resolution = self.resolutions.recv().map_err(|_| CopyError::Cancelled)?;
+        if self.is_cancelled.load(Ordering::SeqCst) {
+            return Err(CopyError::Cancelled);
+        } else if resolution.apply_to_all {
+            self.action_for_all = Some(resolution.action);
+        }
+        Ok(resolution.action)
+    }
+
+    /// Copy `source` with everything inside it to `target`, keeping symbolic links as they are.
+    fn copy_tree(&mut self, source: &Path, target: &Path) -> Result<(), CopyError> {
+        let metadata = fs::symlink_metadata(source)?;
+        let file_type = metadata.file_type();
+
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(source)?, target)?;
+            return Ok(());
+        } else if file_type.is_dir() {
+            fs::create_dir(target)?;
+            for entry in fs::read_dir(source)? {
+                let entry = entry?;
+                self.copy_tree(&entry.path(), &target.join(entry.file_name()))?;
+            }
+        } else {
+            let mut writer = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(target)?;
+            // A partial copy is of no use.
+            if let Err(err) = self.copy_file(source, &mut writer) {
+                let _ = fs::remove_file(target);
+                return Err(err);
+            }
+        }
+
+        // Last, so a read-only folder can still be filled.
+        fs::set_permissions(target, metadata.permissions())?;
+        Ok(())
+    }
+
+    fn copy_file(&mut self, source: &Path, writer: &mut File) -> Result<(), CopyError> {
+        let mut reader = File::open(source)?;
+        let mut buffer = vec![0; BUFFER_SIZE];
+
+        loop {
+            if self.is_cancelled.load(Ordering::SeqCst) {
+                return Err(CopyError::Cancelled);
+            }
+
+            let len = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(len) => len,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
+            writer.write_all(&buffer[..len])?;
+            self.advance(source, len as u64);
+        }
+    }
+
+    fn advance(&mut self, current: &Path, bytes: u64) {
+        self.done_bytes += bytes;
+
+        if self.last_progress.elapsed() >= PROGRESS_INTERVAL {
+            self.last_progress = Instant::now();
+            self.send(CopyMsg::Progress {
+                current: current.to_path_buf(),
+                done_bytes: self.done_bytes,
+            });
+        }
+    }
+
+    fn send(&self, msg: CopyMsg) {
+        let _ = self.proxy.send_event(ProxyMessage::Copy(msg));
+    }
+}
+
+/// First free name like `name (2).ext` next to the taken `path`. Folders keep their dots.
+pub fn suggest_name(path: &Path, is_dir: bool) -> String {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (stem, extension) = match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) if !is_dir => (
+            stem.to_string_lossy(),
+            format!(".{}", extension.to_string_lossy()),
+        ),
+        _ => (file_name, String::new()),
+    };
+
+    (2..)
+        .map(|n| format!("{} ({}){}", stem, n, extension))
+        .find(|name| fs::symlink_metadata(path.with_file_name(name)).is_err())
+        .unwrap_or_default()
+}
+
+/// Free hidden name in `dest` for an item until it replaces the one named `file_name`.
+fn temp_path(dest: &Path, file_name: &OsStr) -> PathBuf {
+    (1..)
+        .map(|n| dest.join(format!(".{}.{}.part", file_name.to_string_lossy(), n)))
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .unwrap_or_default()
+}
+
+/// Put the complete item at `new` in place of `old`.
+fn replace(new: &Path, old: &Path) -> io::Result<()> {
+    let is_new_dir = fs::symlink_metadata(new)?.is_dir();
+    let is_old_dir = fs::symlink_metadata(old)
+        .map(|metadata| metadata.is_dir())
+        .unwrap_or(false);
+    // Files are swapped at once, folders cannot be renamed over or onto.
+    if is_new_dir || is_old_dir {
+        remove(old)?;
+    }
+    fs::rename(new, old)
+}
+
+/// Size of the files in `path` and below.
+fn tree_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn suggests_the_first_free_name() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("report.final.pdf");
+        write(&file, "");
+        assert_eq!(suggest_name(&file, false), "report.final (2).pdf");
+        write(&dir.path().join("report.final (2).pdf"), "");
+        assert_eq!(suggest_name(&file, false), "report.final (3).pdf");
+        assert_eq!(
+            suggest_name(&dir.path().join("README"), false),
+            "README (2)"
+        );
+    }
+
+    #[test]
+    fn suggests_names_keeping_the_dots_of_folders() {
+        let dir = TempDir::new().unwrap();
+        let folder = dir.path().join("photos.2021");
+        fs::create_dir(&folder).unwrap();
+        assert_eq!(suggest_name(&folder, true), "photos.2021 (2)");
+    }
+
+    #[test]
+    fn replaces_files_and_folders() {
+        let dir = TempDir::new().unwrap();
+        let old = dir.path().join("notes.txt");
+        let new = temp_path(dir.path(), OsStr::new("notes.txt"));
+        write(&old, "old");
+        write(&new, "new");
+        replace(&new, &old).unwrap();
+        assert_eq!(fs::read_to_string(&old).unwrap(), "new");
+        assert!(!new.exists());
+
+        let old = dir.path().join("project");
+        let new = temp_path(dir.path(), OsStr::new("project"));
+        write(&old.join("old.txt"), "old");
+        write(&new.join("new.txt"), "new");
+        replace(&new, &old).unwrap();
+        assert!(!old.join("old.txt").exists());
+        assert_eq!(fs::read_to_string(old.join("new.txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn keeps_the_replaced_item_without_a_replacement() {
+        let dir = TempDir::new().unwrap();
+        let old = dir.path().join("project");
+        write(&old.join("old.txt"), "old");
+        let new = temp_path(dir.path(), OsStr::new("project"));
+        assert!(replace(&new, &old).is_err());
+        assert_eq!(fs::read_to_string(old.join("old.txt")).unwrap(), "old");
+    }
+
+    #[test]
+    fn picks_free_hidden_temporary_names() {
+        let dir = TempDir::new().unwrap();
+        let first = temp_path(dir.path(), OsStr::new("a.txt"));
+        assert_eq!(first, dir.path().join(".a.txt.1.part"));
+        write(&first, "");
+        assert_eq!(
+            temp_path(dir.path(), OsStr::new("a.txt")),
+            dir.path().join(".a.txt.2.part")
+        );
+    }
+
+    #[test]
+    fn sums_the_size_of_a_tree() {
+        let dir = TempDir::new().unwrap();
+        write(&dir.path().join("a"), "12345");
+        write(&dir.path().join("b/c"), "123");
+        assert_eq!(tree_size(dir.path()), 8);
+        assert_eq!(tree_size(&dir.path().join("a")), 5);
+    }
+}
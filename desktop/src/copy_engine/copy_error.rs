@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CopyError {
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error("invalid filename: {0}")]
+    NoFilename(String),
+    #[error("cannot copy or move a folder into itself")]
+    IntoItself,
+    #[error("cannot replace a folder with an item inside it")]
+    ReplaceParent,
+    #[error("the operation was cancelled")]
+    Cancelled,
+}
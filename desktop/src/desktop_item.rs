@@ -3,22 +3,34 @@ mod desktop_item_error;
 mod desktop_item_status;
 mod desktop_item_type;
 
-use super::constants::{TYPE, DESKTOP_ENTRY, ICON, NAME, COMMENT, DEFAULT_APPS, MIME_FILE, MIME_INFO_CACHE, MIME_CACHE, INODE_DIR};
+use super::constants::{THUMBNAIL_SIZE, TYPE, DESKTOP_ENTRY, ICON, NAME, GENERIC_NAME, COMMENT, INODE_DIR, TRASH_URI, URL, SCHEME_HANDLER, FILE_SCHEME};
 use std::path::{PathBuf, Path};
 use std::str::FromStr;
 use std::convert::From;
-use desktop_item_type::DesktopItemType;
+pub use desktop_item_type::DesktopItemType;
 use desktop_item_status::DesktopItemStatus;
-use desktop_entry::DesktopEntry;
+use freedesktop::desktop_entry::{validate, DesktopEntry, DesktopEntryError, TerminalEmulator};
+pub use freedesktop::desktop_entry::DesktopAction;
 pub use desktop_item_error::DesktopItemError;
-use lazy_static::lazy_static;
-
-const APPS_DIR: &str = "applications";
-lazy_static! {
-    static ref SYS_DIR: PathBuf = PathBuf::from("/usr/share").join(APPS_DIR);
-    static ref SYS_LOCAL_DIR: PathBuf = PathBuf::from("/usr/local/share").join(APPS_DIR);
-    static ref LOCAL_DIR: PathBuf = dirs_next::data_dir().unwrap().join(APPS_DIR);
-    static ref CONF_DIR: PathBuf = dirs_next::config_dir().unwrap();
+use crate::configs::desktop_item_conf::DesktopItemConf;
+use freedesktop::thumbnail::{self, ThumbnailSize};
+use freedesktop::{executable, icon_theme, locale, mime, mime_apps, trash};
+use freedesktop::mime::TEXT_PLAIN;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use subprocess::Exec;
+use url::Url;
+
+/// Types of the files run rather than opened when executable. Position-independent programs
+/// are sniffed as shared libraries.
+const EXECUTABLE_TYPES: [&str; 3] = ["application/x-executable", "application/x-sharedlib", TEXT_PLAIN];
+
+/// What to do with an executable script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptAction {
+    Run,
+    RunInTerminal,
+    Display,
 }
 
 #[derive(Debug, Clone, Default, Eq, Ord, PartialEq, PartialOrd)]
@@ -26,6 +38,7 @@ pub struct DesktopItem {
     pub path: PathBuf,
     pub name: Option<String>,
     pub icon_path: Option<PathBuf>,
+    pub generic_name: Option<String>,
     pub comment: Option<String>,
     pub entry_type: DesktopItemType,
     status: DesktopItemStatus,
@@ -43,54 +56,39 @@ impl DesktopItem {
 
         if file.exists() {
             if file.is_file() {
-                if let Some(extension) = file.extension() {
-                    if extension.eq("desktop") {
-                        let entry = freedesktop_entry_parser::parse_entry(file)?;
-                        let desktop_entry = entry.section(DESKTOP_ENTRY);
-                        let name = desktop_entry.attr(NAME).map(ToString::to_string);
-                        let comment = desktop_entry.attr(COMMENT).map(ToString::to_string);
-                        let mut entry_type =
-                            DesktopItemType::from_str(desktop_entry.attr(TYPE).unwrap_or(""))?;
-                        if let DesktopItemType::APP(entry) = &mut entry_type {
-                            *entry = DesktopEntry::new(&desktop_entry);
-                        }
-                        let icon_path = desktop_entry.attr(ICON).map(|name| {
-                            if Path::new(name).is_absolute() {
-                                PathBuf::from(name)
-                            } else {
-                                let path = PathBuf::from("/usr/share/icons/hicolor/scalable/apps")
-                                    .join(format!("{}.svg", name));
-                                if path.exists() {
-                                    path
-                                } else {
-                                    walkdir::WalkDir::new("/usr/share/icons")
-                                        .follow_links(true)
-                                        .into_iter()
-                                        .filter_map(|e| e.ok())
-                                        .find(|entry| {
-                                            entry.path().file_stem().unwrap().to_str().unwrap()
-                                                == name.split('.').collect::<Vec<&str>>()[0]
-                                        })
-                                        .map(|entry| entry.into_path())
-                                        .unwrap_or(PathBuf::from("/usr/share/icons/koompi.svg"))
-                                }
-                            }
-                        });
-
-                        desktop_item = Self {
-                            path: file.to_path_buf(),
-                            entry_type, name, icon_path, comment, 
-                            ..Self::default()
-                        };
-                    } else {
-                        desktop_item.entry_type = DesktopItemType::FILE;
-                        desktop_item.icon_path = Some(PathBuf::from("/usr/share/icons/koompi.svg"));
+                if file.extension().map(|extension| extension.eq("desktop")).unwrap_or(false) {
+                    let entry_file = freedesktop_entry_parser::parse_entry(file)?;
+                    let desktop_entry = entry_file.section(DESKTOP_ENTRY);
+                    let name = locale::localized_attr(&desktop_entry, NAME).map(ToString::to_string);
+                    let generic_name = locale::localized_attr(&desktop_entry, GENERIC_NAME).map(ToString::to_string);
+                    let comment = locale::localized_attr(&desktop_entry, COMMENT).map(ToString::to_string);
+                    let mut entry_type =
+                        DesktopItemType::from_str(desktop_entry.attr(TYPE).unwrap_or(""))?;
+                    match &mut entry_type {
+                        DesktopItemType::APP(entry) => *entry = DesktopEntry::from_entry(&entry_file, file),
+                        DesktopItemType::LINK(url) => *url = desktop_entry.attr(URL).ok_or(DesktopItemError::NoUrl)?.to_string(),
+                        _ => {}
                     }
-                    res = true;
+                    let icon_path = desktop_entry.attr(ICON).map(|name| {
+                        icon_theme::lookup_icon(name, DesktopItemConf::MAX_ICON_SIZE, 1)
+                            .unwrap_or(PathBuf::from("/usr/share/icons/koompi.svg"))
+                    });
+
+                    desktop_item = Self {
+                        path: file.to_path_buf(),
+                        entry_type, name, generic_name, icon_path, comment,
+                        ..Self::default()
+                    };
+                } else {
+                    desktop_item.entry_type = DesktopItemType::FILE;
+                    // Thumbnails are made in the background, a missing one shows the MIME type icon.
+                    desktop_item.icon_path = thumbnail::find_thumbnail(file, ThumbnailSize::for_pixels(THUMBNAIL_SIZE))
+                        .or_else(|| Some(file_icon_path(file)));
                 }
+                res = true;
             } else if file.is_dir() {
                 desktop_item.entry_type = DesktopItemType::DIR;
-                desktop_item.icon_path = Some(PathBuf::from("/usr/share/icons/koompi.svg"));
+                desktop_item.icon_path = Some(file_icon_path(file));
                 res = true;
             } 
 
@@ -104,56 +102,259 @@ impl DesktopItem {
         }
     }
 
-    pub fn handle_exec(&self) -> Result<(), DesktopItemError> {
+    /// The trash, with an icon showing whether it is empty.
+    pub fn trash() -> Self {
+        let icon_name = if trash::is_empty() { "user-trash" } else { "user-trash-full" };
+
+        Self {
+            path: trash::home_trash_dir(),
+            name: Some(String::from("Trash")),
+            icon_path: icon_theme::lookup_icon(icon_name, DesktopItemConf::MAX_ICON_SIZE, 1)
+                .or_else(|| Some(file_icon_path(&trash::home_trash_dir()))),
+            entry_type: DesktopItemType::TRASH,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the item is a launcher, named by its `Name` key rather than its file name.
+    pub fn is_desktop_entry(&self) -> bool {
+        self.path.is_file() && self.path.extension().map(|extension| extension == "desktop").unwrap_or(false)
+    }
+
+    pub fn set_entry_name(&self, name: &str) -> Result<(), DesktopItemError> {
+        desktop_entry::set_name(&self.path, name)
+    }
+
+    /// Write a launcher at `path` opening `url`.
+    pub fn create_link<P: AsRef<Path>>(path: P, name: &str, url: &Url) -> Result<(), DesktopItemError> {
+        let icon = match url.scheme() {
+            "http" | "https" => "text-html",
+            "mailto" => "mail-message-new",
+            _ => "emblem-symbolic-link",
+        };
+        desktop_entry::write_link(path, name, icon, url.as_str())
+    }
+
+    /// Open the item. An application is launched with `files`, e.g. the files dropped on it.
+    pub fn handle_exec(&self, files: &[PathBuf], preferred_terminal: Option<&str>) -> Result<(), DesktopItemError> {
         match &self.entry_type {
-            DesktopItemType::APP(entry) => entry.handle_exec(None),
-            DesktopItemType::DIR | DesktopItemType::FILE => {
-                let mut res = false;
-                let mime_type = mime_guess::from_path(self.path.to_path_buf());
-                let mime_type = mime_type.first_raw().unwrap_or(INODE_DIR);
-
-                let mut config = configparser::ini::Ini::new();
-                let _ = config.load(CONF_DIR.join(MIME_FILE).to_str().unwrap());
-                let apps = if let Some(apps) = config.get(DEFAULT_APPS, mime_type) {
-                    Some(apps.to_string())
-                } else {
-                    let _ = config.load(LOCAL_DIR.join(MIME_INFO_CACHE).to_str().unwrap());
-                    if let Some(apps) = config.get(MIME_CACHE, mime_type) {
-                        Some(apps.to_string())
-                    } else {
-                        let _ = config.load(SYS_DIR.join(MIME_INFO_CACHE).to_str().unwrap());
-                        config.get(MIME_CACHE, mime_type)
-                    }
-                }; 
-                
-                if let Some(apps) = apps {
-                    let mut splitted_apps = apps.split(';');
-                    while let Some(app) = splitted_apps.next() {
-                        let mut app_path = LOCAL_DIR.join(app);
-                        if !app_path.exists() {
-                            app_path = SYS_LOCAL_DIR.join(app);
-                            if !app_path.exists() {
-                                app_path = SYS_DIR.join(app);
-                            }
-                        }
-                        
-                        let entry = freedesktop_entry_parser::parse_entry(app_path)?;
-                        let desktop_entry = entry.section(DESKTOP_ENTRY);
-                        let entry = DesktopEntry::new(&desktop_entry);
-                        if let Ok(()) = entry.handle_exec(self.path.to_str()) {
-                            res = true;
-                            break;
-                        }
-                    }
-                }
-                
-                if res {
-                    Ok(())
-                } else {
-                    Err(DesktopItemError::NoExecString)
-                }
+            DesktopItemType::APP(entry) => {
+                self.check_launchable()?;
+                let files: Vec<String> = files.iter().map(|file| file.to_string_lossy().into_owned()).collect();
+                Ok(entry.handle_exec(&files, preferred_terminal)?)
+            },
+            // Scripts may as well be meant to be read, the user chooses.
+            DesktopItemType::FILE if self.is_executable() => if mime::is_subclass(&self.mime_type(), TEXT_PLAIN) {
+                Err(DesktopItemError::ExecutableScript(self.display_name()))
+            } else {
+                self.run(false, preferred_terminal)
+            },
+            DesktopItemType::DIR | DesktopItemType::FILE | DesktopItemType::TRASH => {
+                let mime_type = self.mime_type();
+                // The trash is opened in the file manager, which knows the trash URI.
+                let target = match self.entry_type {
+                    DesktopItemType::TRASH => TRASH_URI.to_string(),
+                    _ => self.path.to_string_lossy().into_owned(),
+                };
+
+                open_with_default(&mime_type, &target, preferred_terminal)
             },
+            DesktopItemType::LINK(url) => open_url(url, preferred_terminal),
             _ => Err(DesktopItemError::InvalidType)
         }
     }
+
+    /// Run an executable script, in a terminal emulator or not, or open it with the application
+    /// for its type.
+    pub fn open_script(&self, action: ScriptAction, preferred_terminal: Option<&str>) -> Result<(), DesktopItemError> {
+        match action {
+            ScriptAction::Run => self.run(false, preferred_terminal),
+            ScriptAction::RunInTerminal => self.run(true, preferred_terminal),
+            ScriptAction::Display => open_with_default(&self.mime_type(), &self.path.to_string_lossy(), preferred_terminal),
+        }
+    }
+
+    /// Run the item as a program from the folder it is in.
+    fn run(&self, in_terminal: bool, preferred_terminal: Option<&str>) -> Result<(), DesktopItemError> {
+        let program = vec![self.path.to_string_lossy().into_owned()];
+        let mut args = if in_terminal {
+            TerminalEmulator::detect(preferred_terminal).ok_or(DesktopItemError::NoTerminal)?.wrap(program)
+        } else {
+            program
+        }.into_iter();
+        let mut cmd = Exec::cmd(args.next().ok_or(DesktopItemError::NoExecString)?).args(&args.collect::<Vec<String>>());
+        if let Some(dir) = self.path.parent() {
+            cmd = cmd.cwd(dir);
+        }
+        let _ = cmd.detached().popen()?;
+        Ok(())
+    }
+
+    /// Whether the item is a file that can be run: a program or a script with the executable
+    /// bit set.
+    pub fn is_executable(&self) -> bool {
+        self.entry_type == DesktopItemType::FILE && executable::is_executable(&self.path) && {
+            let mime_type = self.mime_type();
+            EXECUTABLE_TYPES.iter().any(|executable| mime::is_subclass(&mime_type, executable))
+        }
+    }
+
+    /// Launchers are only trusted to run their command once they are made executable, so that a
+    /// downloaded one cannot pass for a file and run anything.
+    pub fn is_trusted(&self) -> bool {
+        executable::is_executable(&self.path)
+    }
+
+    /// Trust a launcher by making it executable by whoever can read it.
+    pub fn mark_trusted(&self) -> Result<(), DesktopItemError> {
+        let mut permissions = fs::metadata(&self.path)?.permissions();
+        permissions.set_mode(permissions.mode() | (permissions.mode() & 0o444) >> 2);
+        Ok(fs::set_permissions(&self.path, permissions)?)
+    }
+
+    /// Menus leave out entries not meant to be displayed, but the file is still on the desktop.
+    /// Only a deleted entry or a missing program stop the launch, then an untrusted launcher.
+    fn check_launchable(&self) -> Result<(), DesktopItemError> {
+        let entry_file = freedesktop_entry_parser::parse_entry(&self.path)?;
+        match validate(&entry_file.section(DESKTOP_ENTRY)) {
+            Err(err @ DesktopEntryError::Hidden) | Err(err @ DesktopEntryError::TryExecNotFound(_)) => Err(err.into()),
+            _ if !self.is_trusted() => Err(DesktopItemError::Untrusted(self.display_name())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Name shown for the item, its file name when it has no other.
+    pub fn display_name(&self) -> String {
+        self.name.to_owned().unwrap_or_else(|| self.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default())
+    }
+
+    /// MIME type the item is opened as.
+    pub fn mime_type(&self) -> String {
+        match self.entry_type {
+            DesktopItemType::DIR | DesktopItemType::TRASH => INODE_DIR.to_string(),
+            _ => mime::guess_mime_type(&self.path),
+        }
+    }
+
+    /// Name and desktop file of every application able to open the item, the default ones first.
+    pub fn open_with_apps(&self) -> Vec<(String, PathBuf)> {
+        mime_apps::associated_apps(&self.mime_type()).iter().filter_map(|app| {
+            let app_path = mime_apps::app_path(app)?;
+            let entry = freedesktop_entry_parser::parse_entry(&app_path).ok()?;
+            let desktop_entry = entry.section(DESKTOP_ENTRY);
+            // Applications left out of menus still open files.
+            match validate(&desktop_entry) {
+                Ok(()) | Err(DesktopEntryError::NoDisplay) => {},
+                Err(_) => return None,
+            }
+            let name = locale::localized_attr(&desktop_entry, NAME)?.to_string();
+            Some((name, app_path))
+        }).collect()
+    }
+
+    /// Quick actions of a launcher.
+    pub fn actions(&self) -> &[DesktopAction] {
+        match &self.entry_type {
+            DesktopItemType::APP(entry) => entry.actions(),
+            _ => &[],
+        }
+    }
+
+    /// Launch the quick action `id` of a launcher.
+    pub fn launch_action(&self, id: &str, preferred_terminal: Option<&str>) -> Result<(), DesktopItemError> {
+        match &self.entry_type {
+            DesktopItemType::APP(entry) => {
+                self.check_launchable()?;
+                Ok(entry.launch_action(id, &[], preferred_terminal)?)
+            },
+            _ => Err(DesktopItemError::InvalidType),
+        }
+    }
+}
+
+/// Open `files` with the application of the desktop file at `app_path`.
+pub fn open_with<P: AsRef<Path>>(app_path: P, files: &[PathBuf], preferred_terminal: Option<&str>) -> Result<(), DesktopItemError> {
+    let entry = freedesktop_entry_parser::parse_entry(app_path.as_ref())?;
+    let entry = DesktopEntry::new(&entry.section(DESKTOP_ENTRY), app_path.as_ref());
+    let files: Vec<String> = files.iter().map(|file| file.to_string_lossy().into_owned()).collect();
+    Ok(entry.handle_exec(&files, preferred_terminal)?)
+}
+
+/// Open `url` with the default application for its scheme, or for the type of the file a
+/// `file://` URL points to.
+fn open_url(url: &str, preferred_terminal: Option<&str>) -> Result<(), DesktopItemError> {
+    let url = Url::parse(url).map_err(|_| DesktopItemError::InvalidUrl(url.to_string()))?;
+
+    match url.to_file_path() {
+        Ok(path) if url.scheme() == FILE_SCHEME => {
+            open_with_default(&mime::guess_mime_type(&path), &path.to_string_lossy(), preferred_terminal)
+        }
+        _ => open_with_default(&format!("{}/{}", SCHEME_HANDLER, url.scheme()), url.as_str(), preferred_terminal),
+    }
+}
+
+/// Open `target` with the default application for `mime_type`, or the next associated one
+/// able to. The error of the last application tried is returned when none is.
+fn open_with_default(mime_type: &str, target: &str, preferred_terminal: Option<&str>) -> Result<(), DesktopItemError> {
+    let mut last_error = DesktopItemError::NoExecString;
+    for app_path in mime_apps::associated_apps(mime_type).iter().filter_map(|app| mime_apps::app_path(app)) {
+        let entry = match freedesktop_entry_parser::parse_entry(&app_path) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let entry = DesktopEntry::new(&entry.section(DESKTOP_ENTRY), &app_path);
+        match entry.handle_exec(&[target.to_owned()], preferred_terminal) {
+            Ok(()) => return Ok(()),
+            Err(err) => last_error = err.into(),
+        }
+    }
+    Err(last_error)
+}
+
+/// Make the application of the desktop file at `app_path` the default one for `mime_type`.
+pub fn set_default_app<P: AsRef<Path>>(mime_type: &str, app_path: P) -> Result<(), DesktopItemError> {
+    let app_path = app_path.as_ref();
+    let app = mime_apps::desktop_file_id(app_path)
+        .ok_or_else(|| DesktopItemError::NoFilename(app_path.to_string_lossy().into_owned()))?;
+    Ok(mime_apps::set_default_app(mime_type, &app)?)
+}
+
+/// Icon of a regular file or folder resolved from its MIME type. Special XDG folders get
+/// their dedicated icon.
+fn file_icon_path(file: &Path) -> PathBuf {
+    let icon_names = if file.is_dir() {
+        special_folder_icon(file).into_iter().chain(Some("folder")).map(ToString::to_string).collect()
+    } else {
+        let mut icon_names = mime::icon_names(&mime::guess_mime_type(file));
+        icon_names.push(String::from("unknown"));
+        icon_names
+    };
+
+    icon_names
+        .iter()
+        .find_map(|icon_name| icon_theme::lookup_icon(icon_name, DesktopItemConf::MAX_ICON_SIZE, 1))
+        .unwrap_or(PathBuf::from("/usr/share/icons/koompi.svg"))
+}
+
+fn special_folder_icon(dir: &Path) -> Option<&'static str> {
+    let special_dirs = [
+        (dirs_next::home_dir(), "user-home"),
+        (dirs_next::desktop_dir(), "user-desktop"),
+        (dirs_next::document_dir(), "folder-documents"),
+        (dirs_next::download_dir(), "folder-download"),
+        (dirs_next::audio_dir(), "folder-music"),
+        (dirs_next::picture_dir(), "folder-pictures"),
+        (dirs_next::public_dir(), "folder-publicshare"),
+        (dirs_next::template_dir(), "folder-templates"),
+        (dirs_next::video_dir(), "folder-videos"),
+    ];
+    // Compare resolved paths, a special folder is often linked on the desktop.
+    let dir = dir.canonicalize().ok()?;
+
+    special_dirs
+        .iter()
+        .find(|(special_dir, _)| {
+            special_dir.as_ref().and_then(|special_dir| special_dir.canonicalize().ok()).as_ref() == Some(&dir)
+        })
+        .map(|(_, icon_name)| *icon_name)
 }
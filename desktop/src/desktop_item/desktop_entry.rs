@@ -1,54 +1,68 @@
-use crate::constants::{EXEC, TRY_EXEC, TERMINAL};
+use crate::constants::{DESKTOP_ENTRY, NAME, ICON, TYPE, LINK, URL};
 use super::desktop_item_error::DesktopItemError;
-use subprocess::Exec;
-use freedesktop_entry_parser::AttrSelector;
+use freedesktop::locale;
+use std::fs;
+use std::path::Path;
 
-#[derive(Debug, Clone, Default, Eq, Ord, PartialEq, PartialOrd)]
-pub struct DesktopEntry {
-    try_exec: Option<String>,
-    exec: Option<String>,
-    term: bool,
-}
-
-impl DesktopEntry {
-    pub fn new(desktop_entry: &AttrSelector<&str>) -> Self {
-        let try_exec = desktop_entry.attr(TRY_EXEC).map(ToString::to_string);
-        let exec = desktop_entry.attr(EXEC).map(ToString::to_string);
-        let term = desktop_entry.attr(TERMINAL).map(|term| term.parse::<bool>().unwrap_or(false)).unwrap_or(false);
+/// Write `name` as the `Name` key of the desktop entry file at `path`, keeping every other line.
+/// A name already translated to the current locale gets the translation replaced.
+pub fn set_name<P: AsRef<Path>>(path: P, name: &str) -> Result<(), DesktopItemError> {
+    let content = fs::read_to_string(path.as_ref())?;
+    // The name shown is the one translated to the current locale, when there is one.
+    let entry = freedesktop_entry_parser::parse_entry(path.as_ref())?;
+    let key = locale::locales().iter()
+        .find(|locale| entry.section(DESKTOP_ENTRY).attr_with_param(NAME, locale).is_some())
+        .map_or_else(|| NAME.to_string(), |locale| format!("{}[{}]", NAME, locale));
+    let name_line = format!("{}={}", key, escape_value(name));
+    let header = format!("[{}]", DESKTOP_ENTRY);
+    let mut lines: Vec<String> = Vec::new();
+    let mut is_in_entry = false;
+    let mut is_set = false;
 
-        Self {
-            try_exec, exec, term
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            is_in_entry = trimmed == header;
+        } else if is_in_entry && !is_set && trimmed.splitn(2, '=').next().map(str::trim) == Some(key.as_str()) {
+            is_set = true;
+            lines.push(name_line.to_owned());
+            continue;
         }
+        lines.push(line.to_string());
     }
+    if !is_set {
+        let header_idx = lines.iter().position(|line| line.trim() == header).ok_or(DesktopItemError::InvalidType)?;
+        lines.insert(header_idx + 1, name_line);
+    }
+
+    fs::write(path.as_ref(), lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Write a `Type=Link` desktop entry named `name` opening `url` to `path`.
+pub fn write_link<P: AsRef<Path>>(path: P, name: &str, icon: &str, url: &str) -> Result<(), DesktopItemError> {
+    let content = format!(
+        "[{}]\n{}={}\n{}={}\n{}={}\n{}={}\n",
+        DESKTOP_ENTRY, TYPE, LINK, NAME, escape_value(name), ICON, icon, URL, escape_value(url)
+    );
 
-    pub fn handle_exec(&self, arg: Option<&str>) -> Result<(), DesktopItemError> {
-        let exec_str = if let Some(exec) = &self.try_exec {
-            Some(exec)
-        } else if let Some(exec) = &self.exec {
-            Some(exec)
-        } else {
-            None
-        };
+    fs::write(path, content)?;
+    Ok(())
+}
 
-        if let Some(exec_str) = exec_str {
-            let re = regex::Regex::new("%.").unwrap();
-            let formatted_exec_str = re.replace_all(exec_str, "").to_string();
-            let mut splitted_exec_str = formatted_exec_str.trim().split_whitespace();
-            let mut cmd = Exec::cmd(splitted_exec_str.next().unwrap());
-            while let Some(arg) = splitted_exec_str.next() {
-                cmd = cmd.arg(arg);
-            }
-            if let Some(arg) = arg {
-                cmd = cmd.arg(arg);
-            }
-            if self.term {
-                cmd = cmd.arg("&");
-            }
-            let _ = cmd.detached().join()?;
+/// Escape a string value as required by the desktop entry specification.
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
 
-            Ok(())
-        } else {
-            Err(DesktopItemError::NoExecString)
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            ' ' if i == 0 => escaped.push_str("\\s"),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
         }
     }
-}
\ No newline at end of file
+    escaped
+}
@@ -1,3 +1,4 @@
+use freedesktop::desktop_entry::DesktopEntryError;
 use freedesktop_entry_parser::errors::ParseError;
 use subprocess::PopenError;
 use thiserror::Error;
@@ -7,14 +8,24 @@ pub enum DesktopItemError {
     ParseError(#[from] ParseError),
     #[error(transparent)]
     PopenError(#[from] PopenError),
+    #[error(transparent)]
+    DesktopEntryError(#[from] DesktopEntryError),
     #[error("invalid filename: {0}")]
     NoFilename(String),
     #[error("cannot launch due to no execute string")]
     NoExecString,
-    // #[error("cannot launch due to bad execute string")]
-    // BadExecString,
+    #[error("cannot find a terminal emulator to run the application")]
+    NoTerminal,
+    #[error("desktop entry of type Link has no URL")]
+    NoUrl,
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
     #[error("invalid type of desktop item", )]
     InvalidType,
+    #[error("{0} is not marked as trusted")]
+    Untrusted(String),
+    #[error("{0} is an executable script")]
+    ExecutableScript(String),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
 }
@@ -2,14 +2,17 @@ use std::str::FromStr;
 use std::fmt::{self, Display, Formatter};
 use crate::constants::{APP, DIR, LINK, FILE};
 use super::desktop_item_error::DesktopItemError;
-use super::desktop_entry::DesktopEntry;
+use freedesktop::desktop_entry::DesktopEntry;
 
 #[derive(Debug, Clone, Eq, Ord, PartialEq, PartialOrd)]
 pub enum DesktopItemType {
     APP(DesktopEntry),
     DIR,
     FILE,
-    LINK,
+    // The URL a `Type=Link` entry opens.
+    LINK(String),
+    // The trash, shown on the desktop without being a file in the desktop directory.
+    TRASH,
     NULL,
 }
 
@@ -28,7 +31,7 @@ impl FromStr for DesktopItemType {
         } else {
             match s {
                 APP => Ok(DesktopItemType::APP(DesktopEntry::default())),
-                LINK => Ok(DesktopItemType::LINK),
+                LINK => Ok(DesktopItemType::LINK(String::new())),
                 DIR => Ok(DesktopItemType::DIR),
                 FILE => Ok(DesktopItemType::FILE),
                 _ => Err(DesktopItemError::InvalidType)
@@ -41,7 +44,7 @@ impl Display for DesktopItemType {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", match self {
             DesktopItemType::APP(_) => APP,
-            DesktopItemType::LINK => LINK,
+            DesktopItemType::LINK(_) => LINK,
             DesktopItemType::DIR => DIR,
             DesktopItemType::FILE => FILE,
             _ => ""
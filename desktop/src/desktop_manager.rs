@@ -1,33 +1,51 @@
 use super::background::WallpaperItem;
+use super::clipboard::{CopiedFiles, FileClipboard};
 use super::configs::DesktopConf;
-use super::desktop_item::DesktopItem;
+use super::constants::THUMBNAIL_SIZE;
+use super::copy_engine::{suggest_name, CopyEngine, CopyJob, Resolution, Transfer};
+use super::desktop_item::{DesktopItem, DesktopItemType};
 use super::errors::DesktopError;
+use super::proxy_message::ProxyMessage;
 use crate::configs::{desktop_item_conf::Sorting, PersistentData};
+use freedesktop::thumbnail::{self, ThumbnailSize};
+use freedesktop::trash::{self, TrashItem};
+use iced_winit::winit::event_loop::EventLoopProxy;
 use lazy_static::lazy_static;
-use std::fs;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs::{self, DirBuilder};
+use std::os::unix::fs::DirBuilderExt;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use url::Url;
 
 const WALLPAPERS_DIR: &str = "wallpapers";
+const WATCHER_DELAY: Duration = Duration::from_millis(300);
 lazy_static! {
     static ref SYS_DIR: PathBuf = PathBuf::from("/usr/share").join(WALLPAPERS_DIR);
     static ref LOCAL_DIR: PathBuf = dirs_next::data_local_dir().unwrap().join(WALLPAPERS_DIR);
     static ref DESK_DIR: PathBuf =
         dirs_next::desktop_dir().unwrap_or(dirs_next::home_dir().unwrap().join("Desktop"));
+    static ref TRASH_FILES_DIR: PathBuf = trash::home_trash_dir().join("files");
 }
 
 pub struct DesktopManager {
     desktop_items: Vec<DesktopItem>,
     wallpaper_items: Vec<WallpaperItem>,
     conf: DesktopConf,
+    watcher: Option<RecommendedWatcher>,
+    thumbnail_sender: Option<mpsc::Sender<PathBuf>>,
+    // Items of the last move to trash, for undoing it.
+    last_trashed: Vec<TrashItem>,
+    clipboard: Option<FileClipboard>,
+    copy_engine: Option<CopyEngine>,
 }
 
 impl DesktopManager {
     pub fn new() -> Result<Self, DesktopError> {
         let conf = DesktopConf::load()?;
-        let desktop_items: Vec<DesktopItem> = DESK_DIR
-            .read_dir()?
-            .filter_map(|entry| DesktopItem::new(entry.unwrap().path()).ok())
-            .collect();
+        let desktop_items = Self::read_desktop_items()?;
         let mut wallpaper_items: Vec<WallpaperItem> = Vec::new();
         if SYS_DIR.exists() && SYS_DIR.is_dir() {
             let sys_wallpaper_items = SYS_DIR
@@ -42,11 +60,24 @@ impl DesktopManager {
             wallpaper_items.extend(local_wallpaper_items);
         }
         wallpaper_items.sort();
+        // The desktop works without a clipboard, only copy and paste are lost.
+        let clipboard = match FileClipboard::new() {
+            Ok(clipboard) => Some(clipboard),
+            Err(err) => {
+                eprintln!("{:?}", err);
+                None
+            }
+        };
 
         let mut desktop_mn = Self {
             desktop_items,
             wallpaper_items,
             conf,
+            watcher: None,
+            thumbnail_sender: None,
+            last_trashed: Vec::new(),
+            clipboard,
+            copy_engine: None,
         };
         desktop_mn.sort_desktop_items(
             desktop_mn.conf.desktop_item_conf.sorting,
@@ -55,7 +86,8 @@ impl DesktopManager {
         Ok(desktop_mn)
     }
 
-    pub fn create_new_folder(&mut self) -> Result<Vec<DesktopItem>, DesktopError> {
+    /// Create an untitled folder on the desktop, returning its path.
+    pub fn create_new_folder(&mut self) -> Result<PathBuf, DesktopError> {
         let prefix_name = "untitled folder";
         let num_untitled_folders = DESK_DIR
             .read_dir()?
@@ -76,9 +108,322 @@ impl DesktopManager {
         let full_path = DESK_DIR.join(&new_folder);
 
         fs::create_dir(full_path.to_path_buf())?;
-        self.desktop_items.push(DesktopItem::new(full_path)?);
+        self.insert_desktop_item(DesktopItem::new(&full_path)?);
+
+        Ok(full_path)
+    }
+
+    /// Rename the item at `path`. A launcher gets a new `Name` key, any other item a new file
+    /// name which must not be taken already. The desktop items are updated by the watcher.
+    pub fn rename_item<P: AsRef<Path>>(&self, path: P, name: &str) -> Result<(), DesktopError> {
+        let path = path.as_ref();
+        let name = name.trim();
+        let desktop_item = self
+            .desktop_items
+            .iter()
+            .find(|item| item.path == path)
+            .ok_or_else(|| DesktopError::PathIsNotAFile(path.display().to_string()))?;
+
+        if name.is_empty() {
+            return Err(DesktopError::InvalidName);
+        } else if desktop_item.is_desktop_entry() {
+            return Ok(desktop_item.set_entry_name(name)?);
+        } else if name.contains('/') || name == "." || name == ".." || is_trash(path) {
+            return Err(DesktopError::InvalidName);
+        }
+
+        let dest = path.with_file_name(name);
+        if dest == path {
+            Ok(())
+        } else if fs::symlink_metadata(&dest).is_ok() {
+            Err(DesktopError::NameTaken(
+                name.to_string(),
+                suggest_name(&dest, path.is_dir()),
+            ))
+        } else {
+            Ok(fs::rename(path, dest)?)
+        }
+    }
 
-        Ok(self.desktop_items.to_owned())
+    /// Start watching the desktop directory and the trash. Every change is posted back to
+    /// the event loop through `proxy` and must be applied with the matching `on_*` method.
+    pub fn watch_desktop_dir(
+        &mut self,
+        proxy: EventLoopProxy<ProxyMessage>,
+    ) -> Result<(), DesktopError> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::watcher(tx, WATCHER_DELAY)?;
+        watcher.watch(DESK_DIR.as_path(), RecursiveMode::NonRecursive)?;
+        // The trash is only watched for its icon, the desktop works without it.
+        let is_trash_watched = DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(TRASH_FILES_DIR.as_path())
+            .is_ok()
+            && watcher
+                .watch(TRASH_FILES_DIR.as_path(), RecursiveMode::NonRecursive)
+                .is_ok();
+        if !is_trash_watched {
+            eprintln!("cannot watch the trash {:?}", TRASH_FILES_DIR.as_path());
+        }
+
+        // The receiver is closed once the watcher is dropped, which ends this thread.
+        thread::spawn(move || {
+            for event in rx {
+                let msg = match event {
+                    // Only the number of trashed files matters, for the trash icon.
+                    DebouncedEvent::Create(path) | DebouncedEvent::Remove(path)
+                        if is_in_trash(&path) =>
+                    {
+                        ProxyMessage::TrashChanged
+                    }
+                    DebouncedEvent::Write(path) | DebouncedEvent::Chmod(path)
+                        if is_in_trash(&path) =>
+                    {
+                        continue
+                    }
+                    DebouncedEvent::Create(path) => ProxyMessage::ItemCreated(path),
+                    DebouncedEvent::Remove(path) => ProxyMessage::ItemRemoved(path),
+                    DebouncedEvent::Rename(from, to) => ProxyMessage::ItemRenamed(from, to),
+                    DebouncedEvent::Write(path) | DebouncedEvent::Chmod(path) => {
+                        ProxyMessage::ItemModified(path)
+                    }
+                    DebouncedEvent::Rescan => ProxyMessage::ItemsRescanned,
+                    DebouncedEvent::Error(err, path) => {
+                        eprintln!("desktop watcher error {:?}: {}", path, err);
+                        continue;
+                    }
+                    _ => continue,
+                };
+
+                if proxy.send_event(msg).is_err() {
+                    break;
+                }
+            }
+        });
+        self.watcher = Some(watcher);
+
+        Ok(())
+    }
+
+    /// Start making the missing thumbnails of the desktop items in the background. Each new
+    /// thumbnail is posted back through `proxy` and the item reloaded with `on_item_modified`.
+    pub fn start_thumbnailer(&mut self, proxy: EventLoopProxy<ProxyMessage>) {
+        let (tx, rx) = mpsc::channel::<PathBuf>();
+        let size = ThumbnailSize::for_pixels(THUMBNAIL_SIZE);
+
+        thread::spawn(move || {
+            for path in rx {
+                let is_missing = thumbnail::find_thumbnail(&path, size).is_none()
+                    && !thumbnail::has_failed(&path)
+                    && thumbnail::can_thumbnail(&path);
+
+                if is_missing {
+                    match thumbnail::generate_thumbnail(&path, size) {
+                        Ok(_) => {
+                            if proxy
+                                .send_event(ProxyMessage::ThumbnailReady(path))
+                                .is_err()
+                            {
+                                break;
+                            }
+                        }
+                        Err(err) => eprintln!("thumbnail error {:?}: {}", path, err),
+                    }
+                }
+            }
+        });
+        self.thumbnail_sender = Some(tx);
+        self.desktop_items
+            .iter()
+            .for_each(|item| self.request_thumbnail(item));
+    }
+
+    pub fn start_copy_engine(&mut self, proxy: EventLoopProxy<ProxyMessage>) {
+        self.copy_engine = Some(CopyEngine::start(proxy));
+    }
+
+    /// Put the items at `paths` on the clipboard, to be moved when `is_cut`.
+    pub fn copy_to_clipboard<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        is_cut: bool,
+    ) -> Result<(), DesktopError> {
+        let clipboard = self.clipboard.as_ref().ok_or(DesktopError::NoClipboard)?;
+        let paths = paths
+            .iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+
+        Ok(clipboard.set_files(CopiedFiles::new(paths, is_cut))?)
+    }
+
+    /// Copy or move the files on the clipboard to the desktop in the background.
+    pub fn paste(&self) -> Result<(), DesktopError> {
+        let (clipboard, copy_engine) = match (&self.clipboard, &self.copy_engine) {
+            (Some(clipboard), Some(copy_engine)) => (clipboard, copy_engine),
+            _ => return Err(DesktopError::NoClipboard),
+        };
+
+        if let Some(files) = clipboard.files()? {
+            copy_engine.queue(CopyJob {
+                sources: files.paths,
+                dest: DESK_DIR.to_path_buf(),
+                transfer: if files.is_cut {
+                    Transfer::Move
+                } else {
+                    Transfer::Copy
+                },
+            });
+            // Cut files are only pasted once.
+            if files.is_cut {
+                clipboard.clear()?;
+            }
+        } else if let Some(url) = clipboard.text()?.as_deref().and_then(link_url) {
+            self.create_link(&url)?;
+        }
+        Ok(())
+    }
+
+    /// Create a launcher on the desktop opening `url`, named after its host.
+    pub fn create_link(&self, url: &Url) -> Result<PathBuf, DesktopError> {
+        let name = url
+            .host_str()
+            .map(|host| host.trim_start_matches("www.").to_string())
+            .unwrap_or_else(|| url.path().to_string());
+        let file_name = name.replace('/', "-");
+        let mut path = DESK_DIR.join(format!("{}.desktop", file_name));
+        if fs::symlink_metadata(&path).is_ok() {
+            path = DESK_DIR.join(suggest_name(&path, false));
+        }
+
+        DesktopItem::create_link(&path, &name, url)?;
+        Ok(path)
+    }
+
+    /// Create a link on the desktop for each of the dropped `urls` which is not a file.
+    pub fn create_links(&self, urls: &[Url]) -> Result<(), DesktopError> {
+        urls.iter()
+            .filter(|url| is_link_url(url))
+            .try_for_each(|url| self.create_link(url).map(|_| ()))
+    }
+
+    pub fn resolve_conflict(&self, resolution: Resolution) {
+        if let Some(copy_engine) = &self.copy_engine {
+            copy_engine.resolve(resolution);
+        }
+    }
+
+    pub fn cancel_copy(&self) {
+        if let Some(copy_engine) = &self.copy_engine {
+            copy_engine.cancel();
+        }
+    }
+
+    pub fn on_item_created<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        match DesktopItem::new(path.as_ref()) {
+            Ok(desktop_item) => {
+                self.insert_desktop_item(desktop_item);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn on_item_removed<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        let len = self.desktop_items.len();
+        self.desktop_items.retain(|item| item.path != path.as_ref());
+        len != self.desktop_items.len()
+    }
+
+    pub fn on_item_renamed<P: AsRef<Path>>(&mut self, from: P, to: P) -> bool {
+        // A file moved between the desktop and the trash changes the trash icon as well.
+        let is_trash_changed = (is_in_trash(&from) || is_in_trash(&to)) && self.on_trash_changed();
+        let is_removed = self.on_item_removed(from);
+        // A file moved in from outside of the desktop directory comes as a rename too.
+        let is_created = if to.as_ref().parent() == Some(DESK_DIR.as_path()) {
+            self.on_item_created(to)
+        } else {
+            false
+        };
+        is_removed || is_created || is_trash_changed
+    }
+
+    pub fn on_item_modified<P: AsRef<Path>>(&mut self, path: P) -> bool {
+        // A desktop entry can turn invalid on edit, so it is dropped from the desktop.
+        self.on_item_created(path.as_ref()) || self.on_item_removed(path.as_ref())
+    }
+
+    /// Refresh the trash icon.
+    pub fn on_trash_changed(&mut self) -> bool {
+        self.insert_desktop_item(DesktopItem::trash());
+        true
+    }
+
+    pub fn rescan_desktop_items(&mut self) -> Result<(), DesktopError> {
+        self.desktop_items = Self::read_desktop_items()?;
+        self.desktop_items
+            .iter()
+            .for_each(|item| self.request_thumbnail(item));
+        self.sort_desktop_items(
+            self.conf.desktop_item_conf.sorting,
+            self.conf.desktop_item_conf.sort_descending,
+        );
+        Ok(())
+    }
+
+    /// Move `paths` into the folder `dest` in the background, asking what to do with the
+    /// names taken there. The desktop items are updated by the watcher.
+    pub fn move_items<P: AsRef<Path>>(&self, paths: &[P], dest: P) -> Result<(), DesktopError> {
+        let copy_engine = self
+            .copy_engine
+            .as_ref()
+            .ok_or(DesktopError::NoCopyEngine)?;
+
+        copy_engine.queue(CopyJob {
+            sources: paths
+                .iter()
+                .filter(|path| !is_trash(path))
+                .map(|path| path.as_ref().to_path_buf())
+                .collect(),
+            dest: dest.as_ref().to_path_buf(),
+            transfer: Transfer::Move,
+        });
+        Ok(())
+    }
+
+    /// Move `paths` to the trash. The moved items are kept so the move can be undone.
+    pub fn move_to_trash<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<(), DesktopError> {
+        self.last_trashed.clear();
+        let result = paths
+            .iter()
+            .filter(|path| !is_trash(path))
+            .try_for_each(|path| {
+                self.last_trashed.push(trash::move_to_trash(path)?);
+                Ok(())
+            });
+        self.on_trash_changed();
+
+        result
+    }
+
+    /// Put back the items of the last move to trash.
+    pub fn restore_trashed(&mut self) -> Result<(), DesktopError> {
+        let result = self
+            .last_trashed
+            .drain(..)
+            .try_for_each(|item| trash::restore(&item));
+        self.on_trash_changed();
+
+        Ok(result?)
+    }
+
+    pub fn empty_trash(&mut self) -> Result<(), DesktopError> {
+        self.last_trashed.clear();
+        let result = trash::empty_trash();
+        self.on_trash_changed();
+
+        Ok(result?)
     }
 
     pub fn add_wallpaper<P: AsRef<Path>>(
@@ -140,6 +485,8 @@ impl DesktopManager {
     }
 
     pub fn sort_desktop_items(&mut self, sorting: Sorting, sort_desc: bool) {
+        self.conf.desktop_item_conf.sorting = sorting;
+        self.conf.desktop_item_conf.sort_descending = sort_desc;
         match sorting {
             Sorting::Name => self.desktop_items.sort_by(|a, b| {
                 if sort_desc {
@@ -172,7 +519,65 @@ impl DesktopManager {
                 }
                 ordering
             }),
-            _ => self.desktop_items.sort(),
+            // Items are placed by the saved desktop layout, only keep a stable order.
+            Sorting::Manual => self.desktop_items.sort(),
+        }
+    }
+
+    fn read_desktop_items() -> Result<Vec<DesktopItem>, DesktopError> {
+        Ok(DESK_DIR
+            .read_dir()?
+            .filter_map(|entry| DesktopItem::new(entry.ok()?.path()).ok())
+            .chain(Some(DesktopItem::trash()))
+            .collect())
+    }
+
+    fn request_thumbnail(&self, desktop_item: &DesktopItem) {
+        if let (Some(tx), DesktopItemType::FILE) =
+            (&self.thumbnail_sender, &desktop_item.entry_type)
+        {
+            let _ = tx.send(desktop_item.path.to_path_buf());
         }
     }
+
+    fn insert_desktop_item(&mut self, desktop_item: DesktopItem) {
+        self.request_thumbnail(&desktop_item);
+        if let Some(item) = self
+            .desktop_items
+            .iter_mut()
+            .find(|item| item.path == desktop_item.path)
+        {
+            *item = desktop_item;
+        } else {
+            self.desktop_items.push(desktop_item);
+        }
+        self.sort_desktop_items(
+            self.conf.desktop_item_conf.sorting,
+            self.conf.desktop_item_conf.sort_descending,
+        );
+    }
+}
+
+/// Whether `path` is the trash item, which is never moved.
+fn is_trash<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref() == trash::home_trash_dir()
+}
+
+fn is_in_trash<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().starts_with(TRASH_FILES_DIR.as_path())
+}
+
+/// URL in `text` pasted as a link, e.g. a web page copied from a browser.
+fn link_url(text: &str) -> Option<Url> {
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Url::parse(text).ok().filter(is_link_url)
+}
+
+/// Whether `url` is opened as a link rather than being a file.
+fn is_link_url(url: &Url) -> bool {
+    url.scheme() != "file" && (url.has_host() || url.scheme() == "mailto")
 }
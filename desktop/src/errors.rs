@@ -1,5 +1,7 @@
 use super::background::WallpaperError;
+use super::clipboard::ClipboardError;
 use super::desktop_item::DesktopItemError;
+use freedesktop::trash::TrashError;
 use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum DesktopError {
@@ -7,6 +9,15 @@ pub enum DesktopError {
     ConfigNotFound(String),
     #[error("path is not exists or a file: {0}")]
     PathIsNotAFile(String),
+    #[error("a name cannot be empty, contain a slash or be . or ..")]
+    InvalidName,
+    // The taken name, then a free one to suggest instead.
+    #[error("an item named {0} already exists")]
+    NameTaken(String, String),
+    #[error("the clipboard is not available")]
+    NoClipboard,
+    #[error("files cannot be copied or moved")]
+    NoCopyEngine,
     #[error(transparent)]
     ParseConfigError(#[from] toml::de::Error),
     #[error(transparent)]
@@ -17,4 +28,10 @@ pub enum DesktopError {
     WallpaperError(#[from] WallpaperError),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    WatcherError(#[from] notify::Error),
+    #[error(transparent)]
+    TrashError(#[from] TrashError),
+    #[error(transparent)]
+    ClipboardError(#[from] ClipboardError),
 }
@@ -1,13 +1,19 @@
 mod background_config;
 mod color_config;
 mod context_menu;
+mod copy_progress;
 mod desktop;
 mod desktop_config;
 mod has_changed;
+mod properties;
+mod script_prompt;
 mod styles;
 
 pub use background_config::{BackgroundConfMsg, BackgroundConfigUI};
-pub use context_menu::{ContextMenu, ContextMsg};
-pub use desktop::{Desktop, DesktopMsg};
+pub use context_menu::{ContextMenu, ContextMsg, ItemMenu};
+pub use copy_progress::{CopyProgressMsg, CopyProgressUI};
+pub use desktop::{ask_to_trust, Desktop, DesktopMsg};
 pub use desktop_config::{DesktopConfigMsg, DesktopConfigUI};
 pub use has_changed::HasChanged;
+pub use properties::PropertiesUI;
+pub use script_prompt::{ScriptPromptMsg, ScriptPromptUI};
@@ -1,18 +1,39 @@
 use super::styles::{CustomButton, HOVERED};
+use crate::desktop_item::DesktopAction;
 use crate::proxy_message::ProxyMessage;
 use iced_wgpu::Renderer;
 use iced_winit::{
     Command, Container, Element, Length, Program, Button, Text, Column, button, 
     Row, Icon, icon::Icons, Space, Rule, Application, Color, winit, Clipboard,
 };
+use std::path::PathBuf;
 use winit::event_loop::EventLoopProxy;
 
+const MENU_WIDTH: f64 = 300.0;
+const MENU_PADDING: f64 = 8.0;
+const ITEM_HEIGHT: f64 = 35.0;
+const RULE_HEIGHT: f64 = 10.0;
+
 #[derive(Debug)]
 pub struct ContextMenu {
     menu_items: Vec<MenuItemNode>,
+    item_menu: Option<ItemMenu>,
     proxy: EventLoopProxy<ProxyMessage>,
 }
 
+/// Desktop items a menu was opened on and what can be done with them.
+#[derive(Debug, Clone, Default)]
+pub struct ItemMenu {
+    // Left empty for the trash, which is never moved.
+    pub paths: Vec<PathBuf>,
+    // Name and desktop file of every application able to open the items.
+    pub apps: Vec<(String, PathBuf)>,
+    // MIME type the items are opened as.
+    pub mime_type: String,
+    // Quick actions of a launcher.
+    pub actions: Vec<DesktopAction>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct MenuItemNode {
     state: button::State,
@@ -41,37 +62,110 @@ impl MenuItemNode {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum ContextMsg {
+    ShowDesktopMenu,
+    ShowItemMenu(ItemMenu),
+    ToggleSubmenu(usize),
     ChangeBG,
     NewFolder,
+    Paste,
     SortBy,
     DesktopView,
+    Open,
+    OpenWith(usize),
+    AlwaysOpenWith(usize),
+    LaunchAction(usize),
+    Cut,
+    Copy,
+    Rename,
+    MoveToTrash,
+    RestoreTrashed,
+    EmptyTrash,
+    Properties,
+}
+
+impl ContextMenu {
+    /// Size the window needs to show every item of the menu.
+    pub fn size(&self) -> (f64, f64) {
+        let height = self.menu_items.iter().fold(MENU_PADDING, |height, item| {
+            let submenu_len = match &item.submenu {
+                Some(submenu) if item.is_showed => submenu.len(),
+                _ => 0,
+            };
+            let rule_height = if item.has_underline { RULE_HEIGHT } else { 0.0 };
+            height + ITEM_HEIGHT * (1 + submenu_len) as f64 + rule_height
+        });
+        (MENU_WIDTH, height)
+    }
+
+    /// Whether a message sent by the menu is done with it.
+    pub fn is_done(message: &ContextMsg) -> bool {
+        use ContextMsg::*;
+        !matches!(message, ShowDesktopMenu | ShowItemMenu(_) | ToggleSubmenu(_) | SortBy)
+    }
+}
+
+fn desktop_menu_items() -> Vec<MenuItemNode> {
+    use ContextMsg::*;
+    vec![
+        MenuItemNode::new("New Folder", false, Some(NewFolder), None),
+        MenuItemNode::new("Paste", true, Some(Paste), None),
+        MenuItemNode::new("Undo Move to Trash", false, Some(RestoreTrashed), None),
+        MenuItemNode::new("Empty Trash", true, Some(EmptyTrash), None),
+        MenuItemNode::new("Change Desktop Background", false, Some(ChangeBG), None),
+        MenuItemNode::new(
+            "Sort By",
+            true,
+            Some(SortBy),
+            Some(vec![
+                MenuItemNode::new("Manual", true, None, None),
+                MenuItemNode::new("Name", false, None, None),
+                MenuItemNode::new("Type", false, None, None),
+                MenuItemNode::new("Date", false, None, None),
+            ]),
+        ),
+        MenuItemNode::new("Desktop View", false, Some(DesktopView), None),
+    ]
+}
+
+fn item_menu_items(item_menu: &ItemMenu) -> Vec<MenuItemNode> {
+    use ContextMsg::*;
+    // The trash is only opened or emptied.
+    if item_menu.paths.is_empty() {
+        return vec![
+            MenuItemNode::new("Open", true, Some(Open), None),
+            MenuItemNode::new("Empty Trash", false, Some(EmptyTrash), None),
+        ];
+    }
+
+    let mut menu_items = vec![MenuItemNode::new("Open", item_menu.apps.is_empty() && item_menu.actions.is_empty(), Some(Open), None)];
+    if !item_menu.apps.is_empty() {
+        let apps = |msg: fn(usize) -> ContextMsg| -> Vec<MenuItemNode> { item_menu.apps.iter().enumerate().map(|(idx, (name, _))| MenuItemNode::new(name, false, Some(msg(idx)), None)).collect() };
+        let idx = menu_items.len();
+        menu_items.push(MenuItemNode::new("Open With", false, Some(ToggleSubmenu(idx)), Some(apps(OpenWith))));
+        menu_items.push(MenuItemNode::new("Always Open With", item_menu.actions.is_empty(), Some(ToggleSubmenu(idx + 1)), Some(apps(AlwaysOpenWith))));
+    }
+    let num_actions = item_menu.actions.len();
+    menu_items.extend(item_menu.actions.iter().enumerate().map(|(idx, action)| MenuItemNode::new(&action.name, idx + 1 == num_actions, Some(LaunchAction(idx)), None)));
+    menu_items.extend(vec![
+        MenuItemNode::new("Cut", false, Some(Cut), None),
+        MenuItemNode::new("Copy", true, Some(Copy), None),
+        MenuItemNode::new("Rename", false, Some(Rename), None),
+        MenuItemNode::new("Move to Trash", true, Some(MoveToTrash), None),
+        MenuItemNode::new("Properties", false, Some(Properties), None),
+    ]);
+    menu_items
 }
 
 impl Application for ContextMenu {
     type Flags = EventLoopProxy<ProxyMessage>;
 
     fn new(flags: Self::Flags) -> (Self, Command<ContextMsg>) {
-        use ContextMsg::*;
         (
             Self {
-                menu_items: vec![
-                    MenuItemNode::new("New Folder", true, Some(NewFolder), None),
-                    MenuItemNode::new("Change Desktop Background", false, Some(ChangeBG), None),
-                    MenuItemNode::new(
-                        "Sort By",
-                        true,
-                        Some(SortBy),
-                        Some(vec![
-                            MenuItemNode::new("Manual", true, None, None),
-                            MenuItemNode::new("Name", false, None, None),
-                            MenuItemNode::new("Type", false, None, None),
-                            MenuItemNode::new("Date", false, None, None),
-                        ]),
-                    ),
-                    MenuItemNode::new("Desktop View", false, Some(DesktopView), None),
-                ],
+                menu_items: desktop_menu_items(),
+                item_menu: None,
                 proxy: flags,
             },
             Command::none(),
@@ -102,6 +196,55 @@ impl Program for ContextMenu {
                 .proxy
                 .send_event(ProxyMessage::ContextMenu(DesktopView))
                 .unwrap(),
+            ShowDesktopMenu => {
+                self.item_menu = None;
+                self.menu_items = desktop_menu_items();
+            }
+            ShowItemMenu(item_menu) => {
+                self.menu_items = item_menu_items(&item_menu);
+                self.item_menu = Some(item_menu);
+            }
+            ToggleSubmenu(idx) => {
+                if let Some(item) = self.menu_items.get_mut(idx) {
+                    item.is_showed = !item.is_showed;
+                }
+            }
+            OpenWith(idx) => {
+                if let Some(item_menu) = &self.item_menu {
+                    if let Some((_, app_path)) = item_menu.apps.get(idx) {
+                        self.proxy.send_event(ProxyMessage::OpenWith(item_menu.paths.to_owned(), app_path.to_path_buf())).unwrap();
+                    }
+                }
+            }
+            AlwaysOpenWith(idx) => {
+                if let Some(item_menu) = &self.item_menu {
+                    if let Some((_, app_path)) = item_menu.apps.get(idx) {
+                        self.proxy.send_event(ProxyMessage::AlwaysOpenWith(item_menu.paths.to_owned(), app_path.to_path_buf(), item_menu.mime_type.to_owned())).unwrap();
+                    }
+                }
+            }
+            LaunchAction(idx) => {
+                if let Some(item_menu) = &self.item_menu {
+                    if let (Some(path), Some(action)) = (item_menu.paths.first(), item_menu.actions.get(idx)) {
+                        self.proxy.send_event(ProxyMessage::LaunchAction(path.to_path_buf(), action.id.to_owned())).unwrap();
+                    }
+                }
+            }
+            Cut | Copy => {
+                if let Some(item_menu) = &self.item_menu {
+                    let paths = item_menu.paths.to_owned();
+                    let message = if let Cut = message { ProxyMessage::CutItems(paths) } else { ProxyMessage::CopyItems(paths) };
+                    self.proxy.send_event(message).unwrap();
+                }
+            }
+            Properties => {
+                if let Some(path) = self.item_menu.as_ref().and_then(|item_menu| item_menu.paths.first()) {
+                    self.proxy.send_event(ProxyMessage::ShowProperties(path.to_path_buf())).unwrap();
+                }
+            }
+            Paste | Open | Rename | MoveToTrash | RestoreTrashed | EmptyTrash => {
+                self.proxy.send_event(ProxyMessage::ContextMenu(message)).unwrap()
+            }
         }
 
         Command::none()
@@ -109,33 +252,44 @@ impl Program for ContextMenu {
 
     fn view(&mut self) -> Element<ContextMsg, Renderer> {
         let context_menu = self.menu_items.iter_mut().fold(Column::new().padding(4), |mut column, item| {
+            let MenuItemNode { state, title, selected, is_showed, has_underline, submenu, callback } = item;
             let mut content = Row::new().spacing(7).padding(5);
-            if item.selected {
+            if *selected {
                 content = content.push(Icon::new(Icons::Check));
             }
-            content = content.push(Text::new(&item.title));
-            // let mut submenu = None;
-            if let Some(_submenu) = &item.submenu {
+            content = content.push(Text::new(title.as_str()));
+            if submenu.is_some() {
                 content = content
                     .push(Space::with_width(Length::Fill))
-                    .push(Icon::new(Icons::AngleRight));
-                // submenu = menu(submenu);
+                    .push(Icon::new(if *is_showed { Icons::AngleDown } else { Icons::AngleRight }));
             }
-            let mut btn = Button::new(&mut item.state, content)
+            let mut btn = Button::new(state, content)
                 .width(Length::Fill)
                 .style(CustomButton::Transparent);
-            if let Some(callback) = item.callback {
-                btn = btn.on_press(callback);
+            if let Some(callback) = callback {
+                btn = btn.on_press(callback.clone());
             }
 
             column = column.push(btn);
-            if item.has_underline {
+            // An open submenu is shown indented below its item.
+            if let Some(submenu) = submenu.as_mut().filter(|_| *is_showed) {
+                column = submenu.iter_mut().fold(column, |column, child| {
+                    let content = Row::new().spacing(7).padding(5).push(Space::with_width(Length::Units(14))).push(Text::new(child.title.as_str()));
+                    let mut btn = Button::new(&mut child.state, content)
+                        .width(Length::Fill)
+                        .style(CustomButton::Transparent);
+                    if let Some(callback) = &child.callback {
+                        btn = btn.on_press(callback.clone());
+                    }
+                    column.push(btn)
+                });
+            }
+            if *has_underline {
                 column.push(Rule::horizontal(10))
             } else {
                 column
             }
         });
-        // let context_menu = menu(&mut self.menu_items);
 
         Container::new(context_menu)
             .width(Length::Fill)
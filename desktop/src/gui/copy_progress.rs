@@ -0,0 +1,199 @@
+use super::styles::{CustomButton, CustomCheckbox, BACKGROUND};
+use crate::copy_engine::{ConflictAction, CopyStatus, Resolution, Transfer};
+use crate::proxy_message::ProxyMessage;
+use iced_wgpu::Renderer;
+use iced_winit::{
+    button, winit, Align, Application, Button, Checkbox, Clipboard, Color, Column, Command,
+    Element, Length, Program, ProgressBar, Row, Space, Text,
+};
+use std::path::Path;
+use std::{cell::RefCell, rc::Rc};
+use winit::event_loop::EventLoopProxy;
+
+#[derive(Debug)]
+pub struct CopyProgressUI {
+    status: Rc<RefCell<CopyStatus>>,
+    apply_to_all: bool,
+    btn_skip_state: button::State,
+    btn_replace_state: button::State,
+    btn_rename_state: button::State,
+    btn_cancel_state: button::State,
+    proxy: EventLoopProxy<ProxyMessage>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CopyProgressMsg {
+    ActionChosen(ConflictAction),
+    ApplyToAllToggled(bool),
+    Resolved(Resolution),
+    CancelClicked,
+}
+
+impl Application for CopyProgressUI {
+    type Flags = (EventLoopProxy<ProxyMessage>, Rc<RefCell<CopyStatus>>);
+
+    fn new(flags: Self::Flags) -> (Self, Command<CopyProgressMsg>) {
+        (
+            Self {
+                proxy: flags.0,
+                status: flags.1,
+                apply_to_all: false,
+                btn_skip_state: Default::default(),
+                btn_replace_state: Default::default(),
+                btn_rename_state: Default::default(),
+                btn_cancel_state: Default::default(),
+            },
+            Command::none(),
+        )
+    }
+
+    fn title(&self) -> String {
+        match self.status.borrow().transfer {
+            Transfer::Copy => String::from("Copying Files"),
+            Transfer::Move => String::from("Moving Files"),
+        }
+    }
+
+    fn background_color(&self) -> Color {
+        BACKGROUND
+    }
+}
+
+impl Program for CopyProgressUI {
+    type Message = CopyProgressMsg;
+    type Renderer = Renderer;
+    type Clipboard = Clipboard;
+
+    fn update(&mut self, msg: Self::Message, _clipboard: &mut Clipboard) -> Command<Self::Message> {
+        use CopyProgressMsg::*;
+
+        match msg {
+            ActionChosen(action) => {
+                self.status.borrow_mut().conflict = None;
+                self.proxy
+                    .send_event(ProxyMessage::CopyProgress(Resolved(Resolution {
+                        action,
+                        apply_to_all: self.apply_to_all,
+                    })))
+                    .unwrap();
+            }
+            ApplyToAllToggled(is_checked) => self.apply_to_all = is_checked,
+            CancelClicked => self
+                .proxy
+                .send_event(ProxyMessage::CopyProgress(CancelClicked))
+                .unwrap(),
+            Resolved(_) => {}
+        }
+
+        Command::none()
+    }
+
+    fn view(&mut self) -> Element<Self::Message, Renderer> {
+        use CopyProgressMsg::*;
+        let Self {
+            status,
+            apply_to_all,
+            btn_skip_state,
+            btn_replace_state,
+            btn_rename_state,
+            btn_cancel_state,
+            ..
+        } = self;
+
+        let status = status.borrow();
+        let verb = match status.transfer {
+            Transfer::Copy => "Copying",
+            Transfer::Move => "Moving",
+        };
+        let lb_current = Text::new(format!(
+            "{} {}",
+            verb,
+            status.current.as_deref().map(file_name).unwrap_or_default()
+        ));
+        let pb_progress = ProgressBar::new(
+            0.0..=status.total_bytes.max(1) as f32,
+            status.done_bytes as f32,
+        )
+        .height(Length::Units(8));
+        let lb_progress = Text::new(format!(
+            "{} of {}",
+            format_size(status.done_bytes),
+            format_size(status.total_bytes)
+        ))
+        .size(12);
+
+        let mut content = Column::new()
+            .spacing(10)
+            .push(lb_current)
+            .push(pb_progress)
+            .push(lb_progress);
+        if let Some((_, existing, _)) = &status.conflict {
+            let folder = existing.parent().map(file_name).unwrap_or_default();
+            let lb_conflict = Text::new(format!(
+                "An item named {} already exists in {}.",
+                file_name(existing),
+                folder
+            ));
+            let chb_apply_to_all = Checkbox::new(*apply_to_all, "Apply to all", ApplyToAllToggled)
+                .style(CustomCheckbox);
+            let btn_skip = Button::new(btn_skip_state, Text::new("  Skip  "))
+                .on_press(ActionChosen(ConflictAction::Skip))
+                .style(CustomButton::Default);
+            let btn_replace = Button::new(btn_replace_state, Text::new("  Replace  "))
+                .on_press(ActionChosen(ConflictAction::Replace))
+                .style(CustomButton::Default);
+            let btn_rename = Button::new(btn_rename_state, Text::new("  Rename  "))
+                .on_press(ActionChosen(ConflictAction::Rename))
+                .style(CustomButton::Primary);
+
+            content = content.push(lb_conflict).push(chb_apply_to_all).push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Space::with_width(Length::Fill))
+                    .push(btn_skip)
+                    .push(btn_replace)
+                    .push(btn_rename),
+            );
+        }
+
+        let btn_cancel = Button::new(btn_cancel_state, Text::new("  Cancel  "))
+            .on_press(CancelClicked)
+            .style(CustomButton::Secondary);
+
+        Column::new()
+            .padding(15)
+            .width(Length::Fill)
+            .push(content)
+            .push(Space::with_height(Length::Fill))
+            .push(
+                Row::new()
+                    .push(Space::with_width(Length::Fill))
+                    .push(btn_cancel),
+            )
+            .into()
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Size in bytes in a human readable unit.
+pub(super) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -1,17 +1,35 @@
-use super::styles::{CustomButton, CustomTooltip};
+mod selection;
+
+use super::context_menu::{ContextMsg, ItemMenu};
+use super::desktop_config::DesktopConfigMsg;
+use super::styles::{CustomButton, CustomContainer, CustomTooltip};
 use crate::configs::{
-    background_conf::BackgroundType, desktop_item_conf::Arrangement, DesktopConf,
+    background_conf::BackgroundType,
+    desktop_item_conf::{Arrangement, DesktopItemConf, Sorting},
+    desktop_layout::{DesktopLayout, GridCell},
+    DesktopConf, PersistentData,
 };
-use crate::desktop_item::DesktopItem;
+use crate::desktop_item::{DesktopItem, DesktopItemError, DesktopItemType};
+use crate::proxy_message::ProxyMessage;
 use iced::{Image, Svg};
 use iced_wgpu::Renderer;
 use iced_winit::{
-    button, keyboard, mouse, tooltip, Align, Application, Button, Clipboard, Color, Column,
-    Command, Container, Element, Event, Grid, HorizontalAlignment, Length, Program, Row, Stack,
-    Subscription, Text, Tooltip,
+    button, keyboard, text_input, tooltip, window, winit, Align, Application, Button, Clipboard,
+    Color, Column, Command, Container, Element, Event, Grid, HorizontalAlignment, Length, Point,
+    Program, Rectangle, Row, Size, Space, Stack, Subscription, Text, TextInput, Tooltip,
 };
+use selection::Selection;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use std::{cell::RefCell, rc::Rc};
-use tauri_dialog::{DialogBuilder, DialogStyle};
+use tauri_dialog::{DialogBuilder, DialogButtons, DialogSelection, DialogStyle};
+use winit::event_loop::EventLoopProxy;
+
+const GRID_PADDING: u16 = 20;
+/// Distance the pointer must travel with the button held before an item is dragged.
+const DRAG_THRESHOLD: f32 = 5.0;
+/// A second click on the selected item slower than a double click starts renaming it.
+const SLOW_CLICK_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Debug)]
 pub struct Desktop {
@@ -19,22 +37,93 @@ pub struct Desktop {
     desktop_conf: Rc<RefCell<DesktopConf>>,
     ls_desktop_items_state: Vec<button::State>,
     ls_desktop_items: Rc<RefCell<Vec<DesktopItem>>>,
-    selected_desktop_item: Option<usize>,
+    selection: Selection,
+    modifiers: keyboard::Modifiers,
+    layout: DesktopLayout,
+    // Resolution of the monitor, the saved layout being kept per resolution.
+    resolution: (u32, u32),
+    // Desktop item in each cell of the grid, row by row.
+    cells: Vec<Option<usize>>,
+    columns: usize,
+    drag: DragState,
+    rename: Option<Rename>,
+    // Item clicked last and when, to tell a slow double click.
+    last_click: Option<(PathBuf, Instant)>,
+    proxy: EventLoopProxy<ProxyMessage>,
+}
+
+/// Name of a desktop item being edited in place.
+#[derive(Debug)]
+struct Rename {
+    path: PathBuf,
+    name: String,
+    value: String,
+    state: text_input::State,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DragState {
+    Idle,
+    Pressed {
+        item: usize,
+        origin: Point,
+    },
+    // The item under the pointer comes first in `items`.
+    Dragging {
+        items: Vec<usize>,
+        origin: GridCell,
+        target: Option<GridCell>,
+    },
+    // Rubber band from `origin`, adding to the items in `base`.
+    Selecting {
+        origin: Point,
+        current: Point,
+        base: Selection,
+    },
 }
 
 #[derive(Debug, Clone)]
 pub enum DesktopMsg {
     DesktopItemClicked(usize),
     LaunchDesktopItem(usize),
+    FilesDropped(Vec<PathBuf>, Point),
+    DesktopItemsChanged,
+    DesktopItemRenamed(PathBuf, PathBuf),
+    ResolutionChanged(u32, u32),
+    PointerPressed(Point),
+    PointerMoved(Point),
+    PointerReleased(Point),
+    ContextMenuRequested(Point),
+    LaunchSelected,
+    TrashSelected,
+    StartRename(PathBuf),
+    RenameSelected,
+    RenameChanged(String),
+    RenameSubmitted,
     WinitEvent(Event),
 }
 
 impl Desktop {
-    fn handle_exec(&self, idx: usize) {
+    fn handle_exec(&self, idx: usize, files: &[PathBuf]) {
         let desktop_items = self.ls_desktop_items.borrow();
+        let desktop_conf = self.desktop_conf.borrow();
+        let preferred_terminal = desktop_conf.terminal_conf().preferred_terminal.as_deref();
 
         if let Some(desktop_item) = desktop_items.get(idx) {
-            if let Err(err) = desktop_item.handle_exec() {
+            let res = match desktop_item.handle_exec(files, preferred_terminal) {
+                Err(DesktopItemError::Untrusted(_)) if ask_to_trust(desktop_item) => {
+                    desktop_item.handle_exec(files, preferred_terminal)
+                }
+                Err(DesktopItemError::Untrusted(_)) => Ok(()),
+                Err(DesktopItemError::ExecutableScript(_)) => {
+                    self.proxy
+                        .send_event(ProxyMessage::ShowScriptPrompt(desktop_item.path.to_owned()))
+                        .unwrap();
+                    Ok(())
+                }
+                res => res,
+            };
+            if let Err(err) = res {
                 let _ = DialogBuilder::new()
                     .title("Error")
                     .message(&format!("{}", err))
@@ -44,27 +133,613 @@ impl Desktop {
             }
         }
     }
+
+    fn on_desktop_items_changed(&mut self) {
+        let num_items = self.ls_desktop_items.borrow().len();
+        self.ls_desktop_items_state
+            .resize(num_items, button::State::new());
+        {
+            let desktop_items = self.ls_desktop_items.borrow();
+            self.selection
+                .retain(|path| desktop_items.iter().any(|item| item.path == path));
+            if let Some(rename) = &self.rename {
+                if !desktop_items.iter().any(|item| item.path == rename.path) {
+                    self.rename = None;
+                }
+            }
+        }
+        self.arrange();
+    }
+
+    /// Number of columns and rows fitting on the screen and size of a cell of the desktop grid.
+    fn grid_layout(size: (u32, u32), item_conf: &DesktopItemConf) -> (usize, usize, u16) {
+        let grid_spacing = item_conf.grid_spacing;
+        let cell_size = item_conf.icon_size + 40 + (grid_spacing * 2);
+        let step = f32::from(cell_size + grid_spacing);
+        let fit = |length: u32| {
+            let length = length as f32 - f32::from(GRID_PADDING * 2) + f32::from(grid_spacing);
+            ((length / step) as usize).max(1)
+        };
+
+        (fit(size.0), fit(size.1), cell_size)
+    }
+
+    /// Place every desktop item in a cell of the grid. With the Manual sorting items keep
+    /// their saved cell, others take the first free cell following the arrangement.
+    fn arrange(&mut self) {
+        let desktop_conf = self.desktop_conf.borrow();
+        let item_conf = &desktop_conf.desktop_item_conf;
+        let desktop_items = self.ls_desktop_items.borrow();
+        let is_manual = item_conf.sorting == Sorting::Manual;
+        let (columns, rows, _) = Self::grid_layout(self.size, item_conf);
+        // Items not fitting on the screen go to extra rows.
+        let rows = rows.max((desktop_items.len() + columns - 1) / columns);
+        let mut cells = vec![None; columns * rows];
+        let mut pending = Vec::new();
+
+        for (idx, item) in desktop_items.iter().enumerate() {
+            match self
+                .layout
+                .cell(self.resolution, &item.path)
+                .filter(|_| is_manual)
+            {
+                Some(cell)
+                    if cell.column < columns
+                        && cell.row < rows
+                        && cells[cell.row * columns + cell.column].is_none() =>
+                {
+                    cells[cell.row * columns + cell.column] = Some(idx)
+                }
+                _ => pending.push(idx),
+            }
+        }
+
+        let free_cells: Vec<usize> = Self::cells_in_order(item_conf.arrangement, columns, rows)
+            .filter(|cell| cells[*cell].is_none())
+            .collect();
+        let mut is_changed = false;
+        for (idx, cell) in pending.into_iter().zip(free_cells) {
+            cells[cell] = Some(idx);
+            if is_manual {
+                let grid_cell = GridCell {
+                    column: cell % columns,
+                    row: cell / columns,
+                };
+                self.layout
+                    .set_cell(self.resolution, &desktop_items[idx].path, grid_cell);
+                is_changed = true;
+            }
+        }
+        if is_manual {
+            // Items missing for a moment, e.g. saved by removing and creating them again,
+            // keep their cell until they are gone for good.
+            is_changed |= self.layout.retain(|path| path.symlink_metadata().is_ok());
+        }
+        if is_changed {
+            if let Err(err) = self.layout.save() {
+                eprintln!("{:?}", err);
+            }
+        }
+
+        self.cells = cells;
+        self.columns = columns;
+    }
+
+    /// Indices of the cells of the grid in the order the arrangement fills them.
+    fn cells_in_order(
+        arrangement: Arrangement,
+        columns: usize,
+        rows: usize,
+    ) -> impl Iterator<Item = usize> {
+        (0..columns * rows).map(move |order| match arrangement {
+            Arrangement::Rows => order,
+            Arrangement::Columns => (order % rows) * columns + order / rows,
+        })
+    }
+
+    /// Desktop items in the order they are shown.
+    fn visual_order(&self) -> Vec<usize> {
+        let arrangement = self.desktop_conf.borrow().desktop_item_conf.arrangement;
+        let rows = self.cells.len() / self.columns;
+
+        Self::cells_in_order(arrangement, self.columns, rows)
+            .filter_map(|cell| self.cells[cell])
+            .collect()
+    }
+
+    /// Selected desktop items in the order they are shown.
+    fn selected_items(&self) -> Vec<usize> {
+        let desktop_items = self.ls_desktop_items.borrow();
+
+        self.visual_order()
+            .into_iter()
+            .filter(|idx| self.selection.contains(&desktop_items[*idx].path))
+            .collect()
+    }
+
+    /// Items an action started on `idx` applies to: the whole selection when `idx` is part of
+    /// it, otherwise `idx` alone. `idx` always comes first.
+    fn items_for(&self, idx: usize) -> Vec<usize> {
+        let is_selected = self
+            .ls_desktop_items
+            .borrow()
+            .get(idx)
+            .map_or(false, |item| self.selection.contains(&item.path));
+        let mut items = vec![idx];
+
+        if is_selected {
+            items.extend(
+                self.selected_items()
+                    .into_iter()
+                    .filter(|item| *item != idx),
+            );
+        }
+        items
+    }
+
+    fn on_item_clicked(&mut self, idx: usize) {
+        let desktop_items = self.ls_desktop_items.borrow();
+        let path = match desktop_items.get(idx) {
+            Some(item) => &item.path,
+            None => return,
+        };
+
+        if self.modifiers.shift {
+            let order = self.visual_order();
+            let position = |path: &Path| {
+                order
+                    .iter()
+                    .position(|idx| desktop_items[*idx].path == path)
+            };
+
+            match (
+                self.selection.anchor().and_then(position),
+                position(path.as_path()),
+            ) {
+                (Some(anchor), Some(end)) => {
+                    let range = order[anchor.min(end)..=anchor.max(end)]
+                        .iter()
+                        .map(|idx| desktop_items[*idx].path.to_path_buf());
+                    if self.modifiers.control {
+                        self.selection.extend(range);
+                    } else {
+                        self.selection.set(range);
+                    }
+                }
+                _ => self.selection.select(path),
+            }
+        } else if self.modifiers.control {
+            self.selection.toggle(path);
+        } else {
+            let is_slow_click = self.selection.len() == 1
+                && self.selection.contains(path)
+                && self.last_click.as_ref().map_or(false, |(last, at)| {
+                    last == path && at.elapsed() >= SLOW_CLICK_INTERVAL
+                });
+            let path = path.to_path_buf();
+
+            drop(desktop_items);
+            if is_slow_click {
+                self.last_click = None;
+                self.start_rename(path);
+            } else {
+                self.selection.select(&path);
+                self.last_click = Some((path, Instant::now()));
+            }
+        }
+    }
+
+    /// Edit the name of the item at `path` in place. The trash keeps its name.
+    fn start_rename(&mut self, path: PathBuf) {
+        let desktop_items = self.ls_desktop_items.borrow();
+        let item = match desktop_items.iter().find(|item| item.path == path) {
+            Some(item) if item.entry_type != DesktopItemType::TRASH => item,
+            _ => return,
+        };
+        let name = item.name.to_owned().unwrap_or_default();
+        let mut state = text_input::State::focused();
+        state.select_all();
+
+        self.selection.select(&path);
+        self.rename = Some(Rename {
+            path,
+            value: name.to_owned(),
+            name,
+            state,
+        });
+    }
+
+    /// Rename the selected item, when only one is.
+    fn rename_selected(&mut self) {
+        if let [idx] = self.selected_items()[..] {
+            let path = self.ls_desktop_items.borrow()[idx].path.to_path_buf();
+            self.start_rename(path);
+        }
+    }
+
+    fn submit_rename(&mut self) {
+        if let Some(rename) = self.rename.take() {
+            if rename.value != rename.name {
+                self.proxy
+                    .send_event(ProxyMessage::RenameItem(rename.path, rename.value))
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Select the item after or before the last one clicked, wrapping around.
+    fn select_next(&mut self, is_forward: bool) {
+        let order = self.visual_order();
+        let desktop_items = self.ls_desktop_items.borrow();
+        let len = order.len();
+        let current = self.selection.anchor().and_then(|anchor| {
+            order
+                .iter()
+                .position(|idx| desktop_items[*idx].path == anchor)
+        });
+        let next = match current {
+            Some(pos) if is_forward => (pos + 1) % len,
+            Some(pos) => (pos + len - 1) % len,
+            None => 0,
+        };
+
+        if let Some(idx) = order.get(next) {
+            self.selection.select(&desktop_items[*idx].path);
+        }
+    }
+
+    fn select_all(&mut self) {
+        let desktop_items = self.ls_desktop_items.borrow();
+        self.selection
+            .set(desktop_items.iter().map(|item| item.path.to_path_buf()));
+    }
+
+    /// Bounds of a cell of the grid, in the coordinates of the pointer.
+    fn cell_bounds(&self, cell: usize) -> Rectangle {
+        let desktop_conf = self.desktop_conf.borrow();
+        let item_conf = &desktop_conf.desktop_item_conf;
+        let (_, _, cell_size) = Self::grid_layout(self.size, item_conf);
+        let step = f32::from(cell_size + item_conf.grid_spacing);
+        let cell = self.grid_cell(cell);
+
+        Rectangle::new(
+            Point::new(
+                f32::from(GRID_PADDING) + cell.column as f32 * step,
+                f32::from(GRID_PADDING) + cell.row as f32 * step,
+            ),
+            Size::new(f32::from(cell_size), f32::from(cell_size)),
+        )
+    }
+
+    /// Paths of the items whose cell the rubber band touches.
+    fn items_in(&self, rubber_band: Rectangle) -> Vec<PathBuf> {
+        let desktop_items = self.ls_desktop_items.borrow();
+
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(|(cell, idx)| Some((cell, desktop_items.get((*idx)?)?)))
+            .filter(|(cell, _)| self.cell_bounds(*cell).intersection(&rubber_band).is_some())
+            .map(|(_, item)| item.path.to_path_buf())
+            .collect()
+    }
+
+    /// Cell of the grid under `position`.
+    fn cell_at(&self, position: Point) -> Option<GridCell> {
+        let desktop_conf = self.desktop_conf.borrow();
+        let item_conf = &desktop_conf.desktop_item_conf;
+        let (_, _, cell_size) = Self::grid_layout(self.size, item_conf);
+        let step = f32::from(cell_size + item_conf.grid_spacing);
+        let x = position.x - f32::from(GRID_PADDING);
+        let y = position.y - f32::from(GRID_PADDING);
+
+        if x < 0.0 || y < 0.0 || x / step >= self.columns as f32 {
+            None
+        } else {
+            Some(GridCell {
+                column: (x / step) as usize,
+                row: (y / step) as usize,
+            })
+        }
+    }
+
+    /// Index of the desktop item under `position`.
+    fn item_at(&self, position: Point) -> Option<usize> {
+        self.item_in(self.cell_at(position)?)
+    }
+
+    fn item_in(&self, cell: GridCell) -> Option<usize> {
+        self.cells
+            .get(cell.row * self.columns + cell.column)
+            .copied()
+            .flatten()
+    }
+
+    fn grid_cell(&self, cell: usize) -> GridCell {
+        GridCell {
+            column: cell % self.columns,
+            row: cell / self.columns,
+        }
+    }
+
+    /// Folder or trash in `cell` the dragged `items` would be moved into.
+    fn drop_folder(&self, cell: GridCell, items: &[usize]) -> Option<usize> {
+        let idx = self.item_in(cell).filter(|idx| !items.contains(idx))?;
+        let desktop_items = self.ls_desktop_items.borrow();
+
+        match desktop_items.get(idx)?.entry_type {
+            DesktopItemType::DIR | DesktopItemType::TRASH => Some(idx),
+            _ => None,
+        }
+    }
+
+    /// Paths of the files of `items`, leaving out the trash which is never moved.
+    fn item_paths(&self, items: &[usize]) -> Vec<PathBuf> {
+        let desktop_items = self.ls_desktop_items.borrow();
+
+        items
+            .iter()
+            .filter_map(|idx| desktop_items.get(*idx))
+            .filter(|item| item.entry_type != DesktopItemType::TRASH)
+            .map(|item| item.path.to_path_buf())
+            .collect()
+    }
+
+    /// Put the selected items on the clipboard with `msg`, copied or cut.
+    fn copy_selected(&self, msg: fn(Vec<PathBuf>) -> ProxyMessage) {
+        let paths = self.item_paths(&self.selected_items());
+
+        if !paths.is_empty() {
+            self.proxy.send_event(msg(paths)).unwrap();
+        }
+    }
+
+    /// Open the menu of the item under `position`, selecting it first, or the menu of the
+    /// desktop itself.
+    fn request_context_menu(&mut self, position: Point) {
+        self.submit_rename();
+        let message = match self.item_at(position) {
+            Some(idx) => {
+                let path = self.ls_desktop_items.borrow()[idx].path.to_path_buf();
+                if !self.selection.contains(&path) {
+                    self.selection.select(&path);
+                }
+
+                let items = self.items_for(idx);
+                let desktop_items = self.ls_desktop_items.borrow();
+                let item = &desktop_items[idx];
+                // Quick actions only make sense for a single launcher.
+                let actions = if items.len() == 1 {
+                    item.actions().to_vec()
+                } else {
+                    Vec::new()
+                };
+                ContextMsg::ShowItemMenu(ItemMenu {
+                    paths: self.item_paths(&items),
+                    apps: item.open_with_apps(),
+                    mime_type: item.mime_type(),
+                    actions,
+                })
+            }
+            None => ContextMsg::ShowDesktopMenu,
+        };
+
+        self.proxy
+            .send_event(ProxyMessage::ContextMenu(message))
+            .unwrap();
+    }
+
+    fn trash_items(&self, paths: Vec<PathBuf>) {
+        if !paths.is_empty() {
+            self.proxy
+                .send_event(ProxyMessage::TrashItems(paths))
+                .unwrap();
+        }
+    }
+
+    fn on_pointer_pressed(&mut self, position: Point) {
+        let item = self.item_at(position);
+        if let Some(rename) = &self.rename {
+            // Presses in the edited item go to its text input, others end the editing.
+            let desktop_items = self.ls_desktop_items.borrow();
+            if item.map_or(false, |idx| desktop_items[idx].path == rename.path) {
+                return;
+            }
+            drop(desktop_items);
+            self.submit_rename();
+        }
+
+        self.drag = match item {
+            Some(item) => DragState::Pressed {
+                item,
+                origin: position,
+            },
+            None => {
+                // Ctrl adds the items of the rubber band to the selection.
+                if !self.modifiers.control {
+                    self.selection.clear();
+                }
+                DragState::Selecting {
+                    origin: position,
+                    current: position,
+                    base: self.selection.clone(),
+                }
+            }
+        }
+    }
+
+    fn on_pointer_moved(&mut self, position: Point) {
+        let target = self.cell_at(position);
+
+        match &mut self.drag {
+            DragState::Pressed { item, origin } => {
+                let (item, origin) = (*item, *origin);
+                if origin.distance(position) >= DRAG_THRESHOLD {
+                    if let Some(origin) = self.cell_at(origin) {
+                        self.drag = DragState::Dragging {
+                            items: self.items_for(item),
+                            origin,
+                            target,
+                        };
+                    }
+                }
+            }
+            DragState::Dragging {
+                target: drag_target,
+                ..
+            } => *drag_target = target,
+            DragState::Selecting {
+                origin,
+                current,
+                base,
+            } => {
+                *current = position;
+                let (rubber_band, mut selection) = (rubber_band(*origin, position), base.clone());
+                selection.extend(self.items_in(rubber_band));
+                self.selection = selection;
+            }
+            DragState::Idle => {}
+        }
+    }
+
+    fn on_pointer_released(&mut self, position: Point) {
+        let drag = std::mem::replace(&mut self.drag, DragState::Idle);
+        if let DragState::Dragging { .. } = drag {
+            self.last_click = None;
+        }
+
+        if let (DragState::Dragging { items, origin, .. }, Some(target)) =
+            (drag, self.cell_at(position))
+        {
+            match self.drop_folder(target, &items) {
+                Some(folder) => {
+                    let paths = self.item_paths(&items);
+                    let desktop_items = self.ls_desktop_items.borrow();
+                    let folder = &desktop_items[folder];
+
+                    if folder.entry_type == DesktopItemType::TRASH {
+                        self.trash_items(paths);
+                    } else if !paths.is_empty() {
+                        self.proxy
+                            .send_event(ProxyMessage::MoveItems(paths, folder.path.to_path_buf()))
+                            .unwrap();
+                    }
+                }
+                None => self.move_items_to(&items, origin, target),
+            }
+        }
+    }
+
+    /// Move the dragged `items` by the offset between `origin` and `target`, each one taking
+    /// the nearest free cell when its own is taken.
+    fn move_items_to(&mut self, items: &[usize], origin: GridCell, target: GridCell) {
+        if origin == target {
+            return;
+        }
+        self.use_manual_sorting();
+
+        let columns = self.columns as isize;
+        let rows = (self.cells.len() / self.columns) as isize;
+        let mut cells = self.cells.clone();
+        let current: Vec<(usize, usize)> = items
+            .iter()
+            .filter_map(|idx| Some((*idx, cells.iter().position(|cell| *cell == Some(*idx))?)))
+            .collect();
+        // Dragged items leave their cells free for each other.
+        current.iter().for_each(|(_, cell)| cells[*cell] = None);
+
+        let desktop_items = self.ls_desktop_items.borrow();
+        for (idx, cell) in current {
+            let cell = self.grid_cell(cell);
+            let column = cell.column as isize + target.column as isize - origin.column as isize;
+            let row = cell.row as isize + target.row as isize - origin.row as isize;
+            let wanted = GridCell {
+                column: column.clamp(0, columns - 1) as usize,
+                row: row.clamp(0, rows - 1) as usize,
+            };
+            let distance = |cell: &usize| {
+                let cell = self.grid_cell(*cell);
+                (cell.column as isize - wanted.column as isize).abs()
+                    + (cell.row as isize - wanted.row as isize).abs()
+            };
+
+            if let Some(free) = (0..cells.len())
+                .filter(|cell| cells[*cell].is_none())
+                .min_by_key(distance)
+            {
+                cells[free] = Some(idx);
+                self.layout.set_cell(
+                    self.resolution,
+                    &desktop_items[idx].path,
+                    self.grid_cell(free),
+                );
+            }
+        }
+        drop(desktop_items);
+
+        if let Err(err) = self.layout.save() {
+            eprintln!("{:?}", err);
+        }
+        self.arrange();
+    }
+
+    /// Items placed by hand only keep their cell with the Manual sorting, so switch to it
+    /// keeping every item where it is now.
+    fn use_manual_sorting(&mut self) {
+        let mut desktop_conf = self.desktop_conf.borrow_mut();
+
+        if desktop_conf.desktop_item_conf.sorting != Sorting::Manual {
+            let desktop_items = self.ls_desktop_items.borrow();
+            for (cell, idx) in self.cells.iter().enumerate() {
+                if let Some(item) = idx.and_then(|idx| desktop_items.get(idx)) {
+                    self.layout
+                        .set_cell(self.resolution, &item.path, self.grid_cell(cell));
+                }
+            }
+
+            desktop_conf.desktop_item_conf.sorting = Sorting::Manual;
+            if let Err(err) = desktop_conf.save() {
+                eprintln!("{:?}", err);
+            }
+            self.proxy
+                .send_event(ProxyMessage::DesktopConf(DesktopConfigMsg::SortingChanged(
+                    Sorting::Manual,
+                )))
+                .unwrap();
+        }
+    }
 }
 
 impl Application for Desktop {
     type Flags = (
+        EventLoopProxy<ProxyMessage>,
         (u32, u32),
         Rc<RefCell<DesktopConf>>,
         usize,
         Rc<RefCell<Vec<DesktopItem>>>,
+        (u32, u32),
     );
 
     fn new(flags: Self::Flags) -> (Self, Command<DesktopMsg>) {
-        (
-            Self {
-                size: flags.0,
-                desktop_conf: flags.1,
-                ls_desktop_items_state: vec![button::State::new(); flags.2],
-                ls_desktop_items: flags.3,
-                selected_desktop_item: None,
-            },
-            Command::none(),
-        )
+        let mut desktop = Self {
+            size: flags.1,
+            desktop_conf: flags.2,
+            ls_desktop_items_state: vec![button::State::new(); flags.3],
+            ls_desktop_items: flags.4,
+            selection: Selection::default(),
+            modifiers: keyboard::Modifiers::default(),
+            layout: DesktopLayout::load().unwrap_or_default(),
+            resolution: flags.5,
+            cells: Vec::new(),
+            columns: 1,
+            drag: DragState::Idle,
+            rename: None,
+            last_click: None,
+            proxy: flags.0,
+        };
+        desktop.arrange();
+
+        (desktop, Command::none())
     }
 
     fn title(&self) -> String {
@@ -97,43 +772,96 @@ impl Program for Desktop {
         _clipboard: &mut Clipboard,
     ) -> Command<Self::Message> {
         use DesktopMsg::*;
-        let desktop_items = self.ls_desktop_items.borrow();
 
         match message {
-            DesktopItemClicked(idx) => self.selected_desktop_item = Some(idx),
-            LaunchDesktopItem(idx) => self.handle_exec(idx),
+            DesktopItemClicked(idx) => self.on_item_clicked(idx),
+            LaunchDesktopItem(idx) => {
+                for idx in self.items_for(idx) {
+                    self.handle_exec(idx, &[]);
+                }
+            }
+            FilesDropped(files, position) => {
+                if let Some(idx) = self.item_at(position) {
+                    let is_trash =
+                        self.ls_desktop_items.borrow()[idx].entry_type == DesktopItemType::TRASH;
+                    if is_trash {
+                        self.trash_items(files);
+                    } else {
+                        self.handle_exec(idx, &files);
+                    }
+                }
+            }
+            DesktopItemsChanged => self.on_desktop_items_changed(),
+            DesktopItemRenamed(from, to) => {
+                if self.layout.rename(&from, &to) {
+                    if let Err(err) = self.layout.save() {
+                        eprintln!("{:?}", err);
+                    }
+                }
+                self.selection.rename(&from, &to);
+                self.on_desktop_items_changed();
+            }
+            ResolutionChanged(width, height) => {
+                self.resolution = (width, height);
+                self.arrange();
+            }
+            PointerPressed(position) => self.on_pointer_pressed(position),
+            PointerMoved(position) => self.on_pointer_moved(position),
+            PointerReleased(position) => self.on_pointer_released(position),
+            ContextMenuRequested(position) => self.request_context_menu(position),
+            // Shortcuts and the context menu can ask for them with nothing selected.
+            LaunchSelected | TrashSelected | RenameSelected if self.selection.is_empty() => {}
+            LaunchSelected => {
+                for idx in self.selected_items() {
+                    self.handle_exec(idx, &[]);
+                }
+            }
+            TrashSelected => self.trash_items(self.item_paths(&self.selected_items())),
+            StartRename(path) => self.start_rename(path),
+            RenameSelected => self.rename_selected(),
+            RenameChanged(value) => {
+                if let Some(rename) = &mut self.rename {
+                    rename.value = value;
+                }
+            }
+            RenameSubmitted => self.submit_rename(),
             WinitEvent(event) => match event {
-                Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
-                    self.selected_desktop_item = None
+                Event::Window(window::Event::Resized { width, height }) => {
+                    self.size = (width, height);
+                    self.arrange();
                 }
                 Event::Keyboard(key_event) => match key_event {
+                    keyboard::Event::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+                    keyboard::Event::KeyPressed {
+                        key_code: keyboard::KeyCode::Escape,
+                        ..
+                    } if self.rename.is_some() => self.rename = None,
+                    // Keys typed in the name being edited are not shortcuts.
+                    _ if self.rename.is_some() => {}
                     keyboard::Event::CharacterReceived('\r') => {
-                        if let Some(idx) = self.selected_desktop_item {
-                            self.handle_exec(idx);
+                        for idx in self.selected_items() {
+                            self.handle_exec(idx, &[]);
                         }
                     }
-                    keyboard::Event::KeyPressed { key_code, .. } => match key_code {
-                        keyboard::KeyCode::Right => {
-                            if let Some(idx) = &mut self.selected_desktop_item {
-                                if *idx < desktop_items.len() - 1 {
-                                    *idx += 1;
-                                } else {
-                                    *idx = 0;
-                                }
-                            } else {
-                                self.selected_desktop_item = Some(0);
-                            }
+                    keyboard::Event::KeyPressed {
+                        key_code,
+                        modifiers,
+                    } => match key_code {
+                        keyboard::KeyCode::Right => self.select_next(true),
+                        keyboard::KeyCode::Left => self.select_next(false),
+                        keyboard::KeyCode::A if modifiers.control => self.select_all(),
+                        keyboard::KeyCode::C if modifiers.control => {
+                            self.copy_selected(ProxyMessage::CopyItems)
                         }
-                        keyboard::KeyCode::Left => {
-                            if let Some(idx) = &mut self.selected_desktop_item {
-                                if *idx > 0 {
-                                    *idx -= 1;
-                                } else {
-                                    *idx = desktop_items.len() - 1;
-                                }
-                            } else {
-                                self.selected_desktop_item = Some(0);
-                            }
+                        keyboard::KeyCode::X if modifiers.control => {
+                            self.copy_selected(ProxyMessage::CutItems)
+                        }
+                        keyboard::KeyCode::V if modifiers.control => {
+                            self.proxy.send_event(ProxyMessage::PasteItems).unwrap()
+                        }
+                        keyboard::KeyCode::F2 => self.rename_selected(),
+                        keyboard::KeyCode::Delete => {
+                            self.trash_items(self.item_paths(&self.selected_items()))
                         }
                         _ => {}
                     },
@@ -148,11 +876,32 @@ impl Program for Desktop {
 
     fn view(&mut self) -> Element<Self::Message, Renderer> {
         use DesktopMsg::*;
+        // Highlight the folder or the free cell the dragged items would be dropped on.
+        let (drop_cell, drop_folder) = match &self.drag {
+            DragState::Dragging {
+                items,
+                target: Some(target),
+                ..
+            } => match self.drop_folder(*target, items) {
+                Some(folder) => (None, Some(folder)),
+                None => (Some(target.row * self.columns + target.column), None),
+            },
+            _ => (None, None),
+        };
+        let band = match &self.drag {
+            DragState::Selecting {
+                origin, current, ..
+            } => Some(rubber_band(*origin, *current)),
+            _ => None,
+        };
         let Self {
             desktop_conf,
             ls_desktop_items_state,
             ls_desktop_items,
-            selected_desktop_item,
+            selection,
+            cells,
+            columns,
+            rename,
             ..
         } = self;
         let desktop_items = ls_desktop_items.borrow();
@@ -160,85 +909,137 @@ impl Program for Desktop {
         let bg_conf = &desktop_conf.background_conf;
         let item_conf = &desktop_conf.desktop_item_conf;
 
+        // Only the item being renamed gets the text input.
+        let mut rename = rename.as_mut();
+
         let grid_spacing = item_conf.grid_spacing;
         let item_size = item_conf.icon_size + 40;
-        let item_size_spacing = item_size + (grid_spacing * 2);
-        let mut grid = Grid::new()
+        let (_, _, item_size_spacing) = Self::grid_layout(self.size, item_conf);
+        let grid = Grid::new()
             .column_width(item_size_spacing)
-            .padding(20)
+            .columns(*columns)
+            .padding(GRID_PADDING)
             .spacing(grid_spacing);
-        if let Arrangement::Columns = item_conf.arrangement {
-            let items_in_height =
-                usize::from(item_size_spacing + grid_spacing) * desktop_items.len();
-            grid = grid.columns((items_in_height as f32 / self.size.1 as f32).ceil() as usize);
-        }
 
-        let desktop_grid = ls_desktop_items_state
-            .iter_mut()
-            .zip(desktop_items.iter())
-            .enumerate()
-            .fold(grid, |grid, (idx, (state, item))| {
-                let icon: Element<Self::Message, Renderer> =
-                    if let Some(icon_path) = &item.icon_path {
-                        if let Some(extension) = icon_path.extension() {
-                            if extension == "svg" {
-                                Svg::from_path(icon_path)
-                                    .width(Length::Units(item_conf.icon_size))
-                                    .height(Length::Units(item_conf.icon_size))
-                                    .into()
+        let mut item_elements: Vec<Option<Element<Self::Message, Renderer>>> =
+            ls_desktop_items_state
+                .iter_mut()
+                .zip(desktop_items.iter())
+                .enumerate()
+                .map(|(idx, (state, item))| {
+                    let is_edited = rename
+                        .as_ref()
+                        .map_or(false, |rename| rename.path == item.path);
+                    let rename = if is_edited { rename.take() } else { None };
+                    let icon: Element<Self::Message, Renderer> =
+                        if let Some(icon_path) = &item.icon_path {
+                            if let Some(extension) = icon_path.extension() {
+                                if extension == "svg" {
+                                    Svg::from_path(icon_path)
+                                        .width(Length::Units(item_conf.icon_size))
+                                        .height(Length::Units(item_conf.icon_size))
+                                        .into()
+                                } else {
+                                    Image::new(icon_path)
+                                        .width(Length::Units(item_conf.icon_size))
+                                        .height(Length::Units(item_conf.icon_size))
+                                        .into()
+                                }
                             } else {
-                                Image::new(icon_path)
-                                    .width(Length::Units(item_conf.icon_size))
-                                    .height(Length::Units(item_conf.icon_size))
-                                    .into()
+                                Row::new().into()
                             }
                         } else {
                             Row::new().into()
+                        };
+                    let name: Element<Self::Message, Renderer> = match rename {
+                        Some(Rename {
+                            value,
+                            state: input_state,
+                            ..
+                        }) => TextInput::new(input_state, "", value, RenameChanged)
+                            .on_submit(RenameSubmitted)
+                            .padding(3)
+                            .into(),
+                        None => {
+                            Text::new(item.name.as_ref().unwrap_or(&"Unknown name".to_string()))
+                                .horizontal_alignment(HorizontalAlignment::Center)
+                                .into()
                         }
-                    } else {
-                        Row::new().into()
                     };
-                let con = Column::new()
-                    .spacing(10)
-                    .align_items(Align::Center)
-                    .push(icon)
-                    .push(
-                        Text::new(item.name.as_ref().unwrap_or(&"Unknown name".to_string()))
-                            .horizontal_alignment(HorizontalAlignment::Center),
-                    );
-
-                let mut btn = Button::new(state, con)
-                    .width(Length::Units(item_size))
-                    .padding(7)
-                    .on_press(DesktopItemClicked(idx))
-                    .on_double_click(LaunchDesktopItem(idx));
-                if let Some(curr_idx) = *selected_desktop_item {
-                    if curr_idx == idx {
+                    let con = Column::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(icon)
+                        .push(name);
+
+                    let mut btn = Button::new(state, con)
+                        .width(Length::Units(item_size))
+                        .padding(7);
+                    // The item being renamed leaves its clicks to the text input.
+                    if !is_edited {
+                        btn = btn
+                            .on_press(DesktopItemClicked(idx))
+                            .on_double_click(LaunchDesktopItem(idx));
+                    }
+                    if drop_folder == Some(idx) {
+                        btn = btn.style(CustomButton::DropTarget);
+                    } else if selection.contains(&item.path) {
                         btn = btn.style(CustomButton::Selected);
                     } else {
                         btn = btn.style(CustomButton::Transparent);
                     }
-                } else {
-                    btn = btn.style(CustomButton::Transparent);
-                }
 
-                let tooltip_btn: Element<Self::Message, Renderer> = if item_conf.show_tooltip {
-                    if let Some(cmt) = &item.comment {
-                        Tooltip::new(btn, cmt, tooltip::Position::FollowCursor)
-                            .size(12)
-                            .gap(5)
-                            .padding(5)
-                            .style(CustomTooltip)
-                            .into()
+                    let tooltip_btn: Element<Self::Message, Renderer> = if item_conf.show_tooltip {
+                        if let Some(cmt) = item.comment.as_ref().or(item.generic_name.as_ref()) {
+                            Tooltip::new(btn, cmt, tooltip::Position::FollowCursor)
+                                .size(12)
+                                .gap(5)
+                                .padding(5)
+                                .style(CustomTooltip)
+                                .into()
+                        } else {
+                            btn.into()
+                        }
                     } else {
                         btn.into()
-                    }
-                } else {
-                    btn.into()
-                };
+                    };
 
-                grid.push(Container::new(tooltip_btn).center_x().center_y())
-            });
+                    Some(
+                        Container::new(tooltip_btn)
+                            .width(Length::Units(item_size_spacing))
+                            .height(Length::Units(item_size_spacing))
+                            .center_x()
+                            .center_y()
+                            .into(),
+                    )
+                })
+                .collect();
+        // Empty cells up to the last item keep the others in place.
+        let num_cells = cells
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |cell| cell + 1)
+            .max(drop_cell.map_or(0, |cell| cell + 1))
+            .min(cells.len());
+        let desktop_grid =
+            cells[..num_cells]
+                .iter()
+                .enumerate()
+                .fold(grid, |grid, (cell_idx, cell)| {
+                    match cell.and_then(|idx| item_elements.get_mut(idx)?.take()) {
+                        Some(item_element) => grid.push(item_element),
+                        None if drop_cell == Some(cell_idx) => grid.push(
+                            Container::new(Space::new(Length::Fill, Length::Fill))
+                                .width(Length::Units(item_size_spacing))
+                                .height(Length::Units(item_size_spacing))
+                                .style(CustomContainer::DropTarget),
+                        ),
+                        None => grid.push(Space::new(
+                            Length::Units(item_size_spacing),
+                            Length::Units(item_size_spacing),
+                        )),
+                    }
+                });
 
         let desktop_sec: Element<_, _> = match bg_conf.kind {
             BackgroundType::Color => desktop_grid.into(),
@@ -265,9 +1066,69 @@ impl Program for Desktop {
             }
         };
 
+        let desktop_sec: Element<_, _> = match band {
+            Some(bounds) => Stack::new()
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .push(desktop_sec, None)
+                .push(
+                    Column::new()
+                        .push(Space::with_height(Length::Units(bounds.y as u16)))
+                        .push(
+                            Row::new()
+                                .push(Space::with_width(Length::Units(bounds.x as u16)))
+                                .push(
+                                    Container::new(Space::new(Length::Fill, Length::Fill))
+                                        .width(Length::Units(bounds.width as u16))
+                                        .height(Length::Units(bounds.height as u16))
+                                        .style(CustomContainer::RubberBand),
+                                ),
+                        ),
+                    None,
+                )
+                .into(),
+            None => desktop_sec,
+        };
+
         Container::new(desktop_sec)
             .width(Length::Fill)
             .height(Length::Fill)
             .into()
     }
 }
+
+/// Rectangle between the two corners of a rubber band.
+fn rubber_band(origin: Point, current: Point) -> Rectangle {
+    Rectangle::new(
+        Point::new(origin.x.min(current.x), origin.y.min(current.y)),
+        Size::new((origin.x - current.x).abs(), (origin.y - current.y).abs()),
+    )
+}
+
+/// Ask whether to trust a launcher that is not yet, and mark it trusted if so.
+pub fn ask_to_trust(item: &DesktopItem) -> bool {
+    let selection = DialogBuilder::new()
+        .title("Untrusted Application Launcher")
+        .message(&format!(
+            "The application launcher \"{}\" has not been marked as trusted. Launchers can run any command, only trust those from sources you know. Do you want to trust and launch it?",
+            item.display_name()
+        ))
+        .buttons(DialogButtons::YesNo)
+        .style(DialogStyle::Warning)
+        .build()
+        .show();
+    if let DialogSelection::Yes = selection {
+        match item.mark_trusted() {
+            Ok(()) => return true,
+            Err(err) => {
+                let _ = DialogBuilder::new()
+                    .title("Error")
+                    .message(&format!("{}", err))
+                    .style(DialogStyle::Error)
+                    .build()
+                    .show();
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Desktop items selected by the user. Items are kept by path so the selection survives the
+/// desktop items being reloaded or sorted again.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    paths: HashSet<PathBuf>,
+    // Item a Shift+click range starts from.
+    anchor: Option<PathBuf>,
+}
+
+impl Selection {
+    pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.paths.contains(path.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn anchor(&self) -> Option<&Path> {
+        self.anchor.as_deref()
+    }
+
+    /// Select `path` alone.
+    pub fn select<P: AsRef<Path>>(&mut self, path: P) {
+        self.paths.clear();
+        self.paths.insert(path.as_ref().to_path_buf());
+        self.anchor = Some(path.as_ref().to_path_buf());
+    }
+
+    pub fn toggle<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref().to_path_buf();
+
+        if !self.paths.remove(&path) {
+            self.paths.insert(path.clone());
+        }
+        self.anchor = Some(path);
+    }
+
+    /// Replace the selected items, keeping the anchor.
+    pub fn set<I: IntoIterator<Item = PathBuf>>(&mut self, paths: I) {
+        self.paths = paths.into_iter().collect();
+    }
+
+    pub fn extend<I: IntoIterator<Item = PathBuf>>(&mut self, paths: I) {
+        self.paths.extend(paths);
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.anchor = None;
+    }
+
+    /// Follow an item renamed from `from` to `to`.
+    pub fn rename<P: AsRef<Path>>(&mut self, from: P, to: P) {
+        if self.paths.remove(from.as_ref()) {
+            self.paths.insert(to.as_ref().to_path_buf());
+        }
+        if self.anchor.as_deref() == Some(from.as_ref()) {
+            self.anchor = Some(to.as_ref().to_path_buf());
+        }
+    }
+
+    /// Forget the items no longer on the desktop.
+    pub fn retain<F: Fn(&Path) -> bool>(&mut self, is_kept: F) {
+        self.paths.retain(|path| is_kept(path));
+        if !self.anchor.as_deref().map_or(true, &is_kept) {
+            self.anchor = None;
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use super::copy_progress::format_size;
+use super::styles::BACKGROUND;
+use crate::desktop_item::{DesktopItem, DesktopItemType};
+use chrono::{DateTime, Local};
+use iced::{Image, Svg};
+use iced_wgpu::Renderer;
+use iced_winit::{
+    Align, Application, Clipboard, Color, Column, Command, Element, Length, Program, Row, Space,
+    Text,
+};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const ICON_SIZE: u16 = 64;
+const LABEL_WIDTH: u16 = 90;
+
+/// Information about a desktop item, gathered once when the window opens.
+#[derive(Debug)]
+pub struct PropertiesUI {
+    name: String,
+    icon_path: Option<PathBuf>,
+    fields: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PropertiesMsg {}
+
+impl Application for PropertiesUI {
+    type Flags = DesktopItem;
+
+    fn new(item: Self::Flags) -> (Self, Command<PropertiesMsg>) {
+        let name = item.name.to_owned().unwrap_or_else(|| {
+            item.path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        let kind = match item.entry_type {
+            DesktopItemType::APP(_) => "Application Launcher",
+            DesktopItemType::DIR => "Folder",
+            DesktopItemType::LINK(_) => "Link",
+            _ => "File",
+        };
+        let mut fields = vec![("Type", format!("{} ({})", kind, item.mime_type()))];
+
+        if let DesktopItemType::LINK(url) = &item.entry_type {
+            fields.push(("URL", url.to_owned()));
+        }
+        if let Some(parent) = item.path.parent() {
+            fields.push(("Location", parent.to_string_lossy().into_owned()));
+        }
+        if item.entry_type == DesktopItemType::DIR {
+            let (num_items, size) = folder_size(&item.path);
+            fields.push((
+                "Contents",
+                format!("{} items, totalling {}", num_items, format_size(size)),
+            ));
+        }
+        if let Ok(metadata) = fs::metadata(&item.path) {
+            if item.entry_type != DesktopItemType::DIR {
+                fields.push(("Size", format_size(metadata.len())));
+            }
+            if let Ok(modified) = metadata.modified() {
+                fields.push(("Modified", format_time(modified.into())));
+            }
+            if let Ok(accessed) = metadata.accessed() {
+                fields.push(("Accessed", format_time(accessed.into())));
+            }
+            fields.push(("Permissions", permissions(metadata.permissions().mode())));
+        }
+
+        (
+            Self {
+                name,
+                icon_path: item.icon_path,
+                fields,
+            },
+            Command::none(),
+        )
+    }
+
+    fn title(&self) -> String {
+        format!("{} Properties", self.name)
+    }
+
+    fn background_color(&self) -> Color {
+        BACKGROUND
+    }
+}
+
+impl Program for PropertiesUI {
+    type Message = PropertiesMsg;
+    type Renderer = Renderer;
+    type Clipboard = Clipboard;
+
+    fn update(&mut self, msg: Self::Message, _clipboard: &mut Clipboard) -> Command<Self::Message> {
+        match msg {}
+    }
+
+    fn view(&mut self) -> Element<Self::Message, Renderer> {
+        let icon: Element<Self::Message, Renderer> = match &self.icon_path {
+            Some(icon_path) if icon_path.extension().map_or(false, |ext| ext == "svg") => {
+                Svg::from_path(icon_path)
+                    .width(Length::Units(ICON_SIZE))
+                    .height(Length::Units(ICON_SIZE))
+                    .into()
+            }
+            Some(icon_path) => Image::new(icon_path)
+                .width(Length::Units(ICON_SIZE))
+                .height(Length::Units(ICON_SIZE))
+                .into(),
+            None => Space::new(Length::Units(ICON_SIZE), Length::Units(ICON_SIZE)).into(),
+        };
+        let header = Row::new()
+            .spacing(15)
+            .align_items(Align::Center)
+            .push(icon)
+            .push(Text::new(self.name.as_str()).size(16));
+
+        self.fields
+            .iter()
+            .fold(
+                Column::new().padding(15).spacing(10).push(header),
+                |column, (label, value)| {
+                    column.push(
+                        Row::new()
+                            .spacing(10)
+                            .push(Text::new(*label).width(Length::Units(LABEL_WIDTH)))
+                            .push(Text::new(value.as_str()).width(Length::Fill)),
+                    )
+                },
+            )
+            .into()
+    }
+}
+
+/// Number of items in a folder and their size, subfolders included.
+fn folder_size(path: &Path) -> (usize, u64) {
+    WalkDir::new(path)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .fold((0, 0), |(num_items, size), entry| {
+            let len = entry.metadata().map_or(0, |metadata| {
+                if metadata.is_file() {
+                    metadata.len()
+                } else {
+                    0
+                }
+            });
+            (num_items + 1, size + len)
+        })
+}
+
+fn format_time(time: DateTime<Local>) -> String {
+    time.format("%B %-d, %Y %H:%M").to_string()
+}
+
+/// Permissions of the owner, the group and others in the style of `ls -l`.
+fn permissions(mode: u32) -> String {
+    (0..9)
+        .rev()
+        .map(|bit| match (mode >> bit & 1, bit % 3) {
+            (0, _) => '-',
+            (_, 2) => 'r',
+            (_, 1) => 'w',
+            _ => 'x',
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+use super::styles::{CustomButton, BACKGROUND};
+use crate::desktop_item::{DesktopItem, ScriptAction};
+use crate::proxy_message::ProxyMessage;
+use iced_wgpu::Renderer;
+use iced_winit::{
+    button, winit, Align, Application, Button, Clipboard, Color, Column, Command, Element, Length,
+    Program, Row, Space, Text,
+};
+use std::path::{Path, PathBuf};
+use winit::event_loop::EventLoopProxy;
+
+/// Asks whether an executable script is run or displayed when it is opened.
+#[derive(Debug)]
+pub struct ScriptPromptUI {
+    path: PathBuf,
+    name: String,
+    btn_terminal_state: button::State,
+    btn_display_state: button::State,
+    btn_cancel_state: button::State,
+    btn_run_state: button::State,
+    proxy: EventLoopProxy<ProxyMessage>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ScriptPromptMsg {
+    ActionChosen(PathBuf, ScriptAction),
+    CancelClicked(PathBuf),
+}
+
+impl ScriptPromptUI {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Application for ScriptPromptUI {
+    type Flags = (EventLoopProxy<ProxyMessage>, DesktopItem);
+
+    fn new(flags: Self::Flags) -> (Self, Command<ScriptPromptMsg>) {
+        let (proxy, item) = flags;
+
+        (
+            Self {
+                name: item.display_name(),
+                path: item.path,
+                btn_terminal_state: Default::default(),
+                btn_display_state: Default::default(),
+                btn_cancel_state: Default::default(),
+                btn_run_state: Default::default(),
+                proxy,
+            },
+            Command::none(),
+        )
+    }
+
+    fn title(&self) -> String {
+        String::from("Run Script")
+    }
+
+    fn background_color(&self) -> Color {
+        BACKGROUND
+    }
+}
+
+impl Program for ScriptPromptUI {
+    type Message = ScriptPromptMsg;
+    type Renderer = Renderer;
+    type Clipboard = Clipboard;
+
+    fn update(&mut self, msg: Self::Message, _clipboard: &mut Clipboard) -> Command<Self::Message> {
+        self.proxy
+            .send_event(ProxyMessage::ScriptPrompt(msg))
+            .unwrap();
+
+        Command::none()
+    }
+
+    fn view(&mut self) -> Element<Self::Message, Renderer> {
+        use ScriptPromptMsg::*;
+        let Self {
+            path,
+            name,
+            btn_terminal_state,
+            btn_display_state,
+            btn_cancel_state,
+            btn_run_state,
+            ..
+        } = self;
+
+        let lb_question = Text::new(format!(
+            "Do you want to run \"{}\", or display its contents?",
+            name
+        ))
+        .size(16);
+        let lb_details = Text::new(format!("\"{}\" is an executable text file.", name)).size(13);
+        let btn_terminal = Button::new(btn_terminal_state, Text::new("  Run in Terminal  "))
+            .on_press(ActionChosen(path.to_owned(), ScriptAction::RunInTerminal))
+            .style(CustomButton::Default);
+        let btn_display = Button::new(btn_display_state, Text::new("  Display  "))
+            .on_press(ActionChosen(path.to_owned(), ScriptAction::Display))
+            .style(CustomButton::Default);
+        let btn_cancel = Button::new(btn_cancel_state, Text::new("  Cancel  "))
+            .on_press(CancelClicked(path.to_owned()))
+            .style(CustomButton::Secondary);
+        let btn_run = Button::new(btn_run_state, Text::new("  Run  "))
+            .on_press(ActionChosen(path.to_owned(), ScriptAction::Run))
+            .style(CustomButton::Primary);
+
+        Column::new()
+            .padding(15)
+            .spacing(10)
+            .width(Length::Fill)
+            .push(lb_question)
+            .push(lb_details)
+            .push(Space::with_height(Length::Fill))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(btn_terminal)
+                    .push(Space::with_width(Length::Fill))
+                    .push(btn_display)
+                    .push(btn_cancel)
+                    .push(btn_run),
+            )
+            .into()
+    }
+}
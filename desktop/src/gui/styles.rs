@@ -17,6 +17,7 @@ pub enum CustomButton {
     Transparent,
     Selected,
     Hovered,
+    DropTarget,
 }
 
 impl button::StyleSheet for CustomButton {
@@ -26,7 +27,7 @@ impl button::StyleSheet for CustomButton {
             text_color: match self {
                 Primary => PRIMARY,
                 Secondary => SECONDARY,
-                Transparent | Selected => Color::WHITE,
+                Transparent | Selected | DropTarget => Color::WHITE,
                 _ => Color::BLACK,
             },
             background: Some(
@@ -39,12 +40,16 @@ impl button::StyleSheet for CustomButton {
                         ..SECONDARY
                     },
                     Hovered => Color { a: 0.3, ..HOVERED },
+                    DropTarget => Color { a: 0.3, ..PRIMARY },
                     _ => Color::TRANSPARENT,
                 }
                 .into(),
             ),
             border_radius: 7.0,
-            border_color: Color::TRANSPARENT,
+            border_color: match self {
+                DropTarget => PRIMARY,
+                _ => Color::TRANSPARENT,
+            },
             border_width: 1.0,
             shadow_offset: match self {
                 Default => Vector::new(0.5, 0.5),
@@ -76,6 +81,8 @@ impl button::StyleSheet for CustomButton {
 pub enum CustomContainer {
     // Background,
     Foreground,
+    DropTarget,
+    RubberBand,
     // Primary,
     // Secondary,
     // Success,
@@ -90,6 +97,8 @@ impl container::StyleSheet for CustomContainer {
             background: Some(match self {
                 // Background => BACKGROUND,
                 Foreground => FOREGROUND,
+                DropTarget => Color { a: 0.3, ..PRIMARY },
+                RubberBand => Color { a: 0.2, ..PRIMARY },
                 // Primary => PRIMARY,
                 // Secondary => SECONDARY,
                 // Success => SUCCESS,
@@ -97,7 +106,15 @@ impl container::StyleSheet for CustomContainer {
                 // Error => ERROR,
                 // Hovered => HOVERED
             }.into()),
-            border_radius: 7.0,
+            border_radius: match self {
+                RubberBand => 0.0,
+                _ => 7.0,
+            },
+            border_width: match self {
+                RubberBand => 1.0,
+                _ => 0.0,
+            },
+            border_color: PRIMARY,
             ..container::Style::default()
         }
     }
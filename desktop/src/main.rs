@@ -1,6 +1,8 @@
 mod background;
+mod clipboard;
 mod configs;
 mod constants;
+mod copy_engine;
 mod desktop_item;
 mod desktop_manager;
 mod errors;
@@ -9,13 +11,17 @@ mod proxy_message;
 mod window_state;
 use background::WallpaperItem;
 use configs::PersistentData;
-use desktop_item::DesktopItem;
+use constants::FILE_SCHEME;
+use copy_engine::{CopyMsg, CopyStatus, Transfer};
+use desktop_item::{DesktopItem, DesktopItemError};
 use desktop_manager::DesktopManager;
+use errors::DesktopError;
 use tauri_dialog::{DialogBuilder, DialogButtons, DialogSelection, DialogStyle};
 
 use gui::{
-    BackgroundConfMsg, BackgroundConfigUI, ContextMenu, ContextMsg, Desktop, DesktopConfigMsg,
-    DesktopConfigUI,
+    BackgroundConfMsg, BackgroundConfigUI, ContextMenu, ContextMsg, CopyProgressMsg,
+    CopyProgressUI, Desktop, DesktopConfigMsg, DesktopConfigUI, DesktopMsg, PropertiesUI,
+    ScriptPromptMsg, ScriptPromptUI,
 };
 use proxy_message::ProxyMessage;
 use std::{cell::RefCell, rc::Rc};
@@ -24,18 +30,27 @@ use window_state::WindowState;
 use futures::{channel::mpsc, task};
 use iced::executor;
 use iced_wgpu::{wgpu, Settings};
-use iced_winit::{button, futures, winit, Application, Debug, Executor, Proxy, Runtime};
+use iced_winit::{button, futures, winit, Application, Debug, Executor, Point, Proxy, Runtime};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    platform::unix::{WindowBuilderExtUnix, XWindowType},
+    platform::unix::{WindowBuilderExtUnix, WindowExtUnix, XWindowType},
     window::WindowBuilder,
 };
 
+/// Jobs of the copy engine finishing sooner don't show their progress.
+const COPY_PROGRESS_DELAY: Duration = Duration::from_millis(500);
+
 fn main() {
     std::env::set_var("WINIT_X11_SCALE_FACTOR", "1.25");
+    // Launched applications and desktop entry filtering rely on it.
+    if std::env::var_os("XDG_CURRENT_DESKTOP").is_none() {
+        std::env::set_var("XDG_CURRENT_DESKTOP", freedesktop::desktop_entry::CURRENT_DESKTOP);
+    }
     match DesktopManager::new() {
         Ok(mut desktop_manager) => {
             let mut old_desktop_conf = desktop_manager.config().to_owned();
@@ -49,6 +64,13 @@ fn main() {
             let mut windows = HashMap::new();
             let event_loop = EventLoop::with_user_event();
             let event_proxy = event_loop.create_proxy();
+            if let Err(err) = desktop_manager.watch_desktop_dir(event_loop.create_proxy()) {
+                eprintln!("{:?}", err);
+            }
+            desktop_manager.start_thumbnailer(event_loop.create_proxy());
+            desktop_manager.start_copy_engine(event_loop.create_proxy());
+            let copy_status = Rc::new(RefCell::new(CopyStatus::new(Transfer::Copy, 0)));
+            let mut copy_window = None;
             let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
             let mut runtime = {
                 let proxy = Proxy::new(event_loop.create_proxy());
@@ -62,22 +84,29 @@ fn main() {
                 default_text_size: 13,
                 ..Settings::default()
             };
-            let (monitor_size, monitor_position) = event_loop
+            let (monitor_size, monitor_position, scale_factor) = event_loop
                 .primary_monitor()
-                .map(|m| (m.size(), m.position()))
-                .unwrap_or((PhysicalSize::new(1920, 1080), PhysicalPosition::new(0, 0)));
+                .map(|m| (m.size(), m.position(), m.scale_factor()))
+                .unwrap_or((
+                    PhysicalSize::new(1920, 1080),
+                    PhysicalPosition::new(0, 0),
+                    1.0,
+                ));
             let mut cursor_position = PhysicalPosition::new(-1.0, -1.0);
             let mut debug = Debug::new();
 
             // Desktop Init Section
             let desktop_state = {
                 let (desktop, init_cmd) = {
+                    let desktop_size = monitor_size.to_logical::<u32>(scale_factor);
                     runtime.enter(|| {
                         Desktop::new((
-                            (monitor_size.width, monitor_size.height),
+                            event_proxy.to_owned(),
+                            (desktop_size.width, desktop_size.height),
                             Rc::clone(&desktop_conf),
                             desktop_items.borrow().len(),
                             Rc::clone(&desktop_items),
+                            (monitor_size.width, monitor_size.height),
                         ))
                     })
                 };
@@ -103,10 +132,18 @@ fn main() {
                 ))
             };
 
+            if let Some(window) = desktop_state.window.xlib_window() {
+                let proxy = event_loop.create_proxy();
+                if let Err(err) = clipboard::accept_drops(window as u32, proxy) {
+                    eprintln!("{:?}", err);
+                }
+            }
+
             // Context Menu Init Section
-            let context_menu_size = PhysicalSize::new(300.0, 210.0);
+            let (context_menu, _) = ContextMenu::new(event_proxy.to_owned());
+            let (width, height) = context_menu.size();
+            let context_menu_size = PhysicalSize::new(width, height);
             let context_menu_state = {
-                let (context_menu, _) = ContextMenu::new(event_proxy.to_owned());
                 let context_menu_window = WindowBuilder::new()
                     .with_x11_window_type(vec![XWindowType::Desktop, XWindowType::PopupMenu])
                     .with_position(cursor_position)
@@ -153,6 +190,161 @@ fn main() {
                                 let mut desktop_items = desktop_items.borrow_mut();
                                 *desktop_items = desktop_manager.desktop_items().to_owned();
                             }
+                            ProxyMessage::ItemCreated(path) => {
+                                if desktop_manager.on_item_created(path) {
+                                    let mut desktop_items = desktop_items.borrow_mut();
+                                    *desktop_items = desktop_manager.desktop_items().to_owned();
+                                }
+                            }
+                            ProxyMessage::ItemRemoved(path) => {
+                                if desktop_manager.on_item_removed(path) {
+                                    let mut desktop_items = desktop_items.borrow_mut();
+                                    *desktop_items = desktop_manager.desktop_items().to_owned();
+                                }
+                            }
+                            ProxyMessage::ItemRenamed(from, to) => {
+                                if desktop_manager.on_item_renamed(from, to) {
+                                    let mut desktop_items = desktop_items.borrow_mut();
+                                    *desktop_items = desktop_manager.desktop_items().to_owned();
+                                }
+                            }
+                            ProxyMessage::ItemModified(path) | ProxyMessage::ThumbnailReady(path) => {
+                                if desktop_manager.on_item_modified(path) {
+                                    let mut desktop_items = desktop_items.borrow_mut();
+                                    *desktop_items = desktop_manager.desktop_items().to_owned();
+                                }
+                            }
+                            ProxyMessage::MoveItems(paths, dest) => {
+                                if let Err(err) = desktop_manager.move_items(&paths, dest) {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::UrlsDropped(urls, _) => {
+                                if let Err(err) = desktop_manager.create_links(&urls) {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::TrashItems(paths) => {
+                                if let Err(err) = desktop_manager.move_to_trash(&paths) {
+                                    show_error(err);
+                                }
+                                let mut desktop_items = desktop_items.borrow_mut();
+                                *desktop_items = desktop_manager.desktop_items().to_owned();
+                            }
+                            ProxyMessage::RenameItem(path, name) => {
+                                let res = match desktop_manager.rename_item(&path, &name) {
+                                    Err(DesktopError::NameTaken(name, suggestion)) => {
+                                        let selection = DialogBuilder::new()
+                                            .title("Rename")
+                                            .message(&format!(
+                                                "An item named {} already exists. Do you want to name it {} instead?",
+                                                name, suggestion
+                                            ))
+                                            .buttons(DialogButtons::YesNo)
+                                            .style(DialogStyle::Question)
+                                            .build()
+                                            .show();
+                                        match selection {
+                                            DialogSelection::Yes => desktop_manager.rename_item(&path, &suggestion),
+                                            _ => Ok(()),
+                                        }
+                                    }
+                                    res => res,
+                                };
+                                if let Err(err) = res {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::CopyItems(paths) => {
+                                if let Err(err) = desktop_manager.copy_to_clipboard(&paths, false) {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::CutItems(paths) => {
+                                if let Err(err) = desktop_manager.copy_to_clipboard(&paths, true) {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::PasteItems => {
+                                if let Err(err) = desktop_manager.paste() {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::Copy(msg) => match msg {
+                                CopyMsg::Started { .. } => {
+                                    copy_status.borrow_mut().update(msg);
+                                    // Copy Progress Init Section
+                                    let (copy_progress, _) = CopyProgressUI::new((
+                                        event_proxy.to_owned(),
+                                        Rc::clone(&copy_status),
+                                    ));
+                                    let copy_progress_window = WindowBuilder::new()
+                                        .with_x11_window_type(vec![
+                                            XWindowType::Normal,
+                                            XWindowType::Dialog,
+                                        ])
+                                        .with_inner_size(PhysicalSize::new(450, 250))
+                                        .with_title(copy_progress.title())
+                                        .with_resizable(false)
+                                        .with_maximized(false)
+                                        .with_visible(false)
+                                        .build(&event_loop)
+                                        .unwrap();
+                                    copy_window = Some(copy_progress_window.id());
+                                    windows.insert(
+                                        copy_progress_window.id(),
+                                        DynWinState::CopyProgress(futures::executor::block_on(
+                                            WindowState::new(
+                                                &instance,
+                                                copy_progress_window,
+                                                copy_progress,
+                                                false,
+                                                Some(&settings),
+                                                None,
+                                            ),
+                                        )),
+                                    );
+                                }
+                                CopyMsg::Finished { errors } => {
+                                    if let Some(window_id) = copy_window.take() {
+                                        windows.remove(&window_id);
+                                    }
+                                    if !errors.is_empty() {
+                                        show_error(errors.join("\n"));
+                                    }
+                                }
+                                msg => {
+                                    copy_status.borrow_mut().update(msg);
+                                    let status = copy_status.borrow();
+                                    let window = copy_window.and_then(|window_id| windows.get(&window_id));
+                                    if let Some(DynWinState::CopyProgress(state)) = window {
+                                        if status.conflict.is_some() || status.started.elapsed() >= COPY_PROGRESS_DELAY {
+                                            state.window.set_visible(true);
+                                        }
+                                    }
+                                }
+                            },
+                            ProxyMessage::CopyProgress(CopyProgressMsg::Resolved(resolution)) => {
+                                desktop_manager.resolve_conflict(resolution);
+                            }
+                            ProxyMessage::CopyProgress(CopyProgressMsg::CancelClicked) => {
+                                desktop_manager.cancel_copy();
+                            }
+                            ProxyMessage::TrashChanged => {
+                                if desktop_manager.on_trash_changed() {
+                                    let mut desktop_items = desktop_items.borrow_mut();
+                                    *desktop_items = desktop_manager.desktop_items().to_owned();
+                                }
+                            }
+                            ProxyMessage::ItemsRescanned => {
+                                match desktop_manager.rescan_desktop_items() {
+                                    Ok(()) => {
+                                        let mut desktop_items = desktop_items.borrow_mut();
+                                        *desktop_items = desktop_manager.desktop_items().to_owned();
+                                    }
+                                    Err(err) => eprintln!("{:?}", err),
+                                }
+                            }
                             ProxyMessage::Bg(BackgroundConfMsg::AddWallpaperClicked) => {
                                 if let nfd2::Response::Okay(file_path) =
                                     nfd2::open_file_dialog(Some("png,jpg"), None).expect("oh no")
@@ -167,33 +359,149 @@ fn main() {
                                             let _ = desktop_conf.save();
                                             old_desktop_conf = desktop_conf.to_owned();
                                         }
-                                        Err(err) => {
-                                            let _ = DialogBuilder::new()
-                                                .title("Error")
-                                                .message(&format!("{}", err))
-                                                .style(DialogStyle::Error)
-                                                .build()
-                                                .show();
-                                        }
+                                        Err(err) => show_error(err),
+                                    }
+                                }
+                            }
+                            ProxyMessage::OpenWith(paths, app_path) => {
+                                let desktop_conf = desktop_conf.borrow();
+                                let preferred_terminal = desktop_conf.terminal_conf().preferred_terminal.as_deref();
+                                if let Err(err) = desktop_item::open_with(&app_path, &paths, preferred_terminal) {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::AlwaysOpenWith(paths, app_path, mime_type) => {
+                                let desktop_conf = desktop_conf.borrow();
+                                let preferred_terminal = desktop_conf.terminal_conf().preferred_terminal.as_deref();
+                                let res = desktop_item::set_default_app(&mime_type, &app_path)
+                                    .and_then(|_| desktop_item::open_with(&app_path, &paths, preferred_terminal));
+                                if let Err(err) = res {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::LaunchAction(path, id) => {
+                                let desktop_conf = desktop_conf.borrow();
+                                let preferred_terminal = desktop_conf.terminal_conf().preferred_terminal.as_deref();
+                                let res = DesktopItem::new(&path).and_then(|item| match item.launch_action(&id, preferred_terminal) {
+                                    Err(DesktopItemError::Untrusted(_)) if gui::ask_to_trust(&item) => item.launch_action(&id, preferred_terminal),
+                                    Err(DesktopItemError::Untrusted(_)) => Ok(()),
+                                    res => res,
+                                });
+                                if let Err(err) = res {
+                                    show_error(err);
+                                }
+                            }
+                            ProxyMessage::ShowProperties(path) => {
+                                if let Some(item) = desktop_manager.desktop_items().iter().find(|item| item.path == path) {
+                                    // Properties Init Section
+                                    let (properties, _) = PropertiesUI::new(item.to_owned());
+                                    let properties_window = WindowBuilder::new()
+                                        .with_x11_window_type(vec![
+                                            XWindowType::Normal,
+                                            XWindowType::Utility,
+                                        ])
+                                        .with_inner_size(PhysicalSize::new(400, 320))
+                                        .with_title(properties.title())
+                                        .with_resizable(false)
+                                        .with_maximized(false)
+                                        .with_visible(false)
+                                        .build(&event_loop)
+                                        .unwrap();
+                                    windows.insert(
+                                        properties_window.id(),
+                                        DynWinState::Properties(futures::executor::block_on(
+                                            WindowState::new(
+                                                &instance,
+                                                properties_window,
+                                                properties,
+                                                true,
+                                                Some(&settings),
+                                                None,
+                                            ),
+                                        )),
+                                    );
+                                }
+                            }
+                            ProxyMessage::ShowScriptPrompt(path) => {
+                                if let Some(item) = desktop_manager.desktop_items().iter().find(|item| item.path == path) {
+                                    // Script Prompt Init Section
+                                    let (script_prompt, _) = ScriptPromptUI::new((event_proxy.to_owned(), item.to_owned()));
+                                    let script_prompt_window = WindowBuilder::new()
+                                        .with_x11_window_type(vec![
+                                            XWindowType::Normal,
+                                            XWindowType::Dialog,
+                                        ])
+                                        .with_inner_size(PhysicalSize::new(520, 140))
+                                        .with_title(script_prompt.title())
+                                        .with_resizable(false)
+                                        .with_maximized(false)
+                                        .with_visible(false)
+                                        .build(&event_loop)
+                                        .unwrap();
+                                    windows.insert(
+                                        script_prompt_window.id(),
+                                        DynWinState::ScriptPrompt(futures::executor::block_on(
+                                            WindowState::new(
+                                                &instance,
+                                                script_prompt_window,
+                                                script_prompt,
+                                                true,
+                                                Some(&settings),
+                                                None,
+                                            ),
+                                        )),
+                                    );
+                                }
+                            }
+                            ProxyMessage::ScriptPrompt(msg) => {
+                                let path = match &msg {
+                                    ScriptPromptMsg::ActionChosen(path, _) | ScriptPromptMsg::CancelClicked(path) => path,
+                                };
+                                windows.retain(|_, state| !matches!(state, DynWinState::ScriptPrompt(state) if state.application().path() == path));
+                                if let ScriptPromptMsg::ActionChosen(path, action) = msg {
+                                    let desktop_conf = desktop_conf.borrow();
+                                    let preferred_terminal = desktop_conf.terminal_conf().preferred_terminal.as_deref();
+                                    if let Err(err) = DesktopItem::new(&path).and_then(|item| item.open_script(action, preferred_terminal)) {
+                                        show_error(err);
                                     }
                                 }
                             }
                             ProxyMessage::ContextMenu(msg) => match msg {
                                 ContextMsg::NewFolder => {
                                     match desktop_manager.create_new_folder() {
-                                        Ok(items) => {
+                                        Ok(path) => {
                                             let mut desktop_items = desktop_items.borrow_mut();
-                                            *desktop_items = items;
+                                            *desktop_items = desktop_manager.desktop_items().to_owned();
                                             // .into_iter().map(|item| (button::State::new(), item)).collect();
+                                            let _ = event_proxy.send_event(ProxyMessage::Desktop(DesktopMsg::StartRename(path)));
                                         }
-                                        Err(err) => {
-                                            let _ = DialogBuilder::new()
-                                                .title("Error")
-                                                .message(&format!("{}", err))
-                                                .style(DialogStyle::Error)
-                                                .build()
-                                                .show();
+                                        Err(err) => show_error(err),
+                                    }
+                                }
+                                ContextMsg::Paste => {
+                                    let _ = event_proxy.send_event(ProxyMessage::PasteItems);
+                                }
+                                ContextMsg::RestoreTrashed => {
+                                    if let Err(err) = desktop_manager.restore_trashed() {
+                                        show_error(err);
+                                    }
+                                    let mut desktop_items = desktop_items.borrow_mut();
+                                    *desktop_items = desktop_manager.desktop_items().to_owned();
+                                }
+                                ContextMsg::EmptyTrash => {
+                                    let selection = DialogBuilder::new()
+                                        .title("Empty Trash")
+                                        .message("Do you want to permanently delete all items in the trash?")
+                                        .buttons(DialogButtons::YesNo)
+                                        .style(DialogStyle::Question)
+                                        .build()
+                                        .show();
+                                    if let DialogSelection::Yes = selection {
+                                        if let Err(err) = desktop_manager.empty_trash() {
+                                            show_error(err);
                                         }
+                                        let mut desktop_items = desktop_items.borrow_mut();
+                                        *desktop_items = desktop_manager.desktop_items().to_owned();
                                     }
                                 }
                                 ContextMsg::ChangeBG => {
@@ -330,6 +638,22 @@ fn main() {
                                             }
                                         }
                                     }
+                                    // Closed once the copy engine reports the job finished.
+                                    CopyProgress(state) => {
+                                        if state.window_event_request_exit(&event, &mut debug) {
+                                            desktop_manager.cancel_copy();
+                                        }
+                                    }
+                                    Properties(state) => {
+                                        if state.window_event_request_exit(&event, &mut debug) {
+                                            windows.remove(&window_id);
+                                        }
+                                    }
+                                    ScriptPrompt(state) => {
+                                        if state.window_event_request_exit(&event, &mut debug) {
+                                            windows.remove(&window_id);
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -351,6 +675,30 @@ fn main() {
                                     );
                                     state.window.request_redraw();
                                 }
+                                DynWinState::CopyProgress(state) => {
+                                    state.update_frame::<executor::Default>(
+                                        None,
+                                        cursor_position,
+                                        &mut debug,
+                                    );
+                                    state.window.request_redraw();
+                                }
+                                DynWinState::Properties(state) => {
+                                    state.update_frame::<executor::Default>(
+                                        None,
+                                        cursor_position,
+                                        &mut debug,
+                                    );
+                                    state.window.request_redraw();
+                                }
+                                DynWinState::ScriptPrompt(state) => {
+                                    state.update_frame::<executor::Default>(
+                                        None,
+                                        cursor_position,
+                                        &mut debug,
+                                    );
+                                    state.window.request_redraw();
+                                }
                             });
                         }
                         Event::RedrawRequested(window_id) => {
@@ -362,6 +710,15 @@ fn main() {
                                     DynWinState::BgConfig(prog_state) => {
                                         prog_state.redraw(cursor_position, &mut debug)
                                     }
+                                    DynWinState::CopyProgress(prog_state) => {
+                                        prog_state.redraw(cursor_position, &mut debug)
+                                    }
+                                    DynWinState::Properties(prog_state) => {
+                                        prog_state.redraw(cursor_position, &mut debug)
+                                    }
+                                    DynWinState::ScriptPrompt(prog_state) => {
+                                        prog_state.redraw(cursor_position, &mut debug)
+                                    }
                                 })
                             } else {
                                 None
@@ -406,16 +763,104 @@ async fn run_instance<E>(
     let mut debug = Debug::new();
     let mut cursor_position = PhysicalPosition::new(-1.0, -1.0);
     let mut is_context_shown = false;
+    // The menu opens where it was requested once it knows what to show.
+    let mut context_menu_position = cursor_position;
+    let mut context_menu_size = context_menu_size;
+    let mut is_context_requested = false;
+    let mut is_left_pressed = false;
+    let mut dropped_files = Vec::new();
 
     while let Some(event) = receiver.next().await {
+        if let Event::UserEvent(message) = &event {
+            if closes_context_menu(message) {
+                is_context_shown = false;
+                context_menu_state.window.set_visible(false);
+            }
+        }
+
         match event {
             Event::UserEvent(ProxyMessage::Desktop(msg)) => desktop_state.map_message(msg),
+            Event::UserEvent(ProxyMessage::ContextMenu(msg @ ContextMsg::ShowDesktopMenu))
+            | Event::UserEvent(ProxyMessage::ContextMenu(msg @ ContextMsg::ShowItemMenu(_))) => {
+                context_menu_state.map_message(msg);
+                is_context_requested = true;
+            }
+            Event::UserEvent(ProxyMessage::ContextMenu(ContextMsg::Open)) => {
+                desktop_state.map_message(DesktopMsg::LaunchSelected)
+            }
+            // Desktop items were already updated by the event loop, only the view is left.
+            Event::UserEvent(ProxyMessage::ItemCreated(_))
+            | Event::UserEvent(ProxyMessage::ItemRemoved(_))
+            | Event::UserEvent(ProxyMessage::ItemModified(_))
+            | Event::UserEvent(ProxyMessage::ItemsRescanned)
+            | Event::UserEvent(ProxyMessage::ThumbnailReady(_))
+            | Event::UserEvent(ProxyMessage::TrashItems(_))
+            | Event::UserEvent(ProxyMessage::TrashChanged)
+            | Event::UserEvent(ProxyMessage::ContextMenu(ContextMsg::NewFolder))
+            | Event::UserEvent(ProxyMessage::ContextMenu(ContextMsg::RestoreTrashed))
+            | Event::UserEvent(ProxyMessage::ContextMenu(ContextMsg::EmptyTrash))
+            | Event::UserEvent(ProxyMessage::DesktopConf(_)) => {
+                desktop_state.map_message(DesktopMsg::DesktopItemsChanged)
+            }
+            Event::UserEvent(ProxyMessage::ContextMenu(ContextMsg::MoveToTrash)) => {
+                desktop_state.map_message(DesktopMsg::TrashSelected)
+            }
+            Event::UserEvent(ProxyMessage::ContextMenu(ContextMsg::Rename)) => {
+                desktop_state.map_message(DesktopMsg::RenameSelected)
+            }
+            Event::UserEvent(ProxyMessage::ItemRenamed(from, to)) => {
+                desktop_state.map_message(DesktopMsg::DesktopItemRenamed(from, to))
+            }
+            // Other URLs are made links by the event loop.
+            Event::UserEvent(ProxyMessage::UrlsDropped(urls, (x, y))) => {
+                let files: Vec<PathBuf> = urls
+                    .iter()
+                    .filter(|url| url.scheme() == FILE_SCHEME)
+                    .filter_map(|url| url.to_file_path().ok())
+                    .collect();
+                if !files.is_empty() {
+                    let origin = desktop_state
+                        .window
+                        .inner_position()
+                        .unwrap_or_else(|_| PhysicalPosition::new(0, 0));
+                    let position =
+                        PhysicalPosition::new(f64::from(x - origin.x), f64::from(y - origin.y));
+                    desktop_state.map_message(DesktopMsg::FilesDropped(
+                        files,
+                        logical_point(position, desktop_state.window.scale_factor()),
+                    ));
+                }
+            }
             Event::WindowEvent {
                 ref event,
                 window_id,
             } => {
                 match event {
-                    WindowEvent::CursorMoved { position, .. } => cursor_position = *position,
+                    WindowEvent::CursorMoved { position, .. } => {
+                        cursor_position = *position;
+                        // Only moves with the button held matter to drag desktop items.
+                        if is_left_pressed && desktop_state.window.id() == window_id {
+                            desktop_state.map_message(DesktopMsg::PointerMoved(logical_point(
+                                cursor_position,
+                                desktop_state.window.scale_factor(),
+                            )));
+                        }
+                    }
+                    WindowEvent::Resized(_) if desktop_state.window.id() == window_id => {
+                        if let Some(monitor) = desktop_state.window.current_monitor() {
+                            let resolution = monitor.size();
+                            desktop_state.map_message(DesktopMsg::ResolutionChanged(
+                                resolution.width,
+                                resolution.height,
+                            ));
+                        }
+                    }
+                    // Only received when the drop target could not start.
+                    WindowEvent::DroppedFile(path) => {
+                        if desktop_state.window.id() == window_id {
+                            dropped_files.push(path.to_path_buf());
+                        }
+                    }
                     WindowEvent::KeyboardInput { input, .. } => match input {
                         KeyboardInput {
                             state: ElementState::Pressed,
@@ -432,19 +877,42 @@ async fn run_instance<E>(
                         if desktop_state.window.id() == window_id {
                             match button {
                                 MouseButton::Right => {
-                                    context_menu_state.window.set_outer_position(
-                                        get_prefered_position(
+                                    is_context_shown = false;
+                                    context_menu_position = cursor_position;
+                                    desktop_state.map_message(DesktopMsg::ContextMenuRequested(
+                                        logical_point(
                                             cursor_position,
-                                            context_menu_size,
-                                            monitor_size,
+                                            desktop_state.window.scale_factor(),
                                         ),
-                                    );
-                                    is_context_shown = true;
+                                    ));
+                                }
+                                MouseButton::Left => {
+                                    is_context_shown = false;
+                                    is_left_pressed = true;
+                                    desktop_state.map_message(DesktopMsg::PointerPressed(
+                                        logical_point(
+                                            cursor_position,
+                                            desktop_state.window.scale_factor(),
+                                        ),
+                                    ));
                                 }
                                 _ => is_context_shown = false,
                             }
                         }
                     }
+                    WindowEvent::MouseInput {
+                        state: ElementState::Released,
+                        button: MouseButton::Left,
+                        ..
+                    } => {
+                        if is_left_pressed && desktop_state.window.id() == window_id {
+                            is_left_pressed = false;
+                            desktop_state.map_message(DesktopMsg::PointerReleased(logical_point(
+                                cursor_position,
+                                desktop_state.window.scale_factor(),
+                            )));
+                        }
+                    }
                     _ => {}
                 }
 
@@ -458,6 +926,14 @@ async fn run_instance<E>(
                 context_menu_state.window.set_visible(is_context_shown);
             }
             Event::MainEventsCleared => {
+                // Every file of a single drop is received before the events are cleared.
+                if !dropped_files.is_empty() {
+                    desktop_state.map_message(DesktopMsg::FilesDropped(
+                        dropped_files.drain(..).collect(),
+                        logical_point(cursor_position, desktop_state.window.scale_factor()),
+                    ));
+                }
+
                 if let Some(cmd) =
                     desktop_state.update_frame(Some(&mut runtime), cursor_position, &mut debug)
                 {
@@ -471,6 +947,23 @@ async fn run_instance<E>(
                     cursor_position,
                     &mut debug,
                 );
+                // The menu grows and shrinks with its items and open submenus.
+                let (width, height) = context_menu_state.application().size();
+                let size = PhysicalSize::new(width, height);
+                if size != context_menu_size || is_context_requested {
+                    context_menu_size = size;
+                    context_menu_state.window.set_inner_size(context_menu_size);
+                    context_menu_state.window.set_outer_position(get_prefered_position(
+                        context_menu_position,
+                        context_menu_size,
+                        monitor_size,
+                    ));
+                }
+                if is_context_requested {
+                    is_context_requested = false;
+                    is_context_shown = true;
+                    context_menu_state.window.set_visible(true);
+                }
                 context_menu_state.window.request_redraw();
             }
             Event::RedrawRequested(window_id) => {
@@ -489,6 +982,26 @@ async fn run_instance<E>(
     }
 }
 
+/// Position of the cursor in the logical coordinates of the desktop view.
+fn logical_point(position: PhysicalPosition<f64>, scale_factor: f64) -> Point {
+    let position = position.to_logical::<f32>(scale_factor);
+    Point::new(position.x, position.y)
+}
+
+/// Whether the user is done with the context menu once `message` is sent.
+fn closes_context_menu(message: &ProxyMessage) -> bool {
+    match message {
+        ProxyMessage::ContextMenu(msg) => ContextMenu::is_done(msg),
+        ProxyMessage::OpenWith(..)
+        | ProxyMessage::AlwaysOpenWith(..)
+        | ProxyMessage::LaunchAction(..)
+        | ProxyMessage::ShowProperties(_)
+        | ProxyMessage::CopyItems(_)
+        | ProxyMessage::CutItems(_) => true,
+        _ => false,
+    }
+}
+
 fn get_prefered_position(
     cursor_position: PhysicalPosition<f64>,
     window_size: PhysicalSize<f64>,
@@ -508,7 +1021,19 @@ fn get_prefered_position(
     PhysicalPosition::new(prefered_x, prefered_y)
 }
 
+fn show_error(err: impl std::fmt::Display) {
+    let _ = DialogBuilder::new()
+        .title("Error")
+        .message(&err.to_string())
+        .style(DialogStyle::Error)
+        .build()
+        .show();
+}
+
 enum DynWinState {
     DesktopConfig(WindowState<DesktopConfigUI>),
     BgConfig(WindowState<BackgroundConfigUI>),
+    CopyProgress(WindowState<CopyProgressUI>),
+    Properties(WindowState<PropertiesUI>),
+    ScriptPrompt(WindowState<ScriptPromptUI>),
 }
@@ -1,4 +1,9 @@
-use crate::gui::{BackgroundConfMsg, ContextMsg, DesktopConfigMsg, DesktopMsg};
+use crate::copy_engine::CopyMsg;
+use crate::gui::{
+    BackgroundConfMsg, ContextMsg, CopyProgressMsg, DesktopConfigMsg, DesktopMsg, ScriptPromptMsg,
+};
+use std::path::PathBuf;
+use url::Url;
 
 #[derive(Debug, Clone)]
 pub enum ProxyMessage {
@@ -6,6 +11,35 @@ pub enum ProxyMessage {
     ContextMenu(ContextMsg),
     Bg(BackgroundConfMsg),
     DesktopConf(DesktopConfigMsg),
+    CopyProgress(CopyProgressMsg),
+    ScriptPrompt(ScriptPromptMsg),
+    // Desktop directory watcher
+    ItemCreated(PathBuf),
+    ItemRemoved(PathBuf),
+    ItemRenamed(PathBuf, PathBuf),
+    ItemModified(PathBuf),
+    ItemsRescanned,
+    ThumbnailReady(PathBuf),
+    // Desktop items dragged onto a folder
+    MoveItems(Vec<PathBuf>, PathBuf),
+    TrashItems(Vec<PathBuf>),
+    RenameItem(PathBuf, String),
+    // Item context menu
+    OpenWith(Vec<PathBuf>, PathBuf),
+    // Items opened with an application made the default one for their MIME type
+    AlwaysOpenWith(Vec<PathBuf>, PathBuf, String),
+    LaunchAction(PathBuf, String),
+    ShowProperties(PathBuf),
+    // Executable script opened, asking whether to run it
+    ShowScriptPrompt(PathBuf),
+    TrashChanged,
+    // Clipboard and the copy engine
+    CopyItems(Vec<PathBuf>),
+    CutItems(Vec<PathBuf>),
+    PasteItems,
+    Copy(CopyMsg),
+    // URLs dropped on the desktop, with the position on the screen they were dropped at
+    UrlsDropped(Vec<Url>, (i32, i32)),
 }
 
 impl From<DesktopMsg> for ProxyMessage {
@@ -31,3 +65,9 @@ impl From<DesktopConfigMsg> for ProxyMessage {
         Self::DesktopConf(msg)
     }
 }
+
+impl From<CopyProgressMsg> for ProxyMessage {
+    fn from(msg: CopyProgressMsg) -> Self {
+        Self::CopyProgress(msg)
+    }
+}
@@ -329,6 +329,10 @@ impl<A: Application<Renderer=Renderer>> WindowState<A> {
     where A: HasChanged {
         self.application.has_changed()
     }
+
+    pub fn application(&self) -> &A {
+        &self.application
+    }
 }
 
 fn build_user_interface<'a, P: Program>(
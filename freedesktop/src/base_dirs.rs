@@ -0,0 +1,38 @@
+use std::env;
+use std::path::PathBuf;
+
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+const DEFAULT_CONFIG_DIRS: &str = "/etc/xdg";
+const APPLICATIONS_DIR: &str = "applications";
+
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`, in order of preference.
+pub fn data_dirs() -> Vec<PathBuf> {
+    let data_dirs = env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| DEFAULT_DATA_DIRS.into());
+
+    dirs_next::data_dir()
+        .into_iter()
+        .chain(env::split_paths(&data_dirs).filter(|dir| dir.is_absolute()))
+        .collect()
+}
+
+/// `$XDG_CONFIG_HOME` followed by `$XDG_CONFIG_DIRS`, in order of preference.
+pub fn config_dirs() -> Vec<PathBuf> {
+    let config_dirs = env::var_os("XDG_CONFIG_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| DEFAULT_CONFIG_DIRS.into());
+
+    dirs_next::config_dir()
+        .into_iter()
+        .chain(env::split_paths(&config_dirs).filter(|dir| dir.is_absolute()))
+        .collect()
+}
+
+/// `applications` directories of the XDG data directories, in order of preference.
+pub fn applications_dirs() -> Vec<PathBuf> {
+    data_dirs()
+        .into_iter()
+        .map(|dir| dir.join(APPLICATIONS_DIR))
+        .collect()
+}
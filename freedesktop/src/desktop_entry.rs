@@ -0,0 +1,357 @@
+mod exec;
+mod terminal;
+
+pub use exec::{DesktopExec, ExecContext, ThumbnailerExec};
+pub use terminal::TerminalEmulator;
+
+use crate::{base_dirs, executable, locale};
+use freedesktop_entry_parser::errors::ParseError;
+use freedesktop_entry_parser::{AttrSelector, Entry};
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use subprocess::{Exec, PopenError};
+use thiserror::Error;
+
+/// Name of the desktop environment in `XDG_CURRENT_DESKTOP`, `OnlyShowIn` and `NotShowIn`.
+pub const CURRENT_DESKTOP: &str = "KOOMPI";
+pub const DESKTOP_ENTRY: &str = "Desktop Entry";
+const DESKTOP_ACTION: &str = "Desktop Action";
+const APPLICATION: &str = "Application";
+const DESKTOP_EXTENSION: &str = "desktop";
+
+// Keys of the `[Desktop Entry]` group
+const TYPE: &str = "Type";
+const NAME: &str = "Name";
+const GENERIC_NAME: &str = "GenericName";
+const COMMENT: &str = "Comment";
+const ICON: &str = "Icon";
+const EXEC: &str = "Exec";
+const TRY_EXEC: &str = "TryExec";
+const TERMINAL: &str = "Terminal";
+const ACTIONS: &str = "Actions";
+const CATEGORIES: &str = "Categories";
+const KEYWORDS: &str = "Keywords";
+const HIDDEN: &str = "Hidden";
+const NO_DISPLAY: &str = "NoDisplay";
+const ONLY_SHOW_IN: &str = "OnlyShowIn";
+const NOT_SHOW_IN: &str = "NotShowIn";
+const STARTUP_WM_CLASS: &str = "StartupWMClass";
+
+#[derive(Debug, Error)]
+pub enum DesktopEntryError {
+    #[error(transparent)]
+    ParseError(#[from] ParseError),
+    #[error(transparent)]
+    PopenError(#[from] PopenError),
+    #[error("cannot launch due to no execute string")]
+    NoExecString,
+    #[error("cannot launch due to bad execute string")]
+    BadExecString,
+    #[error("no action named {0} in the desktop entry")]
+    NoAction(String),
+    #[error("cannot find a terminal emulator to run the application")]
+    NoTerminal,
+    #[error("desktop entry is hidden")]
+    Hidden,
+    #[error("desktop entry is not meant to be displayed")]
+    NoDisplay,
+    #[error("desktop entry is not shown in {0}")]
+    NotShownIn(String),
+    #[error("cannot find {0} required by the desktop entry")]
+    TryExecNotFound(String),
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+}
+
+/// Application of a `Type=Application` desktop entry.
+#[derive(Debug, Clone, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct DesktopEntry {
+    name: Option<String>,
+    generic_name: Option<String>,
+    comment: Option<String>,
+    icon: Option<String>,
+    location: PathBuf,
+    exec: Option<String>,
+    term: bool,
+    categories: Vec<String>,
+    keywords: Vec<String>,
+    startup_wm_class: Option<String>,
+    actions: Vec<DesktopAction>,
+}
+
+/// Additional way to launch an application, e.g. "New Private Window", from a
+/// `[Desktop Action <id>]` group.
+#[derive(Debug, Clone, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct DesktopAction {
+    pub id: String,
+    pub name: String,
+    pub icon: Option<String>,
+    exec: Option<String>,
+}
+
+impl DesktopAction {
+    fn new(entry: &Entry, id: &str) -> Option<Self> {
+        let section = entry.section(format!("{} {}", DESKTOP_ACTION, id));
+
+        Some(Self {
+            id: id.to_string(),
+            name: locale::localized_attr(&section, NAME)?.to_string(),
+            icon: section.attr(ICON).map(ToString::to_string),
+            exec: section.attr(EXEC).map(ToString::to_string),
+        })
+    }
+}
+
+impl DesktopEntry {
+    /// Entry of the `[Desktop Entry]` group of `entry` together with its actions.
+    pub fn from_entry<P: AsRef<Path>>(entry: &Entry, location: P) -> Self {
+        let desktop_entry = entry.section(DESKTOP_ENTRY);
+        // Actions without a group of their own are ignored.
+        let actions = split_list(desktop_entry.attr(ACTIONS).unwrap_or_default())
+            .iter()
+            .filter_map(|id| DesktopAction::new(entry, id))
+            .collect();
+
+        Self {
+            actions,
+            ..Self::new(&desktop_entry, location)
+        }
+    }
+
+    pub fn new<P: AsRef<Path>>(desktop_entry: &AttrSelector<&str>, location: P) -> Self {
+        let localized = |key| locale::localized_attr(desktop_entry, key).map(ToString::to_string);
+        let term = desktop_entry
+            .attr(TERMINAL)
+            .map(|term| term.trim() == "true")
+            .unwrap_or(false);
+
+        Self {
+            name: localized(NAME),
+            generic_name: localized(GENERIC_NAME),
+            comment: localized(COMMENT),
+            icon: desktop_entry.attr(ICON).map(ToString::to_string),
+            location: location.as_ref().to_path_buf(),
+            exec: desktop_entry.attr(EXEC).map(ToString::to_string),
+            term,
+            categories: split_list(desktop_entry.attr(CATEGORIES).unwrap_or_default()),
+            keywords: split_list(localized(KEYWORDS).as_deref().unwrap_or_default()),
+            startup_wm_class: desktop_entry.attr(STARTUP_WM_CLASS).map(ToString::to_string),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Parse the desktop file at `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, DesktopEntryError> {
+        let entry = freedesktop_entry_parser::parse_entry(path.as_ref())?;
+        Ok(Self::from_entry(&entry, path))
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn generic_name(&self) -> Option<&str> {
+        self.generic_name.as_deref()
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// Path of the desktop file.
+    pub fn location(&self) -> &Path {
+        &self.location
+    }
+
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    pub fn keywords(&self) -> &[String] {
+        &self.keywords
+    }
+
+    /// Class or instance name of the windows the application maps, as in `WM_CLASS`.
+    pub fn startup_wm_class(&self) -> Option<&str> {
+        self.startup_wm_class.as_deref()
+    }
+
+    pub fn actions(&self) -> &[DesktopAction] {
+        &self.actions
+    }
+
+    /// Launch the action `id` of the entry with `files`, in a terminal as the entry would be.
+    pub fn launch_action(
+        &self,
+        id: &str,
+        files: &[String],
+        preferred_terminal: Option<&str>,
+    ) -> Result<(), DesktopEntryError> {
+        let action = self
+            .actions
+            .iter()
+            .find(|action| action.id == id)
+            .ok_or_else(|| DesktopEntryError::NoAction(id.to_string()))?;
+        let entry = Self {
+            name: Some(action.name.to_owned()),
+            icon: action.icon.to_owned().or_else(|| self.icon.to_owned()),
+            location: self.location.to_owned(),
+            exec: action.exec.to_owned(),
+            term: self.term,
+            ..Self::default()
+        };
+
+        entry.handle_exec(files, preferred_terminal)
+    }
+
+    /// Launch the entry with `files`, given as local paths or URLs, substituted for its field codes.
+    /// Entries with `Terminal=true` run inside `preferred_terminal` or else a detected terminal emulator.
+    pub fn handle_exec(
+        &self,
+        files: &[String],
+        preferred_terminal: Option<&str>,
+    ) -> Result<(), DesktopEntryError> {
+        let exec = self
+            .exec
+            .as_ref()
+            .ok_or(DesktopEntryError::NoExecString)?
+            .parse::<DesktopExec>()?;
+        let context = ExecContext {
+            name: self.name.as_deref(),
+            icon: self.icon.as_deref(),
+            location: Some(&self.location),
+        };
+        let terminal = if self.term {
+            Some(
+                TerminalEmulator::detect(preferred_terminal)
+                    .ok_or(DesktopEntryError::NoTerminal)?,
+            )
+        } else {
+            None
+        };
+
+        for args in exec.expand(files, &context) {
+            let args = match &terminal {
+                Some(terminal) if !args.is_empty() => terminal.wrap(args),
+                _ => args,
+            };
+            let mut args = args.into_iter();
+            let cmd = Exec::cmd(args.next().ok_or(DesktopEntryError::NoExecString)?)
+                .args(&args.collect::<Vec<String>>());
+            let _ = cmd.detached().popen()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that the entry of the `[Desktop Entry]` group is meant to be shown in KOOMPI and that
+/// the program its `TryExec` key names is installed.
+pub fn validate(desktop_entry: &AttrSelector<&str>) -> Result<(), DesktopEntryError> {
+    let is_set =
+        |key: &str| matches!(desktop_entry.attr(key), Some(value) if value.trim() == "true");
+    let current_desktops =
+        env::var("XDG_CURRENT_DESKTOP").unwrap_or_else(|_| CURRENT_DESKTOP.to_string());
+    let is_current = |desktops: &str| {
+        split_list(desktops).iter().any(|desktop| {
+            current_desktops
+                .split(':')
+                .any(|current| current == desktop)
+        })
+    };
+
+    if is_set(HIDDEN) {
+        Err(DesktopEntryError::Hidden)
+    } else if is_set(NO_DISPLAY) {
+        Err(DesktopEntryError::NoDisplay)
+    } else if matches!(desktop_entry.attr(ONLY_SHOW_IN), Some(desktops) if !is_current(desktops))
+        || matches!(desktop_entry.attr(NOT_SHOW_IN), Some(desktops) if is_current(desktops))
+    {
+        Err(DesktopEntryError::NotShownIn(current_desktops))
+    } else {
+        match desktop_entry.attr(TRY_EXEC) {
+            Some(try_exec) if executable::find_program(try_exec).is_none() => {
+                Err(DesktopEntryError::TryExecNotFound(try_exec.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Installed applications meant to be shown in menus, with their desktop file ID. An ID found
+/// in several XDG data directories is the one of the most preferred directory, even when that
+/// one hides the application.
+pub fn applications() -> Vec<(String, DesktopEntry)> {
+    let mut ids = HashSet::new();
+    let mut applications = Vec::new();
+
+    for dir in base_dirs::applications_dirs() {
+        for path in desktop_files(&dir) {
+            let id: Vec<String> = path
+                .strip_prefix(&dir)
+                .unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            let id = id.join("-");
+            if ids.insert(id.to_owned()) {
+                if let Some(entry) = read_application(&path) {
+                    applications.push((id, entry));
+                }
+            }
+        }
+    }
+
+    applications
+}
+
+/// Application of the desktop file at `path`, if it is a valid one to show.
+fn read_application(path: &Path) -> Option<DesktopEntry> {
+    let entry = freedesktop_entry_parser::parse_entry(path).ok()?;
+    let desktop_entry = entry.section(DESKTOP_ENTRY);
+    if desktop_entry.attr(TYPE) != Some(APPLICATION) || validate(&desktop_entry).is_err() {
+        return None;
+    }
+
+    Some(DesktopEntry::from_entry(&entry, path))
+}
+
+/// Desktop files in `dir` and its subdirectories.
+fn desktop_files(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    paths.sort();
+
+    paths
+        .into_iter()
+        .flat_map(|path| {
+            if path.is_dir() {
+                desktop_files(&path)
+            } else if path.extension() == Some(DESKTOP_EXTENSION.as_ref()) {
+                vec![path]
+            } else {
+                Vec::new()
+            }
+        })
+        .collect()
+}
+
+/// Values of a key of type `string(s)`, separated by semicolons.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
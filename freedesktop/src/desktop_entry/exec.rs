@@ -0,0 +1,468 @@
+use super::DesktopEntryError;
+use std::path::Path;
+use std::str::FromStr;
+use url::Url;
+
+/// A piece of one argument of the Exec key, either literal text or a field code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExecToken {
+    Literal(String),
+    FieldCode(char),
+}
+
+/// Exec key parsed following the quoting rules of the Desktop Entry Specification.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DesktopExec {
+    args: Vec<Vec<ExecToken>>,
+}
+
+/// Exec key of a `.thumbnailer` file, with the field codes `%u`, `%i`, `%o` and `%s` of the
+/// Thumbnail Managing Standard and the quoting rules of desktop entries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThumbnailerExec {
+    args: Vec<Vec<ExecToken>>,
+}
+
+/// Values of the desktop entry substituted for `%c`, `%i` and `%k`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExecContext<'a> {
+    pub name: Option<&'a str>,
+    pub icon: Option<&'a str>,
+    pub location: Option<&'a Path>,
+}
+
+impl FromStr for DesktopExec {
+    type Err = DesktopEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let args = tokenize(s, "fFuUick", "dDnNvm")?;
+
+        // %F, %U and %i are only allowed as an argument on its own.
+        let is_misplaced = args.iter().any(|arg| {
+            arg.len() > 1
+                && arg.iter().any(|token| match token {
+                    ExecToken::FieldCode(code) => "FUi".contains(*code),
+                    _ => false,
+                })
+        });
+        let num_file_codes = args
+            .iter()
+            .flatten()
+            .filter(|token| match token {
+                ExecToken::FieldCode(code) => "fFuU".contains(*code),
+                _ => false,
+            })
+            .count();
+
+        if is_misplaced || num_file_codes > 1 {
+            Err(DesktopEntryError::BadExecString)
+        } else {
+            Ok(Self { args })
+        }
+    }
+}
+
+impl FromStr for ThumbnailerExec {
+    type Err = DesktopEntryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let args = tokenize(s, "uios", "")?;
+        Ok(Self { args })
+    }
+}
+
+/// Split an Exec key into arguments made of literal text and of the `field_codes`, dropping
+/// the `deprecated_codes`.
+fn tokenize(
+    s: &str,
+    field_codes: &str,
+    deprecated_codes: &str,
+) -> Result<Vec<Vec<ExecToken>>, DesktopEntryError> {
+    let exec = unescape_string(s);
+    let mut args = Vec::new();
+    let mut arg = Vec::new();
+    let mut literal = String::new();
+    let mut in_arg = false;
+    let mut is_quoted = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    push_arg(&mut args, &mut arg, &mut literal, is_quoted);
+                    in_arg = false;
+                    is_quoted = false;
+                }
+            }
+            '"' => {
+                in_arg = true;
+                is_quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ '"') | Some(escaped @ '`') | Some(escaped @ '$')
+                            | Some(escaped @ '\\') => literal.push(escaped),
+                            Some(other) => {
+                                literal.push('\\');
+                                literal.push(other);
+                            }
+                            None => return Err(DesktopEntryError::BadExecString),
+                        },
+                        // Field codes are undefined inside quotes, only "%%" is unescaped.
+                        Some('%') => {
+                            literal.push('%');
+                            if chars.as_str().starts_with('%') {
+                                chars.next();
+                            }
+                        }
+                        Some(other) => literal.push(other),
+                        None => return Err(DesktopEntryError::BadExecString),
+                    }
+                }
+            }
+            '%' => {
+                in_arg = true;
+                match chars.next() {
+                    Some('%') => literal.push('%'),
+                    Some(code) if field_codes.contains(code) => {
+                        push_literal(&mut arg, &mut literal);
+                        arg.push(ExecToken::FieldCode(code));
+                    }
+                    // Deprecated field codes are removed from the command line.
+                    Some(code) if deprecated_codes.contains(code) => {}
+                    _ => return Err(DesktopEntryError::BadExecString),
+                }
+            }
+            other => {
+                in_arg = true;
+                literal.push(other);
+            }
+        }
+    }
+    if in_arg {
+        push_arg(&mut args, &mut arg, &mut literal, is_quoted);
+    }
+
+    Ok(args)
+}
+
+impl DesktopExec {
+    /// Expand the field codes into command lines ready to be spawned. `files` holds local
+    /// paths or URLs. An Exec with `%f` or `%u` yields one command line per file.
+    pub fn expand(&self, files: &[String], context: &ExecContext) -> Vec<Vec<String>> {
+        let is_single = self.has_field_code('f') || self.has_field_code('u');
+        let files: Vec<String> = if self.has_field_code('f') || self.has_field_code('F') {
+            files.iter().filter_map(|file| local_path(file)).collect()
+        } else {
+            files.to_vec()
+        };
+
+        if is_single && files.len() > 1 {
+            files
+                .iter()
+                .map(|file| self.expand_args(std::slice::from_ref(file), context))
+                .collect()
+        } else {
+            vec![self.expand_args(&files, context)]
+        }
+    }
+
+    fn has_field_code(&self, field_code: char) -> bool {
+        self.args
+            .iter()
+            .flatten()
+            .any(|token| *token == ExecToken::FieldCode(field_code))
+    }
+
+    fn expand_args(&self, files: &[String], context: &ExecContext) -> Vec<String> {
+        let mut expanded = Vec::new();
+
+        for arg in &self.args {
+            match arg.as_slice() {
+                [ExecToken::FieldCode('F')] | [ExecToken::FieldCode('U')] => {
+                    expanded.extend(files.iter().cloned())
+                }
+                [ExecToken::FieldCode('i')] => {
+                    if let Some(icon) = context.icon {
+                        expanded.push(String::from("--icon"));
+                        expanded.push(icon.to_string());
+                    }
+                }
+                // An argument made of a single field code disappears when it has no value.
+                [ExecToken::FieldCode(code)] if "fu".contains(*code) && files.is_empty() => {}
+                _ => {
+                    let value = arg.iter().fold(String::new(), |mut value, token| {
+                        match token {
+                            ExecToken::Literal(literal) => value.push_str(literal),
+                            ExecToken::FieldCode('f') | ExecToken::FieldCode('u') => {
+                                if let Some(file) = files.first() {
+                                    value.push_str(file);
+                                }
+                            }
+                            ExecToken::FieldCode('c') => value.push_str(context.name.unwrap_or("")),
+                            ExecToken::FieldCode('k') => {
+                                if let Some(location) = context.location {
+                                    value.push_str(&location.to_string_lossy())
+                                }
+                            }
+                            _ => {}
+                        }
+                        value
+                    });
+                    expanded.push(value);
+                }
+            }
+        }
+
+        expanded
+    }
+}
+
+impl ThumbnailerExec {
+    /// Program run by the thumbnailer, when given literally.
+    pub fn program(&self) -> Option<&str> {
+        match self.args.first()?.as_slice() {
+            [ExecToken::Literal(program)] => Some(program),
+            _ => None,
+        }
+    }
+
+    /// Command line making a thumbnail of at most `size` pixels at `output` for the file at
+    /// `input`, whose URI is `uri`.
+    pub fn expand(&self, uri: &str, input: &Path, output: &Path, size: u32) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|arg| {
+                arg.iter().fold(String::new(), |mut value, token| {
+                    match token {
+                        ExecToken::Literal(literal) => value.push_str(literal),
+                        ExecToken::FieldCode('u') => value.push_str(uri),
+                        ExecToken::FieldCode('i') => value.push_str(&input.to_string_lossy()),
+                        ExecToken::FieldCode('o') => value.push_str(&output.to_string_lossy()),
+                        ExecToken::FieldCode('s') => value.push_str(&size.to_string()),
+                        _ => {}
+                    }
+                    value
+                })
+            })
+            .collect()
+    }
+}
+
+fn push_literal(arg: &mut Vec<ExecToken>, literal: &mut String) {
+    if !literal.is_empty() {
+        arg.push(ExecToken::Literal(std::mem::take(literal)));
+    }
+}
+
+fn push_arg(
+    args: &mut Vec<Vec<ExecToken>>,
+    arg: &mut Vec<ExecToken>,
+    literal: &mut String,
+    is_quoted: bool,
+) {
+    push_literal(arg, literal);
+    if !arg.is_empty() {
+        args.push(std::mem::take(arg));
+    } else if is_quoted {
+        // Keep an explicitly quoted empty argument ("").
+        args.push(vec![ExecToken::Literal(String::new())]);
+    }
+}
+
+/// Undo the escape sequences allowed in any value of type string.
+fn unescape_string(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('s') => unescaped.push(' '),
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some('r') => unescaped.push('\r'),
+                Some('\\') => unescaped.push('\\'),
+                Some(other) => {
+                    unescaped.push('\\');
+                    unescaped.push(other);
+                }
+                None => unescaped.push('\\'),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    unescaped
+}
+
+/// Path of a file given either as a path or as a `file://` URL.
+fn local_path(file: &str) -> Option<String> {
+    match Url::parse(file) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned()),
+        // Remote URLs cannot be handed to an application expecting local files.
+        Ok(_) => None,
+        Err(_) => Some(file.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(exec: &str, files: &[&str]) -> Vec<Vec<String>> {
+        let files: Vec<String> = files.iter().map(ToString::to_string).collect();
+        let context = ExecContext {
+            name: Some("Text Editor"),
+            icon: Some("accessories-text-editor"),
+            location: Some(Path::new("/usr/share/applications/editor.desktop")),
+        };
+        exec.parse::<DesktopExec>()
+            .unwrap()
+            .expand(&files, &context)
+    }
+
+    #[test]
+    fn splits_plain_arguments() {
+        assert_eq!(
+            expand("gedit  --new-window", &[]),
+            vec![vec!["gedit", "--new-window"]]
+        );
+    }
+
+    #[test]
+    fn keeps_quoted_arguments_together() {
+        assert_eq!(
+            expand(r#""/opt/My App/app" "hello world" """#, &[]),
+            vec![vec!["/opt/My App/app", "hello world", ""]]
+        );
+    }
+
+    #[test]
+    fn unescapes_reserved_characters_in_quotes() {
+        // The file contains `\\"` and `\\$`, the string escape is applied first.
+        assert_eq!(
+            expand(r#"sh -c "echo \\"\\$HOME\\"""#, &[]),
+            vec![vec!["sh", "-c", r#"echo "$HOME""#]]
+        );
+    }
+
+    #[test]
+    fn unescapes_general_string_escapes() {
+        assert_eq!(
+            expand(r#""/opt/my\sapp" --flag\tvalue"#, &[]),
+            vec![vec!["/opt/my app", "--flag", "value"]]
+        );
+    }
+
+    #[test]
+    fn expands_percent_sign() {
+        assert_eq!(expand("printf 100%%", &[]), vec![vec!["printf", "100%"]]);
+        assert_eq!(
+            expand(r#"printf "100%%""#, &[]),
+            vec![vec!["printf", "100%"]]
+        );
+    }
+
+    #[test]
+    fn removes_file_codes_without_files() {
+        assert_eq!(expand("gimp %U", &[]), vec![vec!["gimp"]]);
+        assert_eq!(expand("gimp %f", &[]), vec![vec!["gimp"]]);
+    }
+
+    #[test]
+    fn passes_file_lists() {
+        assert_eq!(
+            expand("gimp %F", &["/tmp/a b.png", "file:///tmp/c%20d.png"]),
+            vec![vec!["gimp", "/tmp/a b.png", "/tmp/c d.png"]]
+        );
+        assert_eq!(
+            expand("firefox %U", &["https://koompi.com", "/tmp/a.html"]),
+            vec![vec!["firefox", "https://koompi.com", "/tmp/a.html"]]
+        );
+    }
+
+    #[test]
+    fn launches_once_per_file_for_single_codes() {
+        assert_eq!(
+            expand("vlc --one %f", &["/tmp/a.mp4", "/tmp/b.mp4"]),
+            vec![
+                vec!["vlc", "--one", "/tmp/a.mp4"],
+                vec!["vlc", "--one", "/tmp/b.mp4"]
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_remote_urls_for_local_files() {
+        assert_eq!(
+            expand("viewer %F", &["https://koompi.com/a.png"]),
+            vec![vec!["viewer"]]
+        );
+    }
+
+    #[test]
+    fn expands_field_code_within_argument() {
+        assert_eq!(
+            expand("app --file=%f", &["/tmp/a"]),
+            vec![vec!["app", "--file=/tmp/a"]]
+        );
+    }
+
+    #[test]
+    fn expands_icon_name_and_location() {
+        assert_eq!(
+            expand("editor %i --title=%c %k", &[]),
+            vec![vec![
+                "editor",
+                "--icon",
+                "accessories-text-editor",
+                "--title=Text Editor",
+                "/usr/share/applications/editor.desktop",
+            ]]
+        );
+    }
+
+    #[test]
+    fn removes_deprecated_field_codes() {
+        assert_eq!(expand("app %d %m --x", &[]), vec![vec!["app", "--x"]]);
+    }
+
+    #[test]
+    fn expands_thumbnailer_field_codes() {
+        let exec: ThumbnailerExec =
+            r#""/opt/My Thumbnailer/run" -s %s %u %o --in=%i"#.parse().unwrap();
+        assert_eq!(exec.program(), Some("/opt/My Thumbnailer/run"));
+        assert_eq!(
+            exec.expand(
+                "file:///tmp/a%20b.pdf",
+                Path::new("/tmp/a b.pdf"),
+                Path::new("/tmp/out.png"),
+                128
+            ),
+            vec![
+                "/opt/My Thumbnailer/run",
+                "-s",
+                "128",
+                "file:///tmp/a%20b.pdf",
+                "/tmp/out.png",
+                "--in=/tmp/a b.pdf",
+            ]
+        );
+        assert!("thumbnailer %f".parse::<ThumbnailerExec>().is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_exec_strings() {
+        assert!("app \"unterminated".parse::<DesktopExec>().is_err());
+        assert!("app %z".parse::<DesktopExec>().is_err());
+        assert!("app --files=%F".parse::<DesktopExec>().is_err());
+        assert!("app %f %U".parse::<DesktopExec>().is_err());
+    }
+}
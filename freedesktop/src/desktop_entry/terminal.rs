@@ -0,0 +1,71 @@
+use crate::executable::find_program;
+use std::env;
+use std::path::PathBuf;
+
+const TERMINAL_ENV: &str = "TERMINAL";
+const DEBIAN_TERMINAL: &str = "x-terminal-emulator";
+const DEFAULT_EXEC_FLAG: &[&str] = &["-e"];
+
+/// Known terminal emulators with the arguments placed before the command to execute.
+const KNOWN_TERMINALS: &[(&str, &[&str])] = &[
+    ("konsole", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("xfce4-terminal", &["-x"]),
+    ("mate-terminal", &["-x"]),
+    ("tilix", &["-e"]),
+    ("terminator", &["-x"]),
+    ("qterminal", &["-e"]),
+    ("lxterminal", &["-e"]),
+    ("deepin-terminal", &["-e"]),
+    ("alacritty", &["-e"]),
+    ("kitty", &[]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("terminology", &["-e"]),
+    ("sakura", &["-x"]),
+    ("urxvt", &["-e"]),
+    ("st", &["-e"]),
+    ("xterm", &["-e"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalEmulator {
+    program: PathBuf,
+    exec_flag: &'static [&'static str],
+}
+
+impl TerminalEmulator {
+    /// Find a terminal emulator, trying `preferred` first, then `$TERMINAL`,
+    /// `x-terminal-emulator` and at last the known terminal emulators.
+    pub fn detect(preferred: Option<&str>) -> Option<Self> {
+        let env_terminal = env::var(TERMINAL_ENV).ok();
+
+        preferred
+            .into_iter()
+            .chain(env_terminal.as_deref())
+            .chain(Some(DEBIAN_TERMINAL))
+            .chain(KNOWN_TERMINALS.iter().map(|(name, _)| *name))
+            .filter(|name| !name.trim().is_empty())
+            .find_map(|name| Self::from_program(name.trim()))
+    }
+
+    fn from_program(program: &str) -> Option<Self> {
+        let program = find_program(program)?;
+        let name = program.file_name()?.to_str()?;
+        let exec_flag = KNOWN_TERMINALS
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, exec_flag)| *exec_flag)
+            .unwrap_or(DEFAULT_EXEC_FLAG);
+
+        Some(Self { program, exec_flag })
+    }
+
+    /// Command line running `args` inside this terminal emulator.
+    pub fn wrap(&self, args: Vec<String>) -> Vec<String> {
+        let mut wrapped = vec![self.program.to_string_lossy().into_owned()];
+        wrapped.extend(self.exec_flag.iter().map(ToString::to_string));
+        wrapped.extend(args);
+        wrapped
+    }
+}
@@ -0,0 +1,25 @@
+use std::env;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Resolve `program` to an executable file, searching `$PATH` unless it is a path already.
+pub fn find_program<P: AsRef<Path>>(program: P) -> Option<PathBuf> {
+    let program = program.as_ref();
+
+    if program.components().count() > 1 {
+        Some(program.to_path_buf()).filter(|path| is_executable(path))
+    } else {
+        env::var_os("PATH").and_then(|paths| {
+            env::split_paths(&paths)
+                .map(|dir| dir.join(program))
+                .find(|path| is_executable(path))
+        })
+    }
+}
+
+pub fn is_executable<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
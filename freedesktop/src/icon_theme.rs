@@ -0,0 +1,408 @@
+mod theme;
+
+use crate::base_dirs;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use theme::Theme;
+
+pub const HICOLOR: &str = "hicolor";
+/// Supported icon file extensions in order of preference.
+const EXTENSIONS: [&str; 2] = ["png", "svg"];
+/// Minimum time before the cached directories are checked again for changes.
+const RECHECK_DELAY: Duration = Duration::from_secs(5);
+const GTK_SETTINGS: &str = "gtk-3.0/settings.ini";
+const GTK_ICON_THEME: &str = "gtk-icon-theme-name";
+const KDE_GLOBALS: &str = "kdeglobals";
+const KDE_ICONS: &str = "Icons";
+const KDE_THEME: &str = "Theme";
+
+lazy_static! {
+    static ref ICON_LOOKUP: Mutex<IconLookup> = Mutex::new(IconLookup::new());
+}
+
+/// Resolve an `Icon=` value, either an absolute path or an icon name, to an icon file of
+/// the user's icon theme using a process-wide cache.
+pub fn lookup_icon(icon: &str, size: u16, scale: u16) -> Option<PathBuf> {
+    let path = Path::new(icon);
+
+    if path.is_absolute() {
+        Some(path.to_path_buf()).filter(|path| path.is_file())
+    } else {
+        ICON_LOOKUP
+            .lock()
+            .ok()?
+            .lookup(icon, u32::from(size), u32::from(scale.max(1)))
+    }
+}
+
+/// Name of the icon theme selected by the user in the GTK or KDE settings.
+pub fn current_theme() -> String {
+    let config_dir = dirs_next::config_dir().unwrap_or_default();
+
+    read_setting(&config_dir.join(GTK_SETTINGS), None, GTK_ICON_THEME)
+        .or_else(|| read_setting(&config_dir.join(KDE_GLOBALS), Some(KDE_ICONS), KDE_THEME))
+        .unwrap_or_else(|| HICOLOR.to_string())
+}
+
+#[derive(Debug, Clone)]
+struct CachedDir {
+    modified: Option<SystemTime>,
+    files: HashSet<String>,
+}
+
+impl CachedDir {
+    fn read(path: &Path) -> Self {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let files = fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { modified, files }
+    }
+}
+
+/// Icon Theme Specification lookup remembering themes, directory contents and results.
+#[derive(Debug)]
+pub struct IconLookup {
+    theme: String,
+    base_dirs: Vec<PathBuf>,
+    themes: HashMap<String, Option<Arc<Theme>>>,
+    dirs: HashMap<PathBuf, CachedDir>,
+    icons: HashMap<(String, u32, u32), Option<PathBuf>>,
+    checked_at: Instant,
+}
+
+impl Default for IconLookup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IconLookup {
+    pub fn new() -> Self {
+        let home_icons = dirs_next::home_dir().map(|home| home.join(".icons"));
+        let base_dirs = home_icons
+            .into_iter()
+            .chain(
+                base_dirs::data_dirs()
+                    .into_iter()
+                    .map(|dir| dir.join("icons")),
+            )
+            .chain(Some(PathBuf::from("/usr/share/pixmaps")))
+            .collect();
+
+        Self::with_dirs(current_theme(), base_dirs)
+    }
+
+    fn with_dirs(theme: String, base_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            theme,
+            base_dirs,
+            themes: HashMap::new(),
+            dirs: HashMap::new(),
+            icons: HashMap::new(),
+            checked_at: Instant::now(),
+        }
+    }
+
+    pub fn lookup(&mut self, icon: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        if self.checked_at.elapsed() >= RECHECK_DELAY {
+            self.invalidate_changes();
+        }
+        // Icon names should not have an extension but some entries still give one.
+        let name = EXTENSIONS
+            .iter()
+            .chain(Some(&"xpm"))
+            .find_map(|extension| icon.strip_suffix(&format!(".{}", extension)))
+            .unwrap_or(icon);
+        let key = (name.to_string(), size, scale);
+
+        if let Some(path) = self.icons.get(&key) {
+            return path.clone();
+        }
+        let mut visited = HashSet::new();
+        let theme = self.theme.clone();
+        let path = self
+            .find_icon_in(&theme, name, size, scale, &mut visited)
+            .or_else(|| self.find_icon_in(HICOLOR, name, size, scale, &mut visited))
+            .or_else(|| self.lookup_fallback_icon(name));
+
+        self.icons.insert(key, path.clone());
+        path
+    }
+
+    /// Drop the cached results when the theme or an icon directory changed on disk.
+    fn invalidate_changes(&mut self) {
+        let theme = current_theme();
+        let mut is_changed = theme != self.theme;
+
+        for (path, dir) in self.dirs.iter_mut() {
+            let modified = fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            if modified != dir.modified {
+                *dir = CachedDir::read(path);
+                is_changed = true;
+            }
+        }
+        if is_changed {
+            self.theme = theme;
+            self.themes.clear();
+            self.icons.clear();
+        }
+        self.checked_at = Instant::now();
+    }
+
+    fn find_icon_in(
+        &mut self,
+        theme_name: &str,
+        name: &str,
+        size: u32,
+        scale: u32,
+        visited: &mut HashSet<String>,
+    ) -> Option<PathBuf> {
+        if !visited.insert(theme_name.to_string()) {
+            return None;
+        }
+        let theme = self.theme(theme_name)?;
+
+        self.lookup_in_theme(&theme, name, size, scale).or_else(|| {
+            theme
+                .parents
+                .iter()
+                .find_map(|parent| self.find_icon_in(parent, name, size, scale, visited))
+        })
+    }
+
+    fn lookup_in_theme(
+        &mut self,
+        theme: &Theme,
+        name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<PathBuf> {
+        for theme_dir in theme
+            .dirs
+            .iter()
+            .filter(|dir| dir.matches_size(size, scale))
+        {
+            for dir in theme.dir_paths(theme_dir) {
+                if let Some(path) = self.find_file(&dir, name) {
+                    return Some(path);
+                }
+            }
+        }
+
+        let mut closest = None;
+        let mut min_distance = u32::MAX;
+        for theme_dir in &theme.dirs {
+            let distance = theme_dir.size_distance(size, scale);
+            if distance < min_distance {
+                if let Some(path) = theme
+                    .dir_paths(theme_dir)
+                    .find_map(|dir| self.find_file(&dir, name))
+                {
+                    closest = Some(path);
+                    min_distance = distance;
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn lookup_fallback_icon(&mut self, name: &str) -> Option<PathBuf> {
+        self.base_dirs
+            .clone()
+            .iter()
+            .find_map(|dir| self.find_file(dir, name))
+    }
+
+    fn find_file(&mut self, dir: &Path, name: &str) -> Option<PathBuf> {
+        let cached_dir = self
+            .dirs
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| CachedDir::read(dir));
+
+        EXTENSIONS
+            .iter()
+            .map(|extension| format!("{}.{}", name, extension))
+            .find(|file_name| cached_dir.files.contains(file_name))
+            .map(|file_name| dir.join(file_name))
+    }
+
+    fn theme(&mut self, name: &str) -> Option<Arc<Theme>> {
+        let base_dirs = &self.base_dirs;
+
+        self.themes
+            .entry(name.to_string())
+            .or_insert_with(|| Theme::load(name, base_dirs).map(Arc::new))
+            .clone()
+    }
+}
+
+/// Value of `key` from an ini-style settings file, in `section` if given.
+fn read_setting(path: &Path, section: Option<&str>, key: &str) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut current_section = None;
+
+    content.lines().map(str::trim).find_map(|line| {
+        if line.starts_with('[') && line.ends_with(']') {
+            current_section = Some(line[1..line.len() - 1].to_string());
+            None
+        } else if section.is_none() || current_section.as_deref() == section {
+            let mut pair = line.splitn(2, '=');
+            match (pair.next(), pair.next()) {
+                (Some(name), Some(value)) if name.trim() == key && !value.trim().is_empty() => {
+                    Some(value.trim().to_string())
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// A `koompi` theme inheriting `parent`, with `hicolor` and `pixmaps` in other base
+    /// directories.
+    fn fixture() -> (TempDir, IconLookup) {
+        let dir = tempfile::tempdir().unwrap();
+        let icons = dir.path().join("icons");
+        let pixmaps = dir.path().join("pixmaps");
+        write(
+            &icons.join("koompi/index.theme"),
+            "[Icon Theme]\n\
+             Name=Koompi\n\
+             Inherits=parent\n\
+             Directories=16x16/apps,48x48/apps,32x32/apps,scalable/apps\n\
+             \n\
+             [16x16/apps]\nSize=16\nType=Fixed\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n\
+             [32x32/apps]\nSize=32\nType=Threshold\n\
+             [scalable/apps]\nSize=64\nMinSize=8\nMaxSize=256\nType=Scalable\n",
+        );
+        write(
+            &icons.join("parent/index.theme"),
+            "[Icon Theme]\nDirectories=24x24/apps\n\n[24x24/apps]\nSize=24\nType=Fixed\n",
+        );
+        write(
+            &icons.join("hicolor/index.theme"),
+            "[Icon Theme]\nDirectories=48x48/apps\n\n[48x48/apps]\nSize=48\nType=Fixed\n",
+        );
+        for icon in &[
+            "koompi/16x16/apps/fixed.png",
+            "koompi/48x48/apps/fixed.png",
+            "koompi/32x32/apps/threshold.png",
+            "koompi/scalable/apps/scalable.svg",
+            "koompi/48x48/apps/inherited.png",
+            "parent/24x24/apps/inherited.png",
+            "parent/24x24/apps/parent.png",
+            "hicolor/48x48/apps/parent.png",
+            "hicolor/48x48/apps/hicolor.png",
+        ] {
+            write(&icons.join(icon), "");
+        }
+        write(&pixmaps.join("pixmap.png"), "");
+
+        let lookup = IconLookup::with_dirs(String::from("koompi"), vec![icons, pixmaps]);
+        (dir, lookup)
+    }
+
+    fn icon(dir: &TempDir, path: &str) -> Option<PathBuf> {
+        Some(dir.path().join(path))
+    }
+
+    #[test]
+    fn matches_fixed_sizes_or_takes_the_closest() {
+        let (dir, mut lookup) = fixture();
+        let fixed = |size| icon(&dir, &format!("icons/koompi/{0}x{0}/apps/fixed.png", size));
+        assert_eq!(lookup.lookup("fixed", 16, 1), fixed(16));
+        assert_eq!(lookup.lookup("fixed", 48, 1), fixed(48));
+        assert_eq!(lookup.lookup("fixed", 20, 1), fixed(16));
+        assert_eq!(lookup.lookup("fixed", 40, 1), fixed(48));
+        assert_eq!(lookup.lookup("fixed", 24, 2), fixed(48));
+    }
+
+    #[test]
+    fn matches_scalable_and_threshold_sizes() {
+        let (dir, mut lookup) = fixture();
+        let scalable = icon(&dir, "icons/koompi/scalable/apps/scalable.svg");
+        let threshold = icon(&dir, "icons/koompi/32x32/apps/threshold.png");
+        assert_eq!(lookup.lookup("scalable", 8, 1), scalable);
+        assert_eq!(lookup.lookup("scalable", 256, 1), scalable);
+        assert_eq!(lookup.lookup("scalable", 512, 1), scalable);
+        assert_eq!(lookup.lookup("threshold", 30, 1), threshold);
+        assert_eq!(lookup.lookup("threshold", 34, 1), threshold);
+    }
+
+    #[test]
+    fn prefers_the_theme_over_its_parents() {
+        let (dir, mut lookup) = fixture();
+        assert_eq!(
+            lookup.lookup("inherited", 24, 1),
+            icon(&dir, "icons/koompi/48x48/apps/inherited.png")
+        );
+        assert_eq!(
+            lookup.lookup("parent", 48, 1),
+            icon(&dir, "icons/parent/24x24/apps/parent.png")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_hicolor_then_pixmaps() {
+        let (dir, mut lookup) = fixture();
+        assert_eq!(
+            lookup.lookup("hicolor", 16, 1),
+            icon(&dir, "icons/hicolor/48x48/apps/hicolor.png")
+        );
+        assert_eq!(
+            lookup.lookup("pixmap", 16, 1),
+            icon(&dir, "pixmaps/pixmap.png")
+        );
+        assert_eq!(
+            lookup.lookup("pixmap.png", 16, 1),
+            icon(&dir, "pixmaps/pixmap.png")
+        );
+        assert_eq!(lookup.lookup("missing", 16, 1), None);
+    }
+
+    #[test]
+    fn reads_settings_of_a_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(KDE_GLOBALS);
+        write(
+            &path,
+            "Theme=wrong\n[General]\nTheme=other\n[Icons]\nTheme = breeze\n",
+        );
+        assert_eq!(
+            read_setting(&path, Some(KDE_ICONS), KDE_THEME).as_deref(),
+            Some("breeze")
+        );
+        assert_eq!(
+            read_setting(&path, None, KDE_THEME).as_deref(),
+            Some("wrong")
+        );
+        assert_eq!(read_setting(&path, Some("Missing"), KDE_THEME), None);
+    }
+}
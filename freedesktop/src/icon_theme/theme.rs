@@ -0,0 +1,226 @@
+use std::path::{Path, PathBuf};
+
+const INDEX_FILE: &str = "index.theme";
+const ICON_THEME: &str = "Icon Theme";
+const INHERITS: &str = "Inherits";
+const DIRECTORIES: &str = "Directories";
+const SCALED_DIRECTORIES: &str = "ScaledDirectories";
+const SIZE: &str = "Size";
+const SCALE: &str = "Scale";
+const TYPE: &str = "Type";
+const MIN_SIZE: &str = "MinSize";
+const MAX_SIZE: &str = "MaxSize";
+const THRESHOLD: &str = "Threshold";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// A subdirectory of an icon theme with its size properties.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeDir {
+    pub path: String,
+    size: u32,
+    scale: u32,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+    dir_type: DirType,
+}
+
+impl ThemeDir {
+    pub fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+
+        match self.dir_type {
+            DirType::Fixed => self.size == size,
+            DirType::Scalable => self.min_size <= size && size <= self.max_size,
+            DirType::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size
+                    && size <= self.size + self.threshold
+            }
+        }
+    }
+
+    pub fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let (min_size, max_size) = match self.dir_type {
+            DirType::Fixed => (self.size, self.size),
+            DirType::Scalable => (self.min_size, self.max_size),
+            DirType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+        let size = size * scale;
+
+        if size < min_size * self.scale {
+            min_size * self.scale - size
+        } else {
+            size.saturating_sub(max_size * self.scale)
+        }
+    }
+}
+
+/// An icon theme described by its `index.theme`, spread over several base directories.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Theme {
+    pub roots: Vec<PathBuf>,
+    pub parents: Vec<String>,
+    pub dirs: Vec<ThemeDir>,
+}
+
+impl Theme {
+    pub fn load(name: &str, base_dirs: &[PathBuf]) -> Option<Self> {
+        let roots: Vec<PathBuf> = base_dirs
+            .iter()
+            .map(|base_dir| base_dir.join(name))
+            .filter(|root| root.is_dir())
+            .collect();
+        let index = roots
+            .iter()
+            .map(|root| root.join(INDEX_FILE))
+            .find(|index| index.is_file())?;
+        let entry = freedesktop_entry_parser::parse_entry(&index).ok()?;
+        let icon_theme = entry.section(ICON_THEME);
+
+        let parents = icon_theme
+            .attr(INHERITS)
+            .map(split_list)
+            .unwrap_or_default();
+        let dirs = icon_theme
+            .attr(DIRECTORIES)
+            .map(split_list)
+            .unwrap_or_default()
+            .into_iter()
+            .chain(
+                icon_theme
+                    .attr(SCALED_DIRECTORIES)
+                    .map(split_list)
+                    .unwrap_or_default(),
+            )
+            .filter_map(|path| {
+                let section = entry.section(path.as_str());
+                let attr = |key: &str| {
+                    section
+                        .attr(key)
+                        .and_then(|value| value.trim().parse::<u32>().ok())
+                };
+                let size = attr(SIZE)?;
+                let dir_type = match section.attr(TYPE).map(str::trim) {
+                    Some("Fixed") => DirType::Fixed,
+                    Some("Scalable") => DirType::Scalable,
+                    _ => DirType::Threshold,
+                };
+
+                Some(ThemeDir {
+                    size,
+                    scale: attr(SCALE).unwrap_or(1),
+                    min_size: attr(MIN_SIZE).unwrap_or(size),
+                    max_size: attr(MAX_SIZE).unwrap_or(size),
+                    threshold: attr(THRESHOLD).unwrap_or(2),
+                    dir_type,
+                    path,
+                })
+            })
+            .collect();
+
+        Some(Self {
+            roots,
+            parents,
+            dirs,
+        })
+    }
+
+    /// Directories of `theme_dir` in every base directory the theme is installed in.
+    pub fn dir_paths<'a>(&'a self, theme_dir: &'a ThemeDir) -> impl Iterator<Item = PathBuf> + 'a {
+        self.roots
+            .iter()
+            .map(move |root| Path::new(root).join(&theme_dir.path))
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(index: &str) -> Theme {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("test")).unwrap();
+        std::fs::write(dir.path().join("test").join(INDEX_FILE), index).unwrap();
+        Theme::load("test", &[dir.path().to_path_buf()]).unwrap()
+    }
+
+    #[test]
+    fn loads_directories_and_parents() {
+        let theme = load(
+            "[Icon Theme]\n\
+             Inherits=breeze, hicolor,\n\
+             Directories=16x16/apps,missing,scalable/apps\n\
+             ScaledDirectories=16x16@2/apps\n\
+             \n\
+             [16x16/apps]\nSize=16\nType=Fixed\n\
+             [scalable/apps]\nSize=48\nMinSize=8\nMaxSize=512\nType=Scalable\n\
+             [16x16@2/apps]\nSize=16\nScale=2\nThreshold=4\n",
+        );
+        assert_eq!(theme.parents, vec!["breeze", "hicolor"]);
+        let dir = |path: &str, size, scale, min_size, max_size, threshold, dir_type| ThemeDir {
+            path: String::from(path),
+            size,
+            scale,
+            min_size,
+            max_size,
+            threshold,
+            dir_type,
+        };
+        assert_eq!(
+            theme.dirs,
+            vec![
+                dir("16x16/apps", 16, 1, 16, 16, 2, DirType::Fixed),
+                dir("scalable/apps", 48, 1, 8, 512, 2, DirType::Scalable),
+                dir("16x16@2/apps", 16, 2, 16, 16, 4, DirType::Threshold),
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_sizes_by_type() {
+        let theme = load(
+            "[Icon Theme]\n\
+             Directories=fixed,scalable,threshold\n\
+             \n\
+             [fixed]\nSize=16\nType=Fixed\n\
+             [scalable]\nSize=48\nMinSize=8\nMaxSize=64\nType=Scalable\n\
+             [threshold]\nSize=32\nScale=2\n",
+        );
+        let (fixed, scalable, threshold) = (&theme.dirs[0], &theme.dirs[1], &theme.dirs[2]);
+
+        assert!(fixed.matches_size(16, 1));
+        assert!(!fixed.matches_size(17, 1));
+        assert!(!fixed.matches_size(16, 2));
+        assert!(scalable.matches_size(8, 1) && scalable.matches_size(64, 1));
+        assert!(!scalable.matches_size(65, 1));
+        assert!(threshold.matches_size(30, 2) && threshold.matches_size(34, 2));
+        assert!(!threshold.matches_size(35, 2) && !threshold.matches_size(32, 1));
+
+        assert_eq!(fixed.size_distance(24, 1), 8);
+        assert_eq!(fixed.size_distance(8, 1), 8);
+        assert_eq!(scalable.size_distance(32, 1), 0);
+        assert_eq!(scalable.size_distance(40, 2), 16);
+        assert_eq!(threshold.size_distance(32, 2), 0);
+        assert_eq!(threshold.size_distance(16, 1), 44);
+    }
+}
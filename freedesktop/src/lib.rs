@@ -0,0 +1,9 @@
+pub mod base_dirs;
+pub mod desktop_entry;
+pub mod executable;
+pub mod icon_theme;
+pub mod locale;
+pub mod mime;
+pub mod mime_apps;
+pub mod thumbnail;
+pub mod trash;
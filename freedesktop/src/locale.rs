@@ -0,0 +1,141 @@
+use freedesktop_entry_parser::AttrSelector;
+use lazy_static::lazy_static;
+use std::env;
+
+lazy_static! {
+    static ref LOCALES: Vec<String> = locales_from(|name| env::var(name).ok());
+}
+
+/// Locales to look localized values up with, in order of preference, e.g. `km_KH` then `km`.
+pub fn locales() -> &'static [String] {
+    &LOCALES
+}
+
+/// Value of `key` in `section` for the first of the current locales it is translated to,
+/// e.g. `Name[km_KH]` or `Name[km]`, falling back to the untranslated value.
+pub fn localized_attr<'a, T: AsRef<str>>(
+    section: &'a AttrSelector<T>,
+    key: &str,
+) -> Option<&'a str> {
+    locales()
+        .iter()
+        .find_map(|locale| section.attr_with_param(key, locale))
+        .or_else(|| section.attr(key))
+}
+
+/// Locales of messages following gettext: `LANGUAGE` comes first unless messages are not
+/// translated at all, then the first set of `LC_ALL`, `LC_MESSAGES` and `LANG`.
+fn locales_from<F: Fn(&str) -> Option<String>>(var: F) -> Vec<String> {
+    let var = |name: &str| var(name).filter(|value| !value.is_empty());
+    // Messages are not translated at all in the C locale.
+    let locale = match var("LC_ALL")
+        .or_else(|| var("LC_MESSAGES"))
+        .or_else(|| var("LANG"))
+        .filter(|locale| locale != "C" && locale != "POSIX")
+    {
+        Some(locale) => locale,
+        None => return Vec::new(),
+    };
+    let mut locales = Vec::new();
+
+    if let Some(language) = var("LANGUAGE") {
+        language
+            .split(':')
+            .for_each(|locale| add_variants(&mut locales, locale));
+    }
+    add_variants(&mut locales, &locale);
+    locales
+}
+
+/// Add the variants of `locale`, written `lang_COUNTRY.ENCODING@MODIFIER`, in the matching
+/// order of the Desktop Entry specification. The encoding never takes part in matching.
+fn add_variants(locales: &mut Vec<String>, locale: &str) {
+    let (locale, modifier) = match locale.find('@') {
+        Some(idx) => (&locale[..idx], Some(&locale[idx + 1..])),
+        None => (locale, None),
+    };
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (lang, country) = match locale.find('_') {
+        Some(idx) => (&locale[..idx], Some(&locale[idx + 1..])),
+        None => (locale, None),
+    };
+    if lang.is_empty() {
+        return;
+    }
+
+    let mut variants = Vec::new();
+    if let Some(country) = country {
+        if let Some(modifier) = modifier {
+            variants.push(format!("{}_{}@{}", lang, country, modifier));
+        }
+        variants.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{}@{}", lang, modifier));
+    }
+    variants.push(lang.to_string());
+
+    for variant in variants {
+        if !locales.contains(&variant) {
+            locales.push(variant);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locales_with(vars: &[(&str, &str)]) -> Vec<String> {
+        locales_from(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn orders_variants_of_a_locale() {
+        assert_eq!(
+            locales_with(&[("LANG", "sr_RS.UTF-8@latin")]),
+            vec!["sr_RS@latin", "sr_RS", "sr@latin", "sr"]
+        );
+        assert_eq!(
+            locales_with(&[("LANG", "km_KH.UTF-8")]),
+            vec!["km_KH", "km"]
+        );
+        assert_eq!(locales_with(&[("LANG", "km")]), vec!["km"]);
+    }
+
+    #[test]
+    fn prefers_lc_all_then_lc_messages_then_lang() {
+        let vars = [
+            ("LC_ALL", "km_KH"),
+            ("LC_MESSAGES", "fr_FR"),
+            ("LANG", "de_DE"),
+        ];
+        assert_eq!(locales_with(&vars), vec!["km_KH", "km"]);
+        assert_eq!(locales_with(&vars[1..]), vec!["fr_FR", "fr"]);
+        assert_eq!(locales_with(&vars[2..]), vec!["de_DE", "de"]);
+        // Empty variables are unset.
+        assert_eq!(
+            locales_with(&[("LC_ALL", ""), ("LANG", "de_DE")]),
+            vec!["de_DE", "de"]
+        );
+    }
+
+    #[test]
+    fn puts_language_first() {
+        assert_eq!(
+            locales_with(&[("LANGUAGE", "fr_FR:km"), ("LANG", "km_KH.UTF-8")]),
+            vec!["fr_FR", "fr", "km", "km_KH"]
+        );
+    }
+
+    #[test]
+    fn translates_nothing_in_the_c_locale() {
+        assert!(locales_with(&[("LANGUAGE", "km"), ("LANG", "C")]).is_empty());
+        assert!(locales_with(&[("LANGUAGE", "km"), ("LC_ALL", "POSIX")]).is_empty());
+        assert!(locales_with(&[("LANGUAGE", "km")]).is_empty());
+    }
+}
@@ -0,0 +1,433 @@
+mod cache;
+mod glob;
+mod magic;
+
+use crate::base_dirs;
+use glob::Glob;
+use lazy_static::lazy_static;
+use magic::MagicEntry;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub const INODE_DIRECTORY: &str = "inode/directory";
+pub const OCTET_STREAM: &str = "application/octet-stream";
+pub const TEXT_PLAIN: &str = "text/plain";
+const MIME_DIR: &str = "mime";
+const MIME_CACHE: &str = "mime.cache";
+const GLOBS2: &str = "globs2";
+const GLOBS: &str = "globs";
+const MAGIC: &str = "magic";
+const ICONS: &str = "icons";
+const GENERIC_ICONS: &str = "generic-icons";
+const ALIASES: &str = "aliases";
+const SUBCLASSES: &str = "subclasses";
+/// Upper bound of the bytes read from a file for magic sniffing.
+const MAX_MAGIC_EXTENT: usize = 64 * 1024;
+/// Bytes checked for control characters to tell text from binary data.
+const TEXT_SNIFF_LEN: usize = 128;
+
+lazy_static! {
+    static ref SHARED_MIME_INFO: SharedMimeInfo = SharedMimeInfo::new();
+}
+
+/// MIME type of the file at `path`, see [`SharedMimeInfo::guess_mime_type`].
+pub fn guess_mime_type<P: AsRef<Path>>(path: P) -> String {
+    SHARED_MIME_INFO.guess_mime_type(path.as_ref())
+}
+
+/// Canonical name of `mime_type`, which may be an alias.
+pub fn unalias(mime_type: &str) -> String {
+    SHARED_MIME_INFO.unalias(mime_type)
+}
+
+/// `mime_type` and the types it is a subclass of, see [`SharedMimeInfo::ancestors`].
+pub fn ancestors(mime_type: &str) -> Vec<String> {
+    SHARED_MIME_INFO.ancestors(mime_type)
+}
+
+/// Whether `mime_type` is `parent` or one of its subclasses.
+pub fn is_subclass(mime_type: &str, parent: &str) -> bool {
+    SHARED_MIME_INFO.is_subclass(mime_type, parent)
+}
+
+/// Icon names for `mime_type` from the most specific to the most generic.
+pub fn icon_names(mime_type: &str) -> Vec<String> {
+    SHARED_MIME_INFO.icon_names(mime_type)
+}
+
+/// The shared MIME-info database installed in the XDG data directories.
+#[derive(Debug, Clone, Default)]
+pub struct SharedMimeInfo {
+    globs: Vec<Glob>,
+    magic: Vec<MagicEntry>,
+    icons: HashMap<String, String>,
+    generic_icons: HashMap<String, String>,
+    aliases: HashMap<String, String>,
+    parents: HashMap<String, Vec<String>>,
+}
+
+/// Database of one `mime` directory, read from `mime.cache` or the files it is made from.
+#[derive(Debug, Clone, Default)]
+struct MimeDir {
+    globs: Vec<Glob>,
+    magic: Vec<MagicEntry>,
+    aliases: Vec<(String, String)>,
+    parents: Vec<(String, String)>,
+    icons: Vec<(String, String)>,
+    generic_icons: Vec<(String, String)>,
+}
+
+impl MimeDir {
+    fn read(dir: &Path) -> Self {
+        fs::read(dir.join(MIME_CACHE))
+            .ok()
+            .and_then(|bytes| cache::parse(&bytes))
+            .unwrap_or_else(|| Self::read_files(dir))
+    }
+
+    fn read_files(dir: &Path) -> Self {
+        let globs = if let Ok(globs2) = fs::read_to_string(dir.join(GLOBS2)) {
+            globs2.lines().filter_map(Glob::from_globs2).collect()
+        } else if let Ok(globs) = fs::read_to_string(dir.join(GLOBS)) {
+            globs.lines().filter_map(Glob::from_globs).collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            globs,
+            magic: fs::read(dir.join(MAGIC))
+                .map(|bytes| magic::parse(&bytes))
+                .unwrap_or_default(),
+            aliases: read_pairs(&dir.join(ALIASES)),
+            parents: read_pairs(&dir.join(SUBCLASSES)),
+            icons: read_pairs(&dir.join(ICONS)),
+            generic_icons: read_pairs(&dir.join(GENERIC_ICONS)),
+        }
+    }
+}
+
+impl SharedMimeInfo {
+    pub fn new() -> Self {
+        let dirs: Vec<PathBuf> = base_dirs::data_dirs()
+            .into_iter()
+            .map(|dir| dir.join(MIME_DIR))
+            .collect();
+        Self::from_dirs(&dirs)
+    }
+
+    /// Database of the `mime` directories `dirs`, the first ones taking precedence.
+    fn from_dirs(dirs: &[PathBuf]) -> Self {
+        let mime_dirs: Vec<MimeDir> = dirs
+            .iter()
+            .filter(|dir| dir.is_dir())
+            .map(|dir| MimeDir::read(dir))
+            .collect();
+        let mut mime_info = Self::default();
+
+        for dir in &mime_dirs {
+            mime_info.globs.extend(dir.globs.iter().cloned());
+            mime_info.magic.extend(dir.magic.iter().cloned());
+            for (child, parent) in &dir.parents {
+                let parents = mime_info.parents.entry(child.to_owned()).or_default();
+                if !parents.contains(parent) {
+                    parents.push(parent.to_owned());
+                }
+            }
+        }
+        // Directories listed first take precedence.
+        for dir in mime_dirs.into_iter().rev() {
+            mime_info.icons.extend(dir.icons);
+            mime_info.generic_icons.extend(dir.generic_icons);
+            mime_info.aliases.extend(dir.aliases);
+        }
+        mime_info.magic.sort_by_key(|entry| Reverse(entry.priority));
+
+        mime_info
+    }
+
+    /// Guess the MIME type following the shared MIME-info specification: the file name
+    /// globs decide unless they are ambiguous or unknown, then the magic rules sniff the
+    /// content, and data matching no rule is either `text/plain` or
+    /// `application/octet-stream`. A text type matched by name on binary content is a wrong
+    /// extension, sniffed as well.
+    pub fn guess_mime_type(&self, path: &Path) -> String {
+        if path.is_dir() {
+            return INODE_DIRECTORY.to_string();
+        }
+        let file_name = path.file_name().map(|name| name.to_string_lossy());
+        let glob_types = match file_name {
+            // Without a database, the extension is all there is to go by.
+            Some(_) if self.globs.is_empty() => mime_guess::from_path(path)
+                .first_raw()
+                .map(ToString::to_string)
+                .into_iter()
+                .collect(),
+            Some(file_name) => self.glob_mime_types(&file_name),
+            None => Vec::new(),
+        };
+
+        if let [glob_type] = glob_types.as_slice() {
+            if !self.is_subclass(glob_type, TEXT_PLAIN) {
+                return glob_type.clone();
+            }
+        }
+        let data = match self.read_head(path) {
+            Some(data) => data,
+            None => {
+                return glob_types
+                    .into_iter()
+                    .next()
+                    .unwrap_or_else(|| OCTET_STREAM.to_string())
+            }
+        };
+        let is_text = looks_like_text(&data);
+        if let [glob_type] = glob_types.as_slice() {
+            if is_text {
+                return glob_type.clone();
+            }
+        }
+
+        match (self.magic_mime_type(&data), glob_types.first()) {
+            // A type matched by name that is a subclass of the sniffed one is more specific.
+            (Some(magic_type), _) => glob_types
+                .iter()
+                .find(|glob_type| self.is_subclass(glob_type, &magic_type))
+                .cloned()
+                .unwrap_or(magic_type),
+            (None, Some(glob_type)) => glob_type.clone(),
+            (None, None) if is_text => TEXT_PLAIN.to_string(),
+            (None, None) => OCTET_STREAM.to_string(),
+        }
+    }
+
+    /// MIME types of the best matching globs: highest weight, then longest pattern.
+    fn glob_mime_types(&self, file_name: &str) -> Vec<String> {
+        let matches: Vec<&Glob> = self
+            .globs
+            .iter()
+            .filter(|glob| glob.matches(file_name))
+            .collect();
+        let best = matches
+            .iter()
+            .map(|glob| (glob.weight, glob.pattern_len()))
+            .max();
+        let mut mime_types = Vec::new();
+
+        for glob in matches
+            .into_iter()
+            .filter(|glob| Some((glob.weight, glob.pattern_len())) == best)
+        {
+            if !mime_types.contains(&glob.mime_type) {
+                mime_types.push(glob.mime_type.clone());
+            }
+        }
+
+        mime_types
+    }
+
+    /// Beginning of the file, as much as the magic rules and the text check look at.
+    fn read_head(&self, path: &Path) -> Option<Vec<u8>> {
+        let extent = self
+            .magic
+            .iter()
+            .map(MagicEntry::extent)
+            .max()
+            .unwrap_or(0)
+            .clamp(TEXT_SNIFF_LEN, MAX_MAGIC_EXTENT);
+        let mut data = Vec::with_capacity(extent);
+        File::open(path)
+            .ok()?
+            .take(extent as u64)
+            .read_to_end(&mut data)
+            .ok()?;
+
+        Some(data)
+    }
+
+    fn magic_mime_type(&self, data: &[u8]) -> Option<String> {
+        self.magic
+            .iter()
+            .find(|entry| entry.matches(data))
+            .map(|entry| entry.mime_type.clone())
+    }
+
+    /// Whether `mime_type` is `parent` or one of its subclasses.
+    pub fn is_subclass(&self, mime_type: &str, parent: &str) -> bool {
+        self.ancestors(mime_type).contains(&self.unalias(parent))
+    }
+
+    pub fn unalias(&self, mime_type: &str) -> String {
+        self.aliases
+            .get(mime_type)
+            .cloned()
+            .unwrap_or_else(|| mime_type.to_string())
+    }
+
+    /// The canonical name of `mime_type` followed by the types it is a subclass of, nearest
+    /// first. Text types are subclasses of `text/plain` and every type of file content is
+    /// one of `application/octet-stream`.
+    pub fn ancestors(&self, mime_type: &str) -> Vec<String> {
+        let mut ancestors = vec![self.unalias(mime_type)];
+        let mut idx = 0;
+
+        while let Some(mime_type) = ancestors.get(idx).cloned() {
+            let mut parents: Vec<String> = self
+                .parents
+                .get(&mime_type)
+                .map(|parents| parents.iter().map(|parent| self.unalias(parent)).collect())
+                .unwrap_or_default();
+            if mime_type.starts_with("text/") && mime_type != TEXT_PLAIN {
+                parents.push(TEXT_PLAIN.to_string());
+            }
+            for parent in parents {
+                if !ancestors.contains(&parent) {
+                    ancestors.push(parent);
+                }
+            }
+            idx += 1;
+        }
+
+        let is_content =
+            !(mime_type.starts_with("inode/") || mime_type.starts_with("x-scheme-handler/"));
+        if is_content && !ancestors.iter().any(|ancestor| ancestor == OCTET_STREAM) {
+            ancestors.push(OCTET_STREAM.to_string());
+        }
+        ancestors
+    }
+
+    pub fn icon_names(&self, mime_type: &str) -> Vec<String> {
+        let media = mime_type.split('/').next().unwrap_or(mime_type);
+        let icon = self
+            .icons
+            .get(mime_type)
+            .cloned()
+            .unwrap_or_else(|| mime_type.replace('/', "-"));
+        let generic_icon = self
+            .generic_icons
+            .get(mime_type)
+            .cloned()
+            .unwrap_or_else(|| format!("{}-x-generic", media));
+        let mut icon_names = vec![icon, generic_icon];
+
+        icon_names.dedup();
+        icon_names
+    }
+}
+
+/// Data is text when its beginning has no control characters other than whitespace. Bytes
+/// with the high bit set may be UTF-8.
+fn looks_like_text(data: &[u8]) -> bool {
+    data.iter()
+        .take(TEXT_SNIFF_LEN)
+        .all(|byte| (*byte >= b' ' && *byte != 0x7f) || b"\t\n\r\x0c\x1b".contains(byte))
+}
+
+/// `key:value` lines of the `icons` and `generic-icons` files, or `key value` lines of the
+/// `aliases` and `subclasses` files.
+fn read_pairs(path: &Path) -> Vec<(String, String)> {
+    let separator = if path.ends_with(ALIASES) || path.ends_with(SUBCLASSES) {
+        ' '
+    } else {
+        ':'
+    };
+
+    fs::read_to_string(path)
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| {
+                    let mut pair = line.splitn(2, separator);
+                    Some((pair.next()?.to_string(), pair.next()?.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fixture() -> SharedMimeInfo {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mime");
+        SharedMimeInfo::from_dirs(&[dir])
+    }
+
+    fn guess(file_name: &str, content: &[u8]) -> String {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(file_name);
+        fs::write(&path, content).unwrap();
+        fixture().guess_mime_type(&path)
+    }
+
+    #[test]
+    fn prefers_heavier_then_longer_globs() {
+        let mime_info = fixture();
+        assert_eq!(mime_info.glob_mime_types("main.C"), vec!["text/x-c++src"]);
+        assert_eq!(mime_info.glob_mime_types("main.c"), vec!["text/x-csrc"]);
+        assert_eq!(
+            mime_info.glob_mime_types("backup.TAR.GZ"),
+            vec!["application/x-compressed-tar"]
+        );
+        assert_eq!(
+            mime_info.glob_mime_types("clip.ts"),
+            vec!["video/mp2t", "text/vnd.trolltech.linguist"]
+        );
+    }
+
+    #[test]
+    fn sniffs_scripts_without_extension() {
+        assert_eq!(
+            guess("install", b"#!/bin/sh\necho hello\n"),
+            "application/x-shellscript"
+        );
+        assert_eq!(
+            guess("install.sh", b"echo hello\n"),
+            "application/x-shellscript"
+        );
+    }
+
+    #[test]
+    fn tells_text_from_binary_data_without_extension() {
+        assert_eq!(guess("notes", b"Buy milk\n"), TEXT_PLAIN);
+        assert_eq!(guess("blob", b"\x00\x01\x02"), OCTET_STREAM);
+    }
+
+    #[test]
+    fn sniffs_binary_data_with_a_text_extension() {
+        assert_eq!(
+            guess("photos.txt", b"PK\x03\x04\x14\x00"),
+            "application/zip"
+        );
+        assert_eq!(guess("photos.txt", b"just text"), TEXT_PLAIN);
+    }
+
+    #[test]
+    fn sniffs_ambiguous_globs() {
+        let mut transport_stream = vec![0; 189];
+        transport_stream[0] = b'G';
+        transport_stream[188] = b'G';
+        assert_eq!(guess("clip.ts", &transport_stream), "video/mp2t");
+    }
+
+    #[test]
+    fn follows_subclasses() {
+        let mime_info = fixture();
+        assert!(mime_info.is_subclass("application/x-shellscript", TEXT_PLAIN));
+        assert!(mime_info.is_subclass("text/x-c++src", TEXT_PLAIN));
+        assert_eq!(
+            mime_info.ancestors("application/x-compressed-tar"),
+            vec![
+                "application/x-compressed-tar",
+                "application/gzip",
+                OCTET_STREAM
+            ]
+        );
+        assert!(!mime_info.is_subclass(INODE_DIRECTORY, OCTET_STREAM));
+    }
+}
@@ -0,0 +1,370 @@
+use super::glob::Glob;
+use super::magic::{MagicEntry, MagicRule};
+use super::MimeDir;
+
+const MAJOR_VERSION: u16 = 1;
+const ALIAS_LIST: usize = 0;
+const PARENT_LIST: usize = 1;
+const LITERAL_LIST: usize = 2;
+const REVERSE_SUFFIX_TREE: usize = 3;
+const GLOB_LIST: usize = 4;
+const MAGIC_LIST: usize = 5;
+const ICONS_LIST: usize = 7;
+const GENERIC_ICONS_LIST: usize = 8;
+/// The weight of a glob is in its lowest bits, its flags above.
+const WEIGHT_MASK: u32 = 0xff;
+const CASE_SENSITIVE_FLAG: u32 = 0x100;
+/// Deepest tree of suffixes or matchlets followed, against loops in a corrupt cache.
+const MAX_DEPTH: usize = 255;
+
+/// Parse the binary `mime.cache` file `update-mime-database` generates in each `mime`
+/// directory. `None` when the file is corrupt or of an unknown version.
+pub fn parse(bytes: &[u8]) -> Option<MimeDir> {
+    let cache = Cache { bytes };
+    if cache.u16(0)? != MAJOR_VERSION {
+        return None;
+    }
+    let list_offset = |list: usize| cache.u32(4 + 4 * list);
+    let mut globs = cache.globs(list_offset(LITERAL_LIST)?, false)?;
+    cache.suffix_globs(list_offset(REVERSE_SUFFIX_TREE)?, &mut globs)?;
+    globs.extend(cache.globs(list_offset(GLOB_LIST)?, true)?);
+
+    Some(MimeDir {
+        globs,
+        magic: cache.magic(list_offset(MAGIC_LIST)?)?,
+        aliases: cache.pairs(list_offset(ALIAS_LIST)?)?,
+        parents: cache.parents(list_offset(PARENT_LIST)?)?,
+        icons: cache.pairs(list_offset(ICONS_LIST)?)?,
+        generic_icons: cache.pairs(list_offset(GENERIC_ICONS_LIST)?)?,
+    })
+}
+
+/// Big-endian lists and trees of offsets into the cache.
+struct Cache<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cache<'a> {
+    fn u16(&self, pos: usize) -> Option<u16> {
+        let bytes = self.bytes.get(pos..pos + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, pos: usize) -> Option<usize> {
+        let bytes = self.bytes.get(pos..pos + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    /// Nul-terminated string at the offset stored at `pos`.
+    fn string(&self, pos: usize) -> Option<String> {
+        let start = self.u32(pos)?;
+        let len = self
+            .bytes
+            .get(start..)?
+            .iter()
+            .position(|byte| *byte == 0)?;
+        String::from_utf8(self.bytes[start..start + len].to_vec()).ok()
+    }
+
+    /// Entries of `entry_size` bytes of a list starting with its length.
+    fn list<T, F>(&self, offset: usize, entry_size: usize, entry: F) -> Option<Vec<T>>
+    where
+        F: Fn(usize) -> Option<T>,
+    {
+        (0..self.u32(offset)?)
+            .map(|idx| entry(offset + 4 + idx * entry_size))
+            .collect()
+    }
+
+    /// Pairs of strings of the alias and icon lists.
+    fn pairs(&self, offset: usize) -> Option<Vec<(String, String)>> {
+        self.list(offset, 8, |pos| {
+            Some((self.string(pos)?, self.string(pos + 4)?))
+        })
+    }
+
+    fn parents(&self, offset: usize) -> Option<Vec<(String, String)>> {
+        let entries = self.list(offset, 8, |pos| {
+            let mime_type = self.string(pos)?;
+            let parents = self.list(self.u32(pos + 4)?, 4, |pos| self.string(pos))?;
+            Some((mime_type, parents))
+        })?;
+
+        Some(
+            entries
+                .into_iter()
+                .flat_map(|(mime_type, parents)| {
+                    parents
+                        .into_iter()
+                        .map(move |parent| (mime_type.clone(), parent))
+                })
+                .collect(),
+        )
+    }
+
+    /// Literal file names or glob patterns with their type and weight.
+    fn globs(&self, offset: usize, is_pattern: bool) -> Option<Vec<Glob>> {
+        self.list(offset, 12, |pos| {
+            let pattern = self.string(pos)?;
+            let (weight, case_sensitive) = split_weight(self.u32(pos + 8)? as u32);
+            // Literal names are matched as patterns without wildcards, escape them.
+            let pattern = if is_pattern {
+                pattern
+            } else {
+                pattern
+                    .replace('[', "[[]")
+                    .replace('*', "[*]")
+                    .replace('?', "[?]")
+            };
+            Some(Glob::new(
+                weight,
+                self.string(pos + 4)?,
+                pattern,
+                case_sensitive,
+            ))
+        })
+    }
+
+    /// `*.suffix` globs of the tree of suffixes read from their last character.
+    fn suffix_globs(&self, offset: usize, globs: &mut Vec<Glob>) -> Option<()> {
+        self.suffix_nodes(
+            self.u32(offset + 4)?,
+            self.u32(offset)?,
+            &mut Vec::new(),
+            globs,
+        )
+    }
+
+    fn suffix_nodes(
+        &self,
+        first: usize,
+        len: usize,
+        suffix: &mut Vec<char>,
+        globs: &mut Vec<Glob>,
+    ) -> Option<()> {
+        if suffix.len() > MAX_DEPTH {
+            return None;
+        }
+        for pos in (0..len).map(|idx| first + idx * 12) {
+            match self.u32(pos)? as u32 {
+                // A leaf holds the type of the suffix leading to it.
+                0 => {
+                    let (weight, case_sensitive) = split_weight(self.u32(pos + 8)? as u32);
+                    let pattern = Some('*').into_iter().chain(suffix.iter().rev().copied());
+                    globs.push(Glob::new(
+                        weight,
+                        self.string(pos + 4)?,
+                        pattern.collect(),
+                        case_sensitive,
+                    ));
+                }
+                character => {
+                    suffix.push(std::char::from_u32(character)?);
+                    self.suffix_nodes(self.u32(pos + 8)?, self.u32(pos + 4)?, suffix, globs)?;
+                    suffix.pop();
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn magic(&self, offset: usize) -> Option<Vec<MagicEntry>> {
+        let first = self.u32(offset + 8)?;
+
+        (0..self.u32(offset)?)
+            .map(|idx| {
+                let pos = first + idx * 16;
+                let mut rules = Vec::new();
+                self.matchlets(self.u32(pos + 12)?, self.u32(pos + 8)?, 0, &mut rules)?;
+                Some(MagicEntry::new(
+                    self.u32(pos)? as u32,
+                    self.string(pos + 4)?,
+                    rules,
+                ))
+            })
+            .collect()
+    }
+
+    /// Flatten a tree of matchlets into rules indented by their depth, as in the `magic` file.
+    ///
+    /// The word size at `pos + 8` is ignored: unlike the `magic` file, `update-mime-database`
+    /// stores values and masks as they are compared, so they need no swapping (as in xdgmime).
+    fn matchlets(
+        &self,
+        first: usize,
+        len: usize,
+        indent: u32,
+        rules: &mut Vec<MagicRule>,
+    ) -> Option<()> {
+        if indent as usize > MAX_DEPTH {
+            return None;
+        }
+        for pos in (0..len).map(|idx| first + idx * 32) {
+            let value_len = self.u32(pos + 12)?;
+            let value_offset = self.u32(pos + 16)?;
+            let mask_offset = self.u32(pos + 20)?;
+            let value = self.bytes.get(value_offset..value_offset + value_len)?;
+            let mask = match mask_offset {
+                0 => None,
+                _ => Some(
+                    self.bytes
+                        .get(mask_offset..mask_offset + value_len)?
+                        .to_vec(),
+                ),
+            };
+            rules.push(MagicRule::new(
+                indent,
+                self.u32(pos)?,
+                value.to_vec(),
+                mask,
+                self.u32(pos + 4)?.max(1),
+            ));
+            self.matchlets(self.u32(pos + 28)?, self.u32(pos + 24)?, indent + 1, rules)?;
+        }
+        Some(())
+    }
+}
+
+fn split_weight(weight: u32) -> (u32, bool) {
+    (weight & WEIGHT_MASK, weight & CASE_SENSITIVE_FLAG != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cache laid out bottom-up, each item at the offset returned when it is added.
+    struct Builder {
+        bytes: Vec<u8>,
+    }
+
+    impl Builder {
+        fn new() -> Self {
+            let mut bytes = vec![0; 40];
+            bytes[..2].copy_from_slice(&MAJOR_VERSION.to_be_bytes());
+            Self { bytes }
+        }
+
+        fn data(&mut self, data: &[u8]) -> u32 {
+            let offset = self.bytes.len() as u32;
+            self.bytes.extend_from_slice(data);
+            offset
+        }
+
+        fn string(&mut self, string: &str) -> u32 {
+            self.data(&[string.as_bytes(), b"\0"].concat())
+        }
+
+        fn u32s(&mut self, values: &[u32]) -> u32 {
+            let bytes: Vec<u8> = values
+                .iter()
+                .flat_map(|value| value.to_be_bytes())
+                .collect();
+            self.data(&bytes)
+        }
+
+        fn list(&mut self, list: usize, offset: u32) {
+            let pos = 4 + 4 * list;
+            self.bytes[pos..pos + 4].copy_from_slice(&offset.to_be_bytes());
+        }
+    }
+
+    fn cache() -> Vec<u8> {
+        let mut cache = Builder::new();
+        let zip = cache.string("application/zip");
+        let x_zip = cache.string("application/x-zip");
+        let plain = cache.string("text/plain");
+        let csrc = cache.string("text/x-csrc");
+        let makefile = cache.string("text/x-makefile");
+        let readme = cache.string("text/x-readme");
+        let makefile_name = cache.string("Makefile");
+        let readme_glob = cache.string("README*");
+
+        let aliases = cache.u32s(&[1, x_zip, zip]);
+        cache.list(ALIAS_LIST, aliases);
+        let csrc_parents = cache.u32s(&[1, plain]);
+        let parents = cache.u32s(&[1, csrc, csrc_parents]);
+        cache.list(PARENT_LIST, parents);
+        let literals = cache.u32s(&[1, makefile_name, makefile, 50 | CASE_SENSITIVE_FLAG]);
+        cache.list(LITERAL_LIST, literals);
+
+        let leaf = cache.u32s(&[0, csrc, 50]);
+        let dot = cache.u32s(&['.' as u32, 1, leaf]);
+        let c = cache.u32s(&['c' as u32, 1, dot]);
+        let suffixes = cache.u32s(&[1, c]);
+        cache.list(REVERSE_SUFFIX_TREE, suffixes);
+        let globs = cache.u32s(&[1, readme_glob, readme, 10]);
+        cache.list(GLOB_LIST, globs);
+
+        let value = cache.data(b"AB");
+        let mask = cache.data(b"\xff\0");
+        let child_value = cache.data(b"Z");
+        let child = cache.u32s(&[4, 1, 1, 1, child_value, 0, 0, 0]);
+        let matchlet = cache.u32s(&[0, 8, 2, 2, value, mask, 1, child]);
+        let entry = cache.u32s(&[60, zip, 1, matchlet]);
+        let magic = cache.u32s(&[1, 9, entry]);
+        cache.list(MAGIC_LIST, magic);
+
+        let empty = cache.u32s(&[0]);
+        cache.list(ICONS_LIST, empty);
+        cache.list(GENERIC_ICONS_LIST, empty);
+        cache.bytes
+    }
+
+    fn pair(first: &str, second: &str) -> (String, String) {
+        (String::from(first), String::from(second))
+    }
+
+    #[test]
+    fn parses_lists_and_suffix_tree() {
+        let dir = parse(&cache()).unwrap();
+        assert_eq!(
+            dir.aliases,
+            vec![pair("application/x-zip", "application/zip")]
+        );
+        assert_eq!(dir.parents, vec![pair("text/x-csrc", "text/plain")]);
+        assert_eq!(
+            dir.globs,
+            vec![
+                Glob::new(
+                    50,
+                    String::from("text/x-makefile"),
+                    String::from("Makefile"),
+                    true
+                ),
+                Glob::new(50, String::from("text/x-csrc"), String::from("*.c"), false),
+                Glob::new(
+                    10,
+                    String::from("text/x-readme"),
+                    String::from("README*"),
+                    false
+                ),
+            ]
+        );
+        assert!(dir.icons.is_empty());
+    }
+
+    #[test]
+    fn keeps_matchlet_values_as_stored() {
+        let dir = parse(&cache()).unwrap();
+        assert_eq!(
+            dir.magic,
+            vec![MagicEntry::new(
+                60,
+                String::from("application/zip"),
+                vec![
+                    MagicRule::new(0, 0, b"AB".to_vec(), Some(vec![0xff, 0]), 8),
+                    MagicRule::new(1, 4, b"Z".to_vec(), None, 1),
+                ],
+            )]
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_caches() {
+        let mut bytes = cache();
+        bytes[1] = 2;
+        assert!(parse(&bytes).is_none());
+        assert!(parse(&cache()[..100]).is_none());
+    }
+}
@@ -0,0 +1,170 @@
+const DEFAULT_WEIGHT: u32 = 50;
+const CASE_SENSITIVE: &str = "cs";
+
+/// A file name pattern of the `globs2` (or legacy `globs`) file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pub weight: u32,
+    pub mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+impl Glob {
+    /// Parse a `weight:type:pattern[:flags]` line of `globs2`.
+    pub fn from_globs2(line: &str) -> Option<Self> {
+        if line.starts_with('#') {
+            return None;
+        }
+        let mut fields = line.splitn(4, ':');
+        let weight = fields.next()?.parse().ok()?;
+        let mime_type = fields.next()?.to_string();
+        let pattern = fields.next()?.to_string();
+        let case_sensitive = fields
+            .next()
+            .map(|flags| flags.split(',').any(|flag| flag == CASE_SENSITIVE))
+            .unwrap_or(false);
+
+        Some(Self::new(weight, mime_type, pattern, case_sensitive))
+    }
+
+    /// Parse a `type:pattern` line of the legacy `globs` file.
+    pub fn from_globs(line: &str) -> Option<Self> {
+        if line.starts_with('#') {
+            return None;
+        }
+        let mut fields = line.splitn(2, ':');
+        let mime_type = fields.next()?.to_string();
+        let pattern = fields.next()?.to_string();
+
+        Some(Self::new(DEFAULT_WEIGHT, mime_type, pattern, false))
+    }
+
+    pub fn new(weight: u32, mime_type: String, pattern: String, case_sensitive: bool) -> Self {
+        let pattern = if case_sensitive {
+            pattern
+        } else {
+            pattern.to_lowercase()
+        };
+
+        Self {
+            weight,
+            mime_type,
+            pattern,
+            case_sensitive,
+        }
+    }
+
+    pub fn pattern_len(&self) -> usize {
+        self.pattern.chars().count()
+    }
+
+    pub fn matches(&self, file_name: &str) -> bool {
+        let pattern: Vec<char> = self.pattern.chars().collect();
+        let file_name: Vec<char> = if self.case_sensitive {
+            file_name.chars().collect()
+        } else {
+            file_name.to_lowercase().chars().collect()
+        };
+
+        fnmatch(&pattern, &file_name)
+    }
+}
+
+/// Shell-style matching supporting `*`, `?` and `[...]` character classes.
+fn fnmatch(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| fnmatch(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && fnmatch(rest, &name[1..]),
+        Some(('[', rest)) => match (name.split_first(), class_end(rest)) {
+            (Some((c, name_rest)), Some(end)) => {
+                matches_class(&rest[..end], *c) && fnmatch(&rest[end + 1..], name_rest)
+            }
+            (Some((c, name_rest)), None) => *c == '[' && fnmatch(rest, name_rest),
+            (None, _) => false,
+        },
+        Some((p, rest)) => name.first() == Some(p) && fnmatch(rest, &name[1..]),
+    }
+}
+
+/// Index of the `]` closing a character class, a leading `]` being part of the class.
+fn class_end(class: &[char]) -> Option<usize> {
+    let start = match class.first() {
+        Some('!') if class.get(1) == Some(&']') => 2,
+        Some('!') | Some(']') => 1,
+        _ => 0,
+    };
+
+    class[start..]
+        .iter()
+        .position(|c| *c == ']')
+        .map(|end| start + end)
+}
+
+fn matches_class(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.split_first() {
+        Some(('!', rest)) => (true, rest),
+        _ => (false, class),
+    };
+    let mut is_matched = false;
+    let mut i = 0;
+
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            is_matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            is_matched |= class[i] == c;
+            i += 1;
+        }
+    }
+
+    is_matched != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_globs2_lines() {
+        let glob = Glob::from_globs2("80:text/x-csrc:*.c:cs").unwrap();
+        assert_eq!((glob.weight, glob.mime_type.as_str()), (80, "text/x-csrc"));
+        assert!(glob.case_sensitive);
+        assert!(
+            !Glob::from_globs2("50:text/plain:*.txt")
+                .unwrap()
+                .case_sensitive
+        );
+        assert_eq!(Glob::from_globs2("# comment"), None);
+        assert_eq!(Glob::from_globs2("heavy:text/plain:*.txt"), None);
+        assert_eq!(
+            Glob::from_globs("text/plain:*.txt").unwrap().weight,
+            DEFAULT_WEIGHT
+        );
+    }
+
+    #[test]
+    fn ignores_case_unless_told() {
+        let glob = Glob::from_globs2("50:image/jpeg:*.JPG").unwrap();
+        assert!(glob.matches("photo.jpg"));
+        assert!(glob.matches("PHOTO.JPG"));
+        let glob = Glob::from_globs2("50:text/x-csrc:*.C:cs").unwrap();
+        assert!(glob.matches("main.C"));
+        assert!(!glob.matches("main.c"));
+    }
+
+    #[test]
+    fn matches_wildcards_and_classes() {
+        let glob = |pattern: &str| Glob::new(50, String::new(), pattern.to_string(), true);
+        assert!(glob("README*").matches("README.md"));
+        assert!(glob("*.tar.?z").matches("a.tar.gz"));
+        assert!(!glob("*.tar.?z").matches("a.tar.bz2"));
+        assert!(glob("*.[ch]").matches("main.h"));
+        assert!(!glob("*.[!ch]").matches("main.c"));
+        assert!(glob("*.[0-9]").matches("ls.1"));
+        assert!(glob("[]x]").matches("]"));
+        assert!(glob("a[b").matches("a[b"));
+    }
+}
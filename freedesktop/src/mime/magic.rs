@@ -0,0 +1,301 @@
+const MAGIC_HEADER: &[u8] = b"MIME-Magic\0\n";
+
+/// A line of a magic section, matching `value` somewhere in `offset..offset + range`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagicRule {
+    indent: u32,
+    offset: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+    range: usize,
+}
+
+impl MagicRule {
+    pub fn new(
+        indent: u32,
+        offset: usize,
+        value: Vec<u8>,
+        mask: Option<Vec<u8>>,
+        range: usize,
+    ) -> Self {
+        Self {
+            indent,
+            offset,
+            value,
+            mask,
+            range,
+        }
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        (self.offset..self.offset + self.range).any(|start| {
+            data.get(start..start + self.value.len())
+                .map(|bytes| match &self.mask {
+                    Some(mask) => bytes
+                        .iter()
+                        .zip(&self.value)
+                        .zip(mask)
+                        .all(|((byte, value), mask)| byte & mask == value & mask),
+                    None => bytes == self.value.as_slice(),
+                })
+                .unwrap_or(false)
+        })
+    }
+
+    fn extent(&self) -> usize {
+        self.offset + self.range + self.value.len()
+    }
+}
+
+/// A `[priority:type]` section of the `magic` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MagicEntry {
+    pub priority: u32,
+    pub mime_type: String,
+    rules: Vec<MagicRule>,
+}
+
+impl MagicEntry {
+    pub fn new(priority: u32, mime_type: String, rules: Vec<MagicRule>) -> Self {
+        Self {
+            priority,
+            mime_type,
+            rules,
+        }
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        matches_rules(&self.rules, data)
+    }
+
+    /// Number of bytes of a file needed to check this entry.
+    pub fn extent(&self) -> usize {
+        self.rules.iter().map(MagicRule::extent).max().unwrap_or(0)
+    }
+}
+
+/// A rule matches when it matches itself and, if it has any, one of its nested rules.
+fn matches_rules(rules: &[MagicRule], data: &[u8]) -> bool {
+    let mut i = 0;
+
+    while i < rules.len() {
+        let indent = rules[i].indent;
+        let end = i
+            + 1
+            + rules[i + 1..]
+                .iter()
+                .take_while(|rule| rule.indent > indent)
+                .count();
+        let children = &rules[i + 1..end];
+
+        if rules[i].matches(data) && (children.is_empty() || matches_rules(children, data)) {
+            return true;
+        }
+        i = end;
+    }
+
+    false
+}
+
+/// Parse the binary `magic` file of the shared MIME-info database.
+pub fn parse(bytes: &[u8]) -> Vec<MagicEntry> {
+    let mut entries = Vec::new();
+    let mut parser = Parser { bytes, pos: 0 };
+
+    if !bytes.starts_with(MAGIC_HEADER) {
+        return entries;
+    }
+    parser.pos = MAGIC_HEADER.len();
+
+    while parser.peek().is_some() {
+        match parser.section_header() {
+            Some((priority, mime_type)) => {
+                let mut rules = Vec::new();
+                while parser.peek().map(|b| b != b'[').unwrap_or(false) {
+                    match parser.rule() {
+                        Some(rule) => rules.push(rule),
+                        None => parser.skip_line(),
+                    }
+                }
+                entries.push(MagicEntry::new(priority, mime_type, rules));
+            }
+            None => parser.skip_line(),
+        }
+    }
+
+    entries
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        if self.peek()? == byte {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().map(|b| b.is_ascii_digit()).unwrap_or(false) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            if byte == b'\n' {
+                break;
+            }
+        }
+    }
+
+    fn section_header(&mut self) -> Option<(u32, String)> {
+        self.expect(b'[')?;
+        let priority = self.number()? as u32;
+        self.expect(b':')?;
+        let start = self.pos;
+        while self.peek()? != b']' {
+            self.pos += 1;
+        }
+        let mime_type = String::from_utf8(self.bytes[start..self.pos].to_vec()).ok()?;
+        self.expect(b']')?;
+        self.expect(b'\n')?;
+
+        Some((priority, mime_type))
+    }
+
+    fn rule(&mut self) -> Option<MagicRule> {
+        let indent = if self.peek()? == b'>' {
+            0
+        } else {
+            self.number()? as u32
+        };
+        self.expect(b'>')?;
+        let offset = self.number()?;
+        self.expect(b'=')?;
+        let len = self
+            .take(2)
+            .map(|len| usize::from(u16::from_be_bytes([len[0], len[1]])))?;
+        let mut value = self.take(len)?.to_vec();
+        let mut mask = None;
+        let mut word_size = 1;
+        let mut range = 1;
+
+        if self.peek()? == b'&' {
+            self.pos += 1;
+            mask = Some(self.take(len)?.to_vec());
+        }
+        if self.peek()? == b'~' {
+            self.pos += 1;
+            word_size = self.number()?;
+        }
+        if self.peek()? == b'+' {
+            self.pos += 1;
+            range = self.number()?.max(1);
+        }
+        self.expect(b'\n')?;
+
+        // Values are stored big-endian, words have to be swapped on little-endian hosts.
+        if cfg!(target_endian = "little") && (word_size == 2 || word_size == 4) {
+            value.chunks_mut(word_size).for_each(<[u8]>::reverse);
+            if let Some(mask) = &mut mask {
+                mask.chunks_mut(word_size).for_each(<[u8]>::reverse);
+            }
+        }
+
+        Some(MagicRule::new(indent, offset, value, mask, range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_sections(sections: &[u8]) -> Vec<MagicEntry> {
+        parse(&[MAGIC_HEADER, sections].concat())
+    }
+
+    #[test]
+    fn parses_rules_with_mask_word_size_and_range() {
+        let entries = parse_sections(b"[60:image/x-test]\n>4=\0\x02AB&\0\xff~2+8\n1>0=\0\x01X\n");
+        let value = if cfg!(target_endian = "little") {
+            b"BA".to_vec()
+        } else {
+            b"AB".to_vec()
+        };
+        let mask = if cfg!(target_endian = "little") {
+            vec![0xff, 0]
+        } else {
+            vec![0, 0xff]
+        };
+        assert_eq!(
+            entries,
+            vec![MagicEntry::new(
+                60,
+                String::from("image/x-test"),
+                vec![
+                    MagicRule::new(0, 4, value, Some(mask), 8),
+                    MagicRule::new(1, 0, b"X".to_vec(), None, 1),
+                ],
+            )]
+        );
+        assert_eq!(entries[0].extent(), 14);
+    }
+
+    #[test]
+    fn skips_unknown_lines_and_needs_the_header() {
+        let entries = parse_sections(b"[50:text/x-test]\n>0=\0\x01T\n>bad\n[oops\n[40:a/b]\n");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].rules.len(), 1);
+        assert!(entries[1].rules.is_empty());
+        assert!(parse(b"[50:text/x-test]\n>0=\0\x01T\n").is_empty());
+    }
+
+    #[test]
+    fn matches_within_the_range() {
+        let entry = &parse_sections(b"[50:a/b]\n>2=\0\x02ID+3\n")[0];
+        assert!(entry.matches(b"..ID"));
+        assert!(entry.matches(b"....ID"));
+        assert!(!entry.matches(b".....ID"));
+        assert!(!entry.matches(b"..I"));
+    }
+
+    #[test]
+    fn needs_one_nested_rule_to_match() {
+        let entry =
+            &parse_sections(b"[50:a/b]\n>0=\0\x01A\n1>1=\0\x01B\n1>1=\0\x01C\n>0=\0\x01Z\n")[0];
+        assert!(entry.matches(b"AB"));
+        assert!(entry.matches(b"AC"));
+        assert!(!entry.matches(b"AD"));
+        assert!(entry.matches(b"Z"));
+    }
+
+    #[test]
+    fn applies_masks() {
+        let entry = &parse_sections(b"[50:a/b]\n>0=\0\x01\x40&\xf0\n")[0];
+        assert!(entry.matches(b"\x4f"));
+        assert!(!entry.matches(b"\x5f"));
+    }
+}
@@ -0,0 +1,412 @@
+use crate::{base_dirs, mime};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MIMEAPPS_LIST: &str = "mimeapps.list";
+const MIMEINFO_CACHE: &str = "mimeinfo.cache";
+const DEFAULT_APPLICATIONS: &str = "Default Applications";
+const ADDED_ASSOCIATIONS: &str = "Added Associations";
+const REMOVED_ASSOCIATIONS: &str = "Removed Associations";
+const MIME_CACHE: &str = "MIME Cache";
+
+/// Desktop file IDs by MIME type, for each group of a `mimeapps.list` or `mimeinfo.cache`.
+type Groups = HashMap<String, HashMap<String, Vec<String>>>;
+
+/// Association files of one directory, the most important first.
+#[derive(Debug, Clone, Default)]
+struct AssociationDir {
+    lists: Vec<Groups>,
+    cache: Option<Groups>,
+}
+
+/// Applications associated with MIME types following the XDG MIME Applications specification.
+#[derive(Debug, Clone, Default)]
+pub struct MimeApps {
+    dirs: Vec<AssociationDir>,
+}
+
+/// Desktop file ID of the default application for `mime_type`.
+pub fn default_app(mime_type: &str) -> Option<String> {
+    MimeApps::new().default_app(mime_type)
+}
+
+/// Desktop file IDs of the applications able to open `mime_type`, the default one first.
+pub fn associated_apps(mime_type: &str) -> Vec<String> {
+    MimeApps::new().associated_apps(mime_type)
+}
+
+/// Make `app` the default application for `mime_type` in the `mimeapps.list` of the user.
+pub fn set_default_app(mime_type: &str, app: &str) -> io::Result<()> {
+    let config_home = dirs_next::config_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    let path = config_home.join(MIMEAPPS_LIST);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    let without_app = |apps: Vec<String>| apps.into_iter().filter(|id| id != app).collect();
+
+    let content = update_entry(&content, DEFAULT_APPLICATIONS, mime_type, |_| {
+        vec![app.to_string()]
+    });
+    // Tools setting a default also add it first to the associations.
+    let content = update_entry(&content, ADDED_ASSOCIATIONS, mime_type, |apps| {
+        Some(app.to_string())
+            .into_iter()
+            .chain(without_app(apps))
+            .collect()
+    });
+    let content = update_entry(&content, REMOVED_ASSOCIATIONS, mime_type, without_app);
+
+    fs::create_dir_all(&config_home)?;
+    fs::write(path, content)
+}
+
+/// Desktop file with the ID `app`, the first found in the XDG data directories.
+pub fn app_path(app: &str) -> Option<PathBuf> {
+    // `kde-kate.desktop` may be installed as `kde/kate.desktop`.
+    let candidates: Vec<PathBuf> = Some(PathBuf::from(app))
+        .into_iter()
+        .chain(
+            app.match_indices('-')
+                .map(|(idx, _)| Path::new(&app[..idx]).join(&app[idx + 1..])),
+        )
+        .collect();
+
+    base_dirs::applications_dirs().into_iter().find_map(|dir| {
+        candidates
+            .iter()
+            .map(|candidate| dir.join(candidate))
+            .find(|path| path.is_file())
+    })
+}
+
+/// ID of the desktop file at `path`, its path below an `applications` directory with
+/// slashes turned into dashes.
+pub fn desktop_file_id<P: AsRef<Path>>(path: P) -> Option<String> {
+    let path = path.as_ref();
+
+    base_dirs::applications_dirs().into_iter().find_map(|dir| {
+        let relative = path.strip_prefix(dir).ok()?;
+        let id: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        Some(id.join("-"))
+    })
+}
+
+impl MimeApps {
+    /// Read the association files of the user and the system, in the order the specification
+    /// looks them up in.
+    pub fn new() -> Self {
+        let desktops: Vec<String> = env::var("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|desktop| !desktop.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let list_names: Vec<String> = desktops
+            .iter()
+            .map(|desktop| format!("{}-{}", desktop, MIMEAPPS_LIST))
+            .chain(Some(MIMEAPPS_LIST.to_string()))
+            .collect();
+        let read_lists = |dir: &Path| -> Vec<Groups> {
+            list_names
+                .iter()
+                .filter_map(|name| fs::read_to_string(dir.join(name)).ok())
+                .map(|content| parse_groups(&content))
+                .collect()
+        };
+
+        let config_dirs = base_dirs::config_dirs()
+            .into_iter()
+            .map(|dir| AssociationDir {
+                lists: read_lists(&dir),
+                cache: None,
+            });
+        let data_dirs = base_dirs::applications_dirs().into_iter().map(|dir| AssociationDir {
+            lists: read_lists(&dir),
+            cache: fs::read_to_string(dir.join(MIMEINFO_CACHE))
+                .ok()
+                .map(|content| parse_groups(&content)),
+        });
+
+        Self {
+            dirs: config_dirs.chain(data_dirs).collect(),
+        }
+    }
+
+    /// Desktop file ID of the default application for `mime_type`, falling back to the types
+    /// it is a subclass of.
+    pub fn default_app(&self, mime_type: &str) -> Option<String> {
+        mime::ancestors(mime_type).iter().find_map(|mime_type| {
+            self.explicit_default(mime_type)
+                .or_else(|| self.associations(mime_type).into_iter().next())
+        })
+    }
+
+    /// Desktop file IDs of the applications able to open `mime_type` or the types it is a
+    /// subclass of, the default one first.
+    pub fn associated_apps(&self, mime_type: &str) -> Vec<String> {
+        let mut apps: Vec<String> = self.default_app(mime_type).into_iter().collect();
+
+        for mime_type in mime::ancestors(mime_type) {
+            for app in self.associations(&mime_type) {
+                if !apps.contains(&app) {
+                    apps.push(app);
+                }
+            }
+        }
+        apps
+    }
+
+    /// First installed application of a `[Default Applications]` group for `mime_type`.
+    fn explicit_default(&self, mime_type: &str) -> Option<String> {
+        self.dirs
+            .iter()
+            .flat_map(|dir| dir.lists.iter())
+            .filter_map(|list| list.get(DEFAULT_APPLICATIONS)?.get(mime_type))
+            .flatten()
+            .find(|app| app_path(app).is_some())
+            .cloned()
+    }
+
+    /// Installed applications associated with exactly `mime_type`. Associations removed in a
+    /// directory only hide those of the same or less important directories.
+    fn associations(&self, mime_type: &str) -> Vec<String> {
+        let mut apps: Vec<String> = Vec::new();
+        let mut removed: Vec<String> = Vec::new();
+        let apps_of = |groups: &Groups, group: &str| -> Vec<String> {
+            groups
+                .get(group)
+                .and_then(|group| group.get(mime_type))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        for dir in &self.dirs {
+            for list in &dir.lists {
+                for app in apps_of(list, ADDED_ASSOCIATIONS) {
+                    if !removed.contains(&app) && !apps.contains(&app) {
+                        apps.push(app);
+                    }
+                }
+            }
+            for list in &dir.lists {
+                removed.extend(apps_of(list, REMOVED_ASSOCIATIONS));
+            }
+            if let Some(cache) = &dir.cache {
+                for app in apps_of(cache, MIME_CACHE) {
+                    if !removed.contains(&app) && !apps.contains(&app) {
+                        apps.push(app);
+                    }
+                }
+            }
+        }
+
+        apps.retain(|app| app_path(app).is_some());
+        apps
+    }
+}
+
+/// Groups of a `mimeapps.list` or `mimeinfo.cache` file. A key repeated in a group adds to
+/// the IDs it had.
+fn parse_groups(content: &str) -> Groups {
+    let mut groups = Groups::new();
+    let mut group = None;
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            group = Some(line[1..line.len() - 1].to_string());
+        } else if let (Some(group), Some((key, value))) = (&group, split_entry(line)) {
+            groups
+                .entry(group.to_owned())
+                .or_default()
+                .entry(key.to_string())
+                .or_default()
+                .extend(split_ids(value));
+        }
+    }
+    groups
+}
+
+fn split_entry(line: &str) -> Option<(&str, &str)> {
+    let mut entry = line.splitn(2, '=');
+    Some((entry.next()?.trim(), entry.next()?.trim()))
+}
+
+fn split_ids(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(ToString::to_string)
+}
+
+/// Replace the IDs of `key` in `group` of a `mimeapps.list` with those `update` returns,
+/// keeping every other line. An empty list removes the key.
+fn update_entry<F>(content: &str, group: &str, key: &str, update: F) -> String
+where
+    F: FnOnce(Vec<String>) -> Vec<String>,
+{
+    let header = format!("[{}]", group);
+    let mut lines: Vec<String> = content.lines().map(ToString::to_string).collect();
+
+    match lines.iter().position(|line| line.trim() == header) {
+        Some(start) => {
+            let end = lines[start + 1..]
+                .iter()
+                .position(|line| line.trim().starts_with('['))
+                .map_or(lines.len(), |len| start + 1 + len);
+            let entry_idx = (start + 1..end).find(|idx| {
+                matches!(split_entry(lines[*idx].trim()), Some((entry_key, _)) if entry_key == key)
+            });
+            let ids = entry_idx
+                .and_then(|idx| split_entry(lines[idx].trim()))
+                .map(|(_, value)| split_ids(value).collect())
+                .unwrap_or_default();
+            let ids = update(ids);
+
+            match (entry_idx, ids.is_empty()) {
+                (Some(idx), true) => {
+                    lines.remove(idx);
+                }
+                (Some(idx), false) => lines[idx] = entry_line(key, &ids),
+                (None, true) => {}
+                (None, false) => {
+                    // After the last entry of the group, before the blank line separating groups.
+                    let idx = (start + 1..end)
+                        .rev()
+                        .find(|idx| !lines[*idx].trim().is_empty())
+                        .map_or(start + 1, |idx| idx + 1);
+                    lines.insert(idx, entry_line(key, &ids));
+                }
+            }
+        }
+        None => {
+            let ids = update(Vec::new());
+            if !ids.is_empty() {
+                if matches!(lines.last(), Some(line) if !line.trim().is_empty()) {
+                    lines.push(String::new());
+                }
+                lines.push(header);
+                lines.push(entry_line(key, &ids));
+            }
+        }
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+fn entry_line(key: &str, ids: &[String]) -> String {
+    format!("{}={};", key, ids.join(";"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIMEAPPS: &str = "\
+# Written by hand
+[Default Applications]
+text/plain=gedit.desktop;
+image/png=eog.desktop;
+
+[Added Associations]
+text/plain=gedit.desktop;code.desktop;
+";
+
+    fn prepend(app: &str) -> impl FnOnce(Vec<String>) -> Vec<String> + '_ {
+        move |ids| {
+            std::iter::once(app.to_string())
+                .chain(ids.into_iter().filter(|id| id != app))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn replaces_an_existing_key() {
+        assert_eq!(
+            update_entry(MIMEAPPS, DEFAULT_APPLICATIONS, "text/plain", |_| vec![
+                String::from("code.desktop")
+            ]),
+            MIMEAPPS.replace("text/plain=gedit.desktop;\n", "text/plain=code.desktop;\n")
+        );
+        assert_eq!(
+            update_entry(
+                MIMEAPPS,
+                ADDED_ASSOCIATIONS,
+                "text/plain",
+                prepend("code.desktop")
+            ),
+            MIMEAPPS.replace(
+                "text/plain=gedit.desktop;code.desktop;",
+                "text/plain=code.desktop;gedit.desktop;"
+            )
+        );
+    }
+
+    #[test]
+    fn adds_a_key_after_the_last_entry_of_its_group() {
+        assert_eq!(
+            update_entry(
+                MIMEAPPS,
+                DEFAULT_APPLICATIONS,
+                "text/html",
+                prepend("firefox.desktop")
+            ),
+            MIMEAPPS.replace(
+                "image/png=eog.desktop;\n",
+                "image/png=eog.desktop;\ntext/html=firefox.desktop;\n"
+            )
+        );
+    }
+
+    #[test]
+    fn adds_a_missing_group() {
+        assert_eq!(
+            update_entry(
+                MIMEAPPS,
+                REMOVED_ASSOCIATIONS,
+                "text/plain",
+                prepend("vim.desktop")
+            ),
+            format!(
+                "{}\n[Removed Associations]\ntext/plain=vim.desktop;\n",
+                MIMEAPPS
+            )
+        );
+        assert_eq!(
+            update_entry(
+                "",
+                DEFAULT_APPLICATIONS,
+                "text/plain",
+                prepend("gedit.desktop")
+            ),
+            "[Default Applications]\ntext/plain=gedit.desktop;\n"
+        );
+    }
+
+    #[test]
+    fn removes_a_key_left_without_ids() {
+        assert_eq!(
+            update_entry(MIMEAPPS, DEFAULT_APPLICATIONS, "image/png", |_| Vec::new()),
+            MIMEAPPS.replace("image/png=eog.desktop;\n", "")
+        );
+        // Nothing is written for a group or a key which would stay empty.
+        assert_eq!(
+            update_entry(MIMEAPPS, REMOVED_ASSOCIATIONS, "text/plain", |_| Vec::new()),
+            MIMEAPPS
+        );
+    }
+}
@@ -0,0 +1,310 @@
+mod png_text;
+mod thumbnailer;
+
+use crate::mime;
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+use lazy_static::lazy_static;
+use std::env;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use thiserror::Error;
+use thumbnailer::Thumbnailer;
+use url::Url;
+
+const THUMBNAILS_DIR: &str = "thumbnails";
+const FAIL_DIR: &str = "fail";
+/// Private directory the external thumbnailers write to, out of the shared temporary one.
+const OUTPUT_DIR: &str = "koompi-output";
+/// Name of this application in the fail directory, as required by the specification.
+const APP_NAME: &str = "koompi-desktop-0.1.0";
+const SOFTWARE: &str = "KOOMPI Desktop";
+const THUMB_URI: &str = "Thumb::URI";
+const THUMB_MTIME: &str = "Thumb::MTime";
+const THUMB_SIZE: &str = "Thumb::Size";
+const THUMB_MIMETYPE: &str = "Thumb::Mimetype";
+const SOFTWARE_KEY: &str = "Software";
+/// Image types thumbnailed without an external thumbnailer.
+const IMAGE_MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/bmp",
+    "image/x-bmp",
+    "image/x-icon",
+    "image/vnd.microsoft.icon",
+    "image/tiff",
+    "image/webp",
+    "image/x-tga",
+    "image/x-portable-anymap",
+    "image/x-portable-bitmap",
+    "image/x-portable-graymap",
+    "image/x-portable-pixmap",
+];
+
+lazy_static! {
+    static ref CACHE_DIR: PathBuf = dirs_next::cache_dir()
+        .unwrap_or_default()
+        .join(THUMBNAILS_DIR);
+    static ref THUMBNAILERS: Vec<Thumbnailer> = Thumbnailer::all();
+}
+
+#[derive(Debug, Error)]
+pub enum ThumbnailError {
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error(transparent)]
+    ImageError(#[from] image::ImageError),
+    #[error(transparent)]
+    PopenError(#[from] subprocess::PopenError),
+    #[error("cannot make a thumbnail of {0}")]
+    Unsupported(String),
+    #[error("thumbnailer failed to make a thumbnail")]
+    BadThumbnailer,
+    #[error("thumbnailer took too long to make a thumbnail")]
+    TimedOut,
+    #[error("invalid file path: {0}")]
+    InvalidPath(String),
+}
+
+/// Sizes of the thumbnail directories defined by the specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThumbnailSize {
+    Normal,
+    Large,
+    XLarge,
+    XXLarge,
+}
+
+impl ThumbnailSize {
+    /// The smallest size holding `pixels` without upscaling.
+    pub fn for_pixels(pixels: u16) -> Self {
+        use ThumbnailSize::*;
+
+        [Normal, Large, XLarge]
+            .iter()
+            .copied()
+            .find(|size| u32::from(pixels) <= size.pixels())
+            .unwrap_or(XXLarge)
+    }
+
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+            ThumbnailSize::XLarge => 512,
+            ThumbnailSize::XXLarge => 1024,
+        }
+    }
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+            ThumbnailSize::XLarge => "x-large",
+            ThumbnailSize::XXLarge => "xx-large",
+        }
+    }
+}
+
+/// URI and modification time identifying the version of a file a thumbnail was made from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Original {
+    uri: String,
+    mtime: String,
+    size: u64,
+}
+
+impl Original {
+    fn new(path: &Path) -> Result<Self, ThumbnailError> {
+        let invalid_path = || ThumbnailError::InvalidPath(path.display().to_string());
+        // Symbolic links are kept, as other applications hash the URI of the path as given.
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            env::current_dir()?.join(path)
+        };
+        let metadata = path.metadata()?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| invalid_path())?
+            .as_secs();
+        let uri = Url::from_file_path(&path).map_err(|_| invalid_path())?;
+
+        Ok(Self {
+            uri: uri.to_string(),
+            mtime: mtime.to_string(),
+            size: metadata.len(),
+        })
+    }
+
+    fn file_name(&self) -> String {
+        format!("{:x}.png", md5::compute(self.uri.as_bytes()))
+    }
+
+    /// Whether the PNG at `thumbnail` was made from this version of the file.
+    fn is_thumbnailed_in(&self, thumbnail: &Path) -> bool {
+        fs::read(thumbnail)
+            .map(|png| {
+                let texts = png_text::read_text(&png);
+                texts.get(THUMB_URI) == Some(&self.uri)
+                    && texts.get(THUMB_MTIME) == Some(&self.mtime)
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Path of an up to date thumbnail of `path` in the shared cache, if any.
+pub fn find_thumbnail<P: AsRef<Path>>(path: P, size: ThumbnailSize) -> Option<PathBuf> {
+    let original = Original::new(path.as_ref()).ok()?;
+    let thumbnail = CACHE_DIR.join(size.dir_name()).join(original.file_name());
+
+    Some(thumbnail).filter(|thumbnail| original.is_thumbnailed_in(thumbnail))
+}
+
+/// Whether a previous attempt to thumbnail this version of `path` failed.
+pub fn has_failed<P: AsRef<Path>>(path: P) -> bool {
+    Original::new(path.as_ref())
+        .map(|original| {
+            original.is_thumbnailed_in(
+                &CACHE_DIR
+                    .join(FAIL_DIR)
+                    .join(APP_NAME)
+                    .join(original.file_name()),
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// Whether a thumbnail can be made for `path`, from its MIME type.
+pub fn can_thumbnail<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    // Thumbnails of thumbnails are not wanted.
+    let is_cached = path
+        .canonicalize()
+        .map(|path| path.starts_with(CACHE_DIR.as_path()))
+        .unwrap_or(true);
+    let mime_type = mime::guess_mime_type(path);
+
+    !is_cached
+        && path.is_file()
+        && (IMAGE_MIME_TYPES.contains(&mime_type.as_str())
+            || THUMBNAILERS
+                .iter()
+                .any(|thumbnailer| thumbnailer.supports(&mime_type)))
+}
+
+/// Make a thumbnail of `path` in the shared cache, unless an up to date one exists. A
+/// failure is recorded in the fail directory so it is not tried again.
+pub fn generate_thumbnail<P: AsRef<Path>>(
+    path: P,
+    size: ThumbnailSize,
+) -> Result<PathBuf, ThumbnailError> {
+    let path = path.as_ref();
+    let original = Original::new(path)?;
+    let thumbnail = CACHE_DIR.join(size.dir_name()).join(original.file_name());
+
+    if original.is_thumbnailed_in(&thumbnail) {
+        return Ok(thumbnail);
+    }
+    let mime_type = mime::guess_mime_type(path);
+
+    match render(path, &original, &mime_type, size) {
+        Ok(image) => {
+            save(&image, &original, &mime_type, &thumbnail)?;
+            Ok(thumbnail)
+        }
+        Err(err) => {
+            let fail_thumbnail = CACHE_DIR
+                .join(FAIL_DIR)
+                .join(APP_NAME)
+                .join(original.file_name());
+            save(
+                &DynamicImage::new_rgba8(1, 1),
+                &original,
+                &mime_type,
+                &fail_thumbnail,
+            )?;
+            Err(err)
+        }
+    }
+}
+
+fn render(
+    path: &Path,
+    original: &Original,
+    mime_type: &str,
+    size: ThumbnailSize,
+) -> Result<DynamicImage, ThumbnailError> {
+    let pixels = size.pixels();
+    let image = if IMAGE_MIME_TYPES.contains(&mime_type) {
+        image::open(path)?
+    } else {
+        let thumbnailer = THUMBNAILERS
+            .iter()
+            .find(|thumbnailer| thumbnailer.supports(mime_type))
+            .ok_or_else(|| ThumbnailError::Unsupported(mime_type.to_string()))?;
+        let output_dir = CACHE_DIR.join(OUTPUT_DIR);
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&output_dir)?;
+        let output = output_dir.join(format!("{}-{}", std::process::id(), original.file_name()));
+        let result = thumbnailer
+            .run(&original.uri, path, &output, pixels)
+            .and_then(|_| image::open(&output).map_err(ThumbnailError::from));
+        let _ = fs::remove_file(&output);
+        result?
+    };
+
+    // Images already small enough are kept as they are, never scaled up.
+    if image.width() > pixels || image.height() > pixels {
+        Ok(image.thumbnail(pixels, pixels))
+    } else {
+        Ok(image)
+    }
+}
+
+/// Write `image` with the required metadata, through a temporary file so that other
+/// programs never read a partial thumbnail.
+fn save(
+    image: &DynamicImage,
+    original: &Original,
+    mime_type: &str,
+    thumbnail: &Path,
+) -> Result<(), ThumbnailError> {
+    let dir = thumbnail
+        .parent()
+        .ok_or_else(|| ThumbnailError::InvalidPath(thumbnail.display().to_string()))?;
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    let mut png = Vec::new();
+    image.write_to(&mut png, ImageOutputFormat::Png)?;
+    let size = original.size.to_string();
+    let png = png_text::insert_text(
+        &png,
+        &[
+            (THUMB_URI, &original.uri),
+            (THUMB_MTIME, &original.mtime),
+            (THUMB_SIZE, &size),
+            (THUMB_MIMETYPE, mime_type),
+            (SOFTWARE_KEY, SOFTWARE),
+        ],
+    )
+    .ok_or_else(|| ThumbnailError::InvalidPath(thumbnail.display().to_string()))?;
+
+    let temp_path = thumbnail.with_extension(format!("png.{}.tmp", std::process::id()));
+    let mut temp_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&temp_path)?;
+    temp_file.write_all(&png)?;
+    fs::rename(&temp_path, thumbnail)?;
+
+    Ok(())
+}
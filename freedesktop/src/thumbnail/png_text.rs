@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const TEXT: &[u8] = b"tEXt";
+const INTERNATIONAL_TEXT: &[u8] = b"iTXt";
+const IMAGE_END: &[u8] = b"IEND";
+
+/// Uncompressed text chunks of a PNG file.
+pub fn read_text(png: &[u8]) -> HashMap<String, String> {
+    let mut texts = HashMap::new();
+
+    if png.starts_with(PNG_SIGNATURE) {
+        for (chunk_type, data) in chunks(png) {
+            if chunk_type == TEXT {
+                let mut fields = data.splitn(2, |b| *b == 0);
+                if let (Some(key), Some(value)) = (fields.next(), fields.next()) {
+                    texts.insert(latin1(key), latin1(value));
+                }
+            } else if chunk_type == INTERNATIONAL_TEXT {
+                // keyword, compression flag and method, language tag, translated keyword, text
+                let mut fields = data.splitn(2, |b| *b == 0);
+                if let (Some(key), Some(rest)) = (fields.next(), fields.next()) {
+                    if rest.first() == Some(&0) {
+                        let mut fields = rest.get(2..).unwrap_or_default().splitn(3, |b| *b == 0);
+                        if let Some(value) = fields.nth(2) {
+                            texts.insert(latin1(key), String::from_utf8_lossy(value).into_owned());
+                        }
+                    }
+                }
+            } else if chunk_type == IMAGE_END {
+                break;
+            }
+        }
+    }
+
+    texts
+}
+
+/// Copy of `png` with a text chunk for every key and value added after the header.
+pub fn insert_text(png: &[u8], texts: &[(&str, &str)]) -> Option<Vec<u8>> {
+    if !png.starts_with(PNG_SIGNATURE) {
+        return None;
+    }
+    let (_, header) = chunks(png).next()?;
+    let header_end = PNG_SIGNATURE.len() + 12 + header.len();
+    let mut output = png[..header_end].to_vec();
+
+    for (key, value) in texts {
+        let mut data = to_latin1(key);
+        data.push(0);
+        data.extend(to_latin1(value));
+
+        output.extend(&(data.len() as u32).to_be_bytes());
+        let crc_start = output.len();
+        output.extend(TEXT);
+        output.extend(&data);
+        let crc = crc32fast::hash(&output[crc_start..]);
+        output.extend(&crc.to_be_bytes());
+    }
+    output.extend(&png[header_end..]);
+
+    Some(output)
+}
+
+/// Type and data of each chunk following the PNG signature.
+fn chunks(png: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut pos = PNG_SIGNATURE.len();
+
+    std::iter::from_fn(move || {
+        let len = u32::from_be_bytes(png.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let chunk_type = png.get(pos + 4..pos + 8)?;
+        let data = png.get(pos + 8..pos + 8 + len)?;
+        pos += 12 + len;
+        Some((chunk_type, data))
+    })
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| char::from(*b)).collect()
+}
+
+fn to_latin1(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+        .collect()
+}
@@ -0,0 +1,93 @@
+use super::ThumbnailError;
+use crate::base_dirs;
+use crate::desktop_entry::ThumbnailerExec;
+use crate::executable::find_program;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use subprocess::Exec;
+
+const THUMBNAILERS_DIR: &str = "thumbnailers";
+const THUMBNAILER_ENTRY: &str = "Thumbnailer Entry";
+const TRY_EXEC: &str = "TryExec";
+const EXEC: &str = "Exec";
+const MIME_TYPE: &str = "MimeType";
+/// Time after which a thumbnailer is killed, so that a hung one does not stop the others.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An external thumbnailer described by a `.thumbnailer` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnailer {
+    exec: ThumbnailerExec,
+    mime_types: Vec<String>,
+}
+
+impl Thumbnailer {
+    /// Installed thumbnailers, a file in a preferred data directory hiding one of the same name.
+    pub fn all() -> Vec<Self> {
+        let mut file_names = HashSet::new();
+
+        base_dirs::data_dirs()
+            .into_iter()
+            .filter_map(|dir| fs::read_dir(dir.join(THUMBNAILERS_DIR)).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .map(|ext| ext == "thumbnailer")
+                    .unwrap_or(false)
+            })
+            .filter(|path| file_names.insert(path.file_name().map(ToOwned::to_owned)))
+            .filter_map(|path| Self::from_file(&path))
+            .collect()
+    }
+
+    fn from_file(path: &Path) -> Option<Self> {
+        let entry = freedesktop_entry_parser::parse_entry(path).ok()?;
+        let section = entry.section(THUMBNAILER_ENTRY);
+        let exec: ThumbnailerExec = section.attr(EXEC)?.parse().ok()?;
+        let program = section.attr(TRY_EXEC).or_else(|| exec.program())?;
+        let mime_types = section
+            .attr(MIME_TYPE)?
+            .split(';')
+            .filter(|mime_type| !mime_type.is_empty())
+            .map(ToString::to_string)
+            .collect();
+
+        find_program(program).map(|_| Self { exec, mime_types })
+    }
+
+    pub fn supports(&self, mime_type: &str) -> bool {
+        self.mime_types
+            .iter()
+            .any(|supported| supported == mime_type)
+    }
+
+    /// Run the thumbnailer for the file at `input` writing a PNG of at most `size` pixels to `output`.
+    pub fn run(
+        &self,
+        uri: &str,
+        input: &Path,
+        output: &Path,
+        size: u32,
+    ) -> Result<(), ThumbnailError> {
+        let mut args = self.exec.expand(uri, input, output, size).into_iter();
+        let program = args.next().ok_or(ThumbnailError::BadThumbnailer)?;
+        let mut child = Exec::cmd(program).args(&args.collect::<Vec<_>>()).popen()?;
+        let status = match child.wait_timeout(TIMEOUT)? {
+            Some(status) => status,
+            None => {
+                child.kill()?;
+                child.wait()?;
+                return Err(ThumbnailError::TimedOut);
+            }
+        };
+
+        if status.success() && output.is_file() {
+            Ok(())
+        } else {
+            Err(ThumbnailError::BadThumbnailer)
+        }
+    }
+}
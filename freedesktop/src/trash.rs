@@ -0,0 +1,497 @@
+use lazy_static::lazy_static;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const TRASH_DIR: &str = "Trash";
+const FILES_DIR: &str = "files";
+const INFO_DIR: &str = "info";
+const INFO_EXTENSION: &str = "trashinfo";
+const TRASH_INFO: &str = "[Trash Info]";
+const PATH_KEY: &str = "Path=";
+const DELETION_DATE_KEY: &str = "DeletionDate=";
+/// Cache of the size of trashed directories, outdated once the trash is emptied.
+const DIRECTORY_SIZES: &str = "directorysizes";
+const MOUNTS: &str = "/proc/mounts";
+const STICKY_BIT: u32 = 0o1000;
+
+lazy_static! {
+    static ref HOME_TRASH: PathBuf = dirs_next::data_dir().unwrap_or_default().join(TRASH_DIR);
+}
+
+#[derive(Debug, Error)]
+pub enum TrashError {
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+    #[error("no trash can hold {0}")]
+    NoTrash(String),
+    #[error("cannot trash {0}")]
+    InvalidPath(String),
+    #[error("{0} already exists")]
+    AlreadyExists(String),
+}
+
+/// A trash directory, either the home trash or the one at the top of a mounted file system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TrashDir {
+    path: PathBuf,
+    // Top directory of the mount, original paths are stored relative to it.
+    topdir: Option<PathBuf>,
+}
+
+impl TrashDir {
+    fn home() -> Self {
+        Self {
+            path: HOME_TRASH.to_path_buf(),
+            topdir: None,
+        }
+    }
+
+    /// `$topdir/.Trash/$uid` when the administrator made a shared `.Trash`, otherwise
+    /// `$topdir/.Trash-$uid`.
+    fn candidates(topdir: &Path) -> Vec<Self> {
+        // SAFETY: `getuid` cannot fail.
+        let uid = unsafe { libc::getuid() };
+        let shared = topdir.join(".Trash");
+        // The shared trash must be a real sticky directory, not a link to somewhere else.
+        let is_shared_valid = fs::symlink_metadata(&shared)
+            .map(|metadata| metadata.is_dir() && metadata.permissions().mode() & STICKY_BIT != 0)
+            .unwrap_or(false);
+        let mut candidates = Vec::new();
+
+        if is_shared_valid {
+            candidates.push(shared.join(uid.to_string()));
+        }
+        candidates.push(topdir.join(format!(".Trash-{}", uid)));
+        candidates
+            .into_iter()
+            .map(|path| Self {
+                path,
+                topdir: Some(topdir.to_path_buf()),
+            })
+            .collect()
+    }
+
+    /// Trash directories of the user that exist now.
+    fn all() -> Vec<Self> {
+        let mounts = fs::read(MOUNTS).unwrap_or_default();
+        let mut trash_dirs = vec![Self::home()];
+
+        trash_dirs.extend(
+            mounts
+                .split(|b| *b == b'\n')
+                .filter_map(|line| line.split(|b| *b == b' ').nth(1))
+                .map(|mount_point| PathBuf::from(OsString::from_vec(unescape_mount(mount_point))))
+                .flat_map(|topdir| Self::candidates(&topdir))
+                .filter(|trash_dir| trash_dir.files_dir().is_dir()),
+        );
+        trash_dirs.dedup();
+        trash_dirs
+    }
+
+    /// Trash directory for `path`: the home trash for a file on the same file system,
+    /// otherwise the trash at the top of its mount.
+    fn for_path(path: &Path) -> Result<Self, TrashError> {
+        let device = fs::symlink_metadata(path)?.dev();
+        let home = Self::home();
+        home.create()?;
+
+        if fs::metadata(&home.path)?.dev() == device {
+            return Ok(home);
+        }
+        let topdir = topdir(path, device)?;
+        Self::candidates(&topdir)
+            .into_iter()
+            .find(|trash_dir| trash_dir.create().is_ok())
+            .ok_or_else(|| TrashError::NoTrash(path.display().to_string()))
+    }
+
+    fn files_dir(&self) -> PathBuf {
+        self.path.join(FILES_DIR)
+    }
+
+    fn info_dir(&self) -> PathBuf {
+        self.path.join(INFO_DIR)
+    }
+
+    fn info_path(&self, name: &OsStr) -> PathBuf {
+        let mut file_name = name.to_os_string();
+        file_name.push(".");
+        file_name.push(INFO_EXTENSION);
+        self.info_dir().join(file_name)
+    }
+
+    fn create(&self) -> Result<(), TrashError> {
+        let mut builder = DirBuilder::new();
+        builder.recursive(true).mode(0o700);
+        builder.create(self.files_dir())?;
+        builder.create(self.info_dir())?;
+        Ok(())
+    }
+
+    fn items(&self) -> Vec<TrashItem> {
+        fs::read_dir(self.info_dir())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension() == Some(OsStr::new(INFO_EXTENSION)))
+            .filter_map(|path| TrashItem::from_info(self, &path))
+            .collect()
+    }
+}
+
+/// A file in the trash with the location it was deleted from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashItem {
+    pub original_path: PathBuf,
+    /// Local time of the deletion, as `YYYY-MM-DDThh:mm:ss`.
+    pub deletion_date: String,
+    name: OsString,
+    trash_dir: TrashDir,
+}
+
+impl TrashItem {
+    fn from_info(trash_dir: &TrashDir, info_path: &Path) -> Option<Self> {
+        let info = fs::read(info_path).ok()?;
+        let mut lines = info
+            .split(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+
+        if lines.next()? != TRASH_INFO.as_bytes() {
+            return None;
+        }
+        let (mut path, mut deletion_date) = (None, None);
+        for line in lines {
+            if let Some(value) = line.strip_prefix(PATH_KEY.as_bytes()) {
+                path = Some(PathBuf::from(OsString::from_vec(percent_decode(value))));
+            } else if let Some(value) = line.strip_prefix(DELETION_DATE_KEY.as_bytes()) {
+                deletion_date = Some(String::from_utf8_lossy(value).into_owned());
+            }
+        }
+        let path = path?;
+        let original_path = match &trash_dir.topdir {
+            Some(topdir) if path.is_relative() => topdir.join(path),
+            _ => path,
+        };
+
+        Some(Self {
+            original_path,
+            deletion_date: deletion_date.unwrap_or_default(),
+            name: info_path.file_stem()?.to_os_string(),
+            trash_dir: trash_dir.to_owned(),
+        })
+    }
+
+    /// Path of the trashed file itself.
+    pub fn path(&self) -> PathBuf {
+        self.trash_dir.files_dir().join(&self.name)
+    }
+}
+
+/// Directory of the home trash, holding its `files` and `info` directories.
+pub fn home_trash_dir() -> PathBuf {
+    HOME_TRASH.to_path_buf()
+}
+
+/// Move `path` to the trash of its file system, recording where it came from.
+pub fn move_to_trash<P: AsRef<Path>>(path: P) -> Result<TrashItem, TrashError> {
+    let path = path.as_ref();
+    let invalid_path = || TrashError::InvalidPath(path.display().to_string());
+    let path = match path.parent() {
+        // Resolve the parent only, a link is trashed and not the file it points to.
+        Some(parent) => parent
+            .canonicalize()?
+            .join(path.file_name().ok_or_else(invalid_path)?),
+        None => return Err(invalid_path()),
+    };
+    let trash_dir = TrashDir::for_path(&path)?;
+    if path.starts_with(&trash_dir.path) {
+        return Err(invalid_path());
+    }
+    let stored_path = match &trash_dir.topdir {
+        Some(topdir) => path.strip_prefix(topdir).unwrap_or(&path),
+        None => path.as_path(),
+    };
+    let deletion_date = local_time_now();
+    let info = format!(
+        "{}\n{}{}\n{}{}\n",
+        TRASH_INFO,
+        PATH_KEY,
+        percent_encode(stored_path.as_os_str().as_bytes()),
+        DELETION_DATE_KEY,
+        deletion_date,
+    );
+
+    // Creating the info file first reserves the name against other programs trashing too.
+    let file_name = path.file_name().ok_or_else(invalid_path)?;
+    let (name, mut info_file) = (1..)
+        .map(|n| unique_name(file_name, n))
+        .find_map(|name| {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(trash_dir.info_path(&name))
+            {
+                Ok(file) => Some(Ok((name, file))),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => None,
+                Err(err) => Some(Err(err)),
+            }
+        })
+        .ok_or_else(invalid_path)??;
+    let item = TrashItem {
+        original_path: path.to_path_buf(),
+        deletion_date,
+        name,
+        trash_dir,
+    };
+
+    let result = info_file
+        .write_all(info.as_bytes())
+        .and_then(|_| fs::rename(&path, item.path()));
+    if let Err(err) = result {
+        let _ = fs::remove_file(item.trash_dir.info_path(&item.name));
+        return Err(err.into());
+    }
+    Ok(item)
+}
+
+/// Put a trashed file back where it was deleted from, never replacing another file.
+pub fn restore(item: &TrashItem) -> Result<(), TrashError> {
+    if fs::symlink_metadata(&item.original_path).is_ok() {
+        return Err(TrashError::AlreadyExists(
+            item.original_path.display().to_string(),
+        ));
+    }
+    if let Some(parent) = item.original_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(item.path(), &item.original_path)?;
+    fs::remove_file(item.trash_dir.info_path(&item.name))?;
+    Ok(())
+}
+
+/// Every file in the trashes of the user.
+pub fn trashed_items() -> Vec<TrashItem> {
+    TrashDir::all().iter().flat_map(TrashDir::items).collect()
+}
+
+pub fn is_empty() -> bool {
+    TrashDir::all().iter().all(|trash_dir| {
+        fs::read_dir(trash_dir.files_dir())
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(true)
+    })
+}
+
+/// Delete every file in the trashes of the user for good.
+pub fn empty_trash() -> Result<(), TrashError> {
+    for trash_dir in TrashDir::all() {
+        for dir in &[trash_dir.files_dir(), trash_dir.info_dir()] {
+            for entry in fs::read_dir(dir).into_iter().flatten() {
+                let path = entry?.path();
+                // Links are removed, never followed.
+                if fs::symlink_metadata(&path)?.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+        match fs::remove_file(trash_dir.path.join(DIRECTORY_SIZES)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Top directory of the mount holding `path`, found where the device changes.
+fn topdir(path: &Path, device: u64) -> Result<PathBuf, TrashError> {
+    let mut topdir = path.parent().unwrap_or(path);
+
+    while let Some(parent) = topdir.parent() {
+        if fs::metadata(parent)?.dev() != device {
+            break;
+        }
+        topdir = parent;
+    }
+    Ok(topdir.to_path_buf())
+}
+
+/// `name` for the first try, then `stem.n.extension`.
+fn unique_name(file_name: &OsStr, n: usize) -> OsString {
+    if n == 1 {
+        return file_name.to_os_string();
+    }
+    let path = Path::new(file_name);
+    let mut name = path.file_stem().unwrap_or(file_name).to_os_string();
+    name.push(format!(".{}", n));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    name
+}
+
+/// Escape the bytes of a path as in a URI, keeping the slashes.
+fn percent_encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                char::from(*b).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Mount points in `/proc/mounts` escape spaces and the like as `\ooo`.
+fn unescape_mount(bytes: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .and_then(|octal| std::str::from_utf8(octal).ok())
+            .and_then(|octal| u8::from_str_radix(octal, 8).ok());
+        match (bytes[i], octal) {
+            (b'\\', Some(b)) => {
+                unescaped.push(b);
+                i += 4;
+            }
+            (b, _) => {
+                unescaped.push(b);
+                i += 1;
+            }
+        }
+    }
+    unescaped
+}
+
+/// The current local time as `YYYY-MM-DDThh:mm:ss`.
+fn local_time_now() -> String {
+    // SAFETY: `localtime_r` only writes to the given `tm`.
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_names_before_their_extension() {
+        let name = |name: &str, n| unique_name(OsStr::new(name), n);
+        assert_eq!(name("notes.txt", 1), "notes.txt");
+        assert_eq!(name("notes.txt", 2), "notes.2.txt");
+        assert_eq!(name("archive.tar.gz", 3), "archive.tar.3.gz");
+        assert_eq!(name("README", 2), "README.2");
+        assert_eq!(name(".bashrc", 2), ".bashrc.2");
+    }
+
+    #[test]
+    fn percent_encodes_paths() {
+        assert_eq!(
+            percent_encode(b"/home/user/a-b_c.d~"),
+            "/home/user/a-b_c.d~"
+        );
+        assert_eq!(
+            percent_encode(b"/tmp/my file%.txt"),
+            "/tmp/my%20file%25.txt"
+        );
+        assert_eq!(
+            percent_encode("/tmp/ឯកសារ".as_bytes()),
+            "/tmp/%E1%9E%AF%E1%9E%80%E1%9E%9F%E1%9E%B6%E1%9E%9A"
+        );
+    }
+
+    #[test]
+    fn percent_decodes_what_it_encodes() {
+        for path in [
+            "/tmp/my file.txt",
+            "/tmp/100% done",
+            "/tmp/ឯកសារ/café",
+            "/tmp/%41",
+        ] {
+            let encoded = percent_encode(path.as_bytes());
+            assert_eq!(percent_decode(encoded.as_bytes()), path.as_bytes());
+        }
+        assert_eq!(percent_decode(b"/tmp/50%"), b"/tmp/50%");
+        assert_eq!(percent_decode(b"/tmp/%zz%4"), b"/tmp/%zz%4");
+        assert_eq!(percent_decode(&[b'/', 0xff]), [b'/', 0xff]);
+    }
+
+    #[test]
+    fn unescapes_mount_points() {
+        assert_eq!(unescape_mount(br"/media/My\040Disk"), b"/media/My Disk");
+        assert_eq!(unescape_mount(br"/mnt/a\011b\134c"), b"/mnt/a\tb\\c");
+        assert_eq!(unescape_mount(br"/mnt/a\04"), br"/mnt/a\04");
+        assert_eq!(unescape_mount(br"/mnt/a\xyz"), br"/mnt/a\xyz");
+    }
+
+    #[test]
+    fn reads_info_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let trash_dir = TrashDir {
+            path: dir.path().join(".Trash-1000"),
+            topdir: Some(dir.path().to_path_buf()),
+        };
+        trash_dir.create().unwrap();
+        let info =
+            "[Trash Info]\r\nPath=docs/my%20caf%C3%A9.txt\r\nDeletionDate=2021-03-04T05:06:07\r\n";
+        fs::write(trash_dir.info_path(OsStr::new("my café.txt")), info).unwrap();
+        fs::write(
+            trash_dir.info_path(OsStr::new("broken")),
+            "Path=/tmp/broken\n",
+        )
+        .unwrap();
+        fs::write(trash_dir.info_dir().join("other.txt"), info).unwrap();
+
+        let items = trash_dir.items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].original_path, dir.path().join("docs/my café.txt"));
+        assert_eq!(items[0].deletion_date, "2021-03-04T05:06:07");
+        assert_eq!(items[0].path(), trash_dir.files_dir().join("my café.txt"));
+    }
+}
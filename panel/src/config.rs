@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+const CONFIG_DIR: &str = "koompi/panel";
+const CONFIG_FILE: &str = "panel.toml";
+const MAX_RECENT: usize = 8;
+
+/// Settings of the panel, kept as TOML in the user's config directory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelConfig {
+    /// Desktop file IDs of the applications pinned to the taskbar, in their order.
+    pub pinned: Vec<String>,
+    /// Desktop file IDs of the favorite applications of the launcher, in their order.
+    pub favorites: Vec<String>,
+    /// Desktop file IDs of the applications launched from the launcher, most recent first.
+    pub recent: Vec<String>,
+}
+
+impl PanelConfig {
+    /// Saved settings, or the default ones when none were saved yet.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = config_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        Ok(fs::write(path, toml::to_string_pretty(self)?)?)
+    }
+
+    pub fn is_pinned(&self, id: &str) -> bool {
+        self.pinned.iter().any(|pinned| pinned == id)
+    }
+
+    /// Pin the application with the desktop file ID `id` after the others, or unpin it.
+    pub fn toggle_pinned(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
+        let pin = !self.is_pinned(id);
+        self.change(|config| set_listed(&mut config.pinned, id, pin))
+    }
+
+    pub fn is_favorite(&self, id: &str) -> bool {
+        self.favorites.iter().any(|favorite| favorite == id)
+    }
+
+    /// Add the application with the desktop file ID `id` to the favorites, or remove it.
+    pub fn toggle_favorite(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
+        let add = !self.is_favorite(id);
+        self.change(|config| set_listed(&mut config.favorites, id, add))
+    }
+
+    /// Put the application with the desktop file ID `id` first among the recent ones.
+    pub fn add_recent(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
+        self.change(|config| {
+            config.recent.retain(|recent| recent != id);
+            config.recent.insert(0, id.to_string());
+            config.recent.truncate(MAX_RECENT);
+        })
+    }
+
+    /// Apply `change` to the saved settings and save them. The taskbar and the launcher each
+    /// hold settings, so those saved by the other one are kept.
+    fn change<F: FnOnce(&mut Self)>(&mut self, change: F) -> Result<(), Box<dyn Error>> {
+        let mut config = Self::load()?;
+        change(&mut config);
+        config.save()?;
+        *self = config;
+        Ok(())
+    }
+}
+
+/// Add `id` after the other IDs unless it is among them, or remove it.
+fn set_listed(ids: &mut Vec<String>, id: &str, is_listed: bool) {
+    if !is_listed {
+        ids.retain(|listed| listed != id);
+    } else if !ids.iter().any(|listed| listed == id) {
+        ids.push(id.to_string());
+    }
+}
+
+fn config_path() -> Result<PathBuf, Box<dyn Error>> {
+    let dir = dirs_next::config_dir().ok_or("no config directory")?;
+    Ok(dir.join(CONFIG_DIR).join(CONFIG_FILE))
+}
@@ -2,18 +2,25 @@ const WINDOW_HEIGHT: u32 = 32;
 const RESERVE_SIZE: [u64; 4] = [0, 0, 32, 0];
 const MENU_WIDTH: u16 = 400;
 const MENU_HEIGHT: u16 = MENU_WIDTH;
+const LAUNCHER_WIDTH: u16 = 560;
+const LAUNCHER_HEIGHT: u16 = 480;
+const WINDOW_LIST_WIDTH: u16 = 240;
+mod config;
 mod proxy_message;
 mod styles;
+mod system_tray;
 mod task_manager;
 mod views;
 use proxy_message::ProxyMessage;
 use views::{
+    app_launcher::{AppLauncher, AppLauncherMsg},
     applets::{Applets, AppletsMsg, ControlType},
     panel::{DesktopPanel, Message},
+    taskbar::{TaskbarMsg, WindowGroup, WindowList, WindowListMsg},
 };
 use winit::{
-    dpi::{PhysicalPosition, PhysicalSize},
-    platform::unix::{WindowBuilderExtUnix, XWindowStrut, XWindowType},
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    platform::unix::{WindowBuilderExtUnix, WindowExtUnix, XWindowStrut, XWindowType},
     window::{Window, WindowBuilder},
 };
 mod window_state;
@@ -26,13 +33,20 @@ use iced_winit::{button, futures, winit, Application, Debug, Executor, Program,
 use window_state::State;
 use winit::{
     event::{
-        DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, StartCause,
-        VirtualKeyCode, WindowEvent,
+        DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseButton,
+        MouseScrollDelta, StartCause, VirtualKeyCode, WindowEvent,
     },
     event_loop::{ControlFlow, EventLoop},
 };
 fn main() {
     std::env::set_var("WINIT_X11_SCALE_FACTOR", "1.25");
+    // Launched applications and desktop entry filtering rely on it.
+    if std::env::var_os("XDG_CURRENT_DESKTOP").is_none() {
+        std::env::set_var(
+            "XDG_CURRENT_DESKTOP",
+            freedesktop::desktop_entry::CURRENT_DESKTOP,
+        );
+    }
 
     let event_loop = EventLoop::with_user_event();
     // uncomment to be able to test task manager.
@@ -56,6 +70,22 @@ fn main() {
         .with_visible(false)
         .build(&event_loop)
         .unwrap();
+    let launcher_window = WindowBuilder::new()
+        .with_x11_window_type(vec![XWindowType::PopupMenu, XWindowType::Menu])
+        .with_decorations(false)
+        .with_always_on_top(true)
+        .with_inner_size(PhysicalSize::new(LAUNCHER_WIDTH, LAUNCHER_HEIGHT))
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
+    let window_list_window = WindowBuilder::new()
+        .with_x11_window_type(vec![XWindowType::PopupMenu, XWindowType::Menu])
+        .with_decorations(false)
+        .with_always_on_top(true)
+        .with_inner_size(LogicalSize::new(WINDOW_LIST_WIDTH, WINDOW_LIST_WIDTH))
+        .with_visible(false)
+        .build(&event_loop)
+        .unwrap();
     let mut popup_x = 0;
     let (mut cursor_position, mut debug, mut modifiers) = (
         PhysicalPosition::new(-1.0, -1.0),
@@ -75,6 +105,26 @@ fn main() {
         &instance,
     ));
 
+    let (launcher, _) = AppLauncher::new(event_loop.create_proxy());
+    let mut launcher_state = block_on(State::new(
+        launcher_window,
+        launcher,
+        Some(&setttings(16)),
+        cursor_position,
+        &mut debug,
+        &instance,
+    ));
+
+    let (window_list, _) = WindowList::new(event_loop.create_proxy());
+    let mut window_list_state = block_on(State::new(
+        window_list_window,
+        window_list,
+        Some(&setttings(16)),
+        cursor_position,
+        &mut debug,
+        &instance,
+    ));
+
     let event_send_proxy = event_loop.create_proxy();
     let (panel, _) = DesktopPanel::new(event_send_proxy);
     let mut control_state = block_on(State::new(
@@ -85,6 +135,22 @@ fn main() {
         &mut debug,
         &instance,
     ));
+    // Third-party tray icons are embedded in the panel's own X11 window.
+    match control_state.window.xlib_window() {
+        Some(xid) => system_tray::spawn(
+            xid as u32,
+            control_state.window.scale_factor(),
+            control_state.win_state.program().background_color,
+            event_loop.create_proxy(),
+        ),
+        None => println!("Error: the system tray needs an X11 window"),
+    }
+    // Messages of the panel's subscriptions come back as user events.
+    let mut runtime = {
+        let executor = iced::executor::Default::new().unwrap();
+        Runtime::new(executor, Proxy::new(event_loop.create_proxy()))
+    };
+    runtime.track(control_state.win_state.program().subscription());
     let event_loop_proxy = event_loop.create_proxy();
     use std::time::Instant;
     let timer_length = std::time::Duration::new(1, 0);
@@ -102,6 +168,19 @@ fn main() {
             }
             Event::DeviceEvent { device_id, event } => match event {
                 DeviceEvent::Button { .. } => {
+                    // Clicks on the panel are left to its KOOMPI button, which toggles it.
+                    if launcher_state.is_visible
+                        && launcher_state.is_cursor_left == Some(true)
+                        && control_state.is_cursor_left == Some(true)
+                    {
+                        show_launcher(&mut launcher_state, false);
+                    }
+                    if window_list_state.is_visible
+                        && window_list_state.is_cursor_left != Some(false)
+                        && control_state.is_cursor_left == Some(true)
+                    {
+                        hide_window_list(&mut window_list_state, &mut control_state);
+                    }
                     let kind = menu_state.win_state.program().kind;
                     if menu_state.is_cursor_left.unwrap() && menu_state.is_visible {
                         match kind {
@@ -136,6 +215,14 @@ fn main() {
             Event::UserEvent(event) => match event {
                 Message::Timer => {
                     control_state.win_state.queue_message(Message::Timer);
+                    // The list of a taskbar group stays open a moment after the cursor left
+                    // both the panel and the list.
+                    if window_list_state.is_visible
+                        && window_list_state.is_cursor_left != Some(false)
+                        && control_state.is_cursor_left != Some(false)
+                    {
+                        hide_window_list(&mut window_list_state, &mut control_state);
+                    }
                     if coutner == 30 {
                         menu_state.win_state.queue_message(AppletsMsg::BatteryTimer);
                         control_state
@@ -154,7 +241,18 @@ fn main() {
                         coutner += 1;
                     }
                 }
-                Message::ShowMenu => {}
+                Message::ShowMenu => {
+                    let is_visible = !launcher_state.is_visible;
+                    show_launcher(&mut launcher_state, is_visible);
+                }
+                Message::HideMenu => show_launcher(&mut launcher_state, false),
+                Message::ShowWindowList(group) => show_window_list(&mut window_list_state, group),
+                message @ Message::Tick(_)
+                | message @ Message::TasksChanged(_)
+                | message @ Message::Taskbar(_)
+                | message @ Message::TrayResized(_) => {
+                    control_state.win_state.queue_message(message)
+                }
                 Message::MonitorShow(is_visible) => {
                     handle_visible_pos(&mut menu_state, ControlType::Monitor, is_visible, popup_x);
                     menu_state.is_visible = is_visible;
@@ -189,7 +287,13 @@ fn main() {
                             state: ElementState::Pressed,
                             virtual_keycode: Some(VirtualKeyCode::Escape),
                             ..
-                        } => *control_flow = ControlFlow::Exit,
+                        } => {
+                            if launcher_state.window.id() == window_id {
+                                show_launcher(&mut launcher_state, false);
+                            } else {
+                                *control_flow = ControlFlow::Exit;
+                            }
+                        }
                         _ => {
                             println!("Is synthetic: {:?}", is_synthetic);
                         }
@@ -199,10 +303,24 @@ fn main() {
                     }
                     WindowEvent::CursorLeft { device_id } => {
                         println!("Cursor left: {:?}", device_id);
+                        if launcher_state.window.id() == window_id {
+                            launcher_state.is_cursor_left = Some(true);
+                        } else if window_list_state.window.id() == window_id {
+                            window_list_state.is_cursor_left = Some(true);
+                        } else if control_state.window.id() == window_id {
+                            control_state.is_cursor_left = Some(true);
+                        }
                         menu_state.is_cursor_left = Some(true);
                     }
                     WindowEvent::CursorEntered { device_id } => {
                         println!("Cursor Enter: {:?}", device_id);
+                        if launcher_state.window.id() == window_id {
+                            launcher_state.is_cursor_left = Some(false);
+                        } else if window_list_state.window.id() == window_id {
+                            window_list_state.is_cursor_left = Some(false);
+                        } else if control_state.window.id() == window_id {
+                            control_state.is_cursor_left = Some(false);
+                        }
                         menu_state.is_cursor_left = Some(false);
                     }
                     WindowEvent::MouseInput {
@@ -215,10 +333,31 @@ fn main() {
                             control_state.resize(*physical_size);
                         } else if menu_state.window.id() == window_id {
                             menu_state.resize(*physical_size);
+                        } else if launcher_state.window.id() == window_id {
+                            launcher_state.resize(*physical_size);
+                        } else if window_list_state.window.id() == window_id {
+                            window_list_state.resize(*physical_size);
                         }
                     }
                     WindowEvent::CursorMoved { position, .. } => {
                         cursor_position = *position;
+                        if control_state.window.id() == window_id {
+                            let x = position.x / control_state.window.scale_factor();
+                            control_state
+                                .win_state
+                                .queue_message(Message::CursorMoved(Some(x as f32)));
+                        }
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        if control_state.window.id() == window_id {
+                            let lines = match delta {
+                                MouseScrollDelta::LineDelta(_, y) => *y,
+                                MouseScrollDelta::PixelDelta(position) => position.y as f32,
+                            };
+                            control_state
+                                .win_state
+                                .queue_message(Message::Scrolled(lines));
+                        }
                     }
                     WindowEvent::ModifiersChanged(modi) => modifiers = *modi,
                     WindowEvent::ScaleFactorChanged {
@@ -229,6 +368,10 @@ fn main() {
                             control_state.resize(**new_inner_size);
                         } else if menu_state.window.id() == window_id {
                             menu_state.resize(**new_inner_size);
+                        } else if launcher_state.window.id() == window_id {
+                            launcher_state.resize(**new_inner_size);
+                        } else if window_list_state.window.id() == window_id {
+                            window_list_state.resize(**new_inner_size);
                         }
                     }
                     _ => {}
@@ -237,6 +380,10 @@ fn main() {
                     control_state.map_event(&modifiers, &event);
                 } else if window_id == menu_state.window.id() {
                     menu_state.map_event(&modifiers, &event);
+                } else if window_id == launcher_state.window.id() {
+                    launcher_state.map_event(&modifiers, &event);
+                } else if window_id == window_list_state.window.id() {
+                    window_list_state.map_event(&modifiers, &event);
                 } else {
                     {}
                 }
@@ -244,12 +391,18 @@ fn main() {
             Event::MainEventsCleared => {
                 control_state.update_frame(cursor_position, &mut debug);
                 menu_state.update_frame(cursor_position, &mut debug);
+                launcher_state.update_frame(cursor_position, &mut debug);
+                window_list_state.update_frame(cursor_position, &mut debug);
             }
             Event::RedrawRequested(window_id) => {
                 if control_state.window.id() == window_id {
                     control_state.redraw(&debug);
                 } else if menu_state.window.id() == window_id {
                     menu_state.redraw(&debug);
+                } else if launcher_state.window.id() == window_id {
+                    launcher_state.redraw(&debug);
+                } else if window_list_state.window.id() == window_id {
+                    window_list_state.redraw(&debug);
                 } else {
                 }
             }
@@ -287,6 +440,55 @@ pub fn handle_visible_pos(win: &mut State<Applets>, kind: ControlType, is_visibl
         .set_outer_position(PhysicalPosition::new(pos, 32));
 }
 
+/// Open the application launcher below the KOOMPI button, or close it.
+pub fn show_launcher(win: &mut State<AppLauncher>, is_visible: bool) {
+    if is_visible {
+        // Applications installed since it was last open get listed.
+        win.win_state.queue_message(AppLauncherMsg::Refresh);
+        win.window
+            .set_outer_position(PhysicalPosition::new(0, WINDOW_HEIGHT));
+        win.window.set_visible(true);
+        win.window.set_always_on_top(true);
+        // Until the cursor enters it, a click anywhere but on the panel closes it.
+        win.is_cursor_left = Some(true);
+    } else {
+        win.window.set_visible(false);
+    }
+    win.is_visible = is_visible;
+}
+
+/// Open the list of a taskbar group's windows below its button, or close it.
+pub fn show_window_list(win: &mut State<WindowList>, group: Option<WindowGroup>) {
+    match group {
+        Some(group) => {
+            let x = f64::from(group.x) * win.window.scale_factor();
+            win.window
+                .set_inner_size(LogicalSize::new(WINDOW_LIST_WIDTH, group.height()));
+            win.window
+                .set_outer_position(PhysicalPosition::new(x as u32, WINDOW_HEIGHT));
+            win.win_state.queue_message(WindowListMsg::Show(group));
+            if !win.is_visible {
+                win.window.set_visible(true);
+                win.window.set_always_on_top(true);
+                win.is_cursor_left = Some(true);
+            }
+            win.is_visible = true;
+        }
+        None => {
+            win.window.set_visible(false);
+            win.is_visible = false;
+        }
+    }
+}
+
+/// Close the list of a taskbar group's windows, until another group is hovered.
+pub fn hide_window_list(win: &mut State<WindowList>, panel: &mut State<DesktopPanel>) {
+    show_window_list(win, None);
+    panel
+        .win_state
+        .queue_message(Message::Taskbar(TaskbarMsg::CursorMoved(None)));
+}
+
 // async fn run_instance<E>(
 //     mut desktop_state: State<DesktopPanel>,
 //     mut context_menu_state: State<Applets>,
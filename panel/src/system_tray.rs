@@ -0,0 +1,52 @@
+pub mod tray_manager;
+
+use crate::views::panel::Message;
+use iced_winit::winit::event_loop::EventLoopProxy;
+use iced_winit::Color;
+use std::error::Error;
+use std::thread;
+use tray_manager::TrayManager;
+use x11rb::protocol::xproto::Window;
+
+/// Size of the tray icons in pixels.
+const ICON_SIZE: u16 = 24;
+
+/// Host the system tray at the right edge of the panel window `parent`, over its `background`
+/// color, telling the panel the width it takes whenever it changes.
+pub fn spawn(parent: Window, scale_factor: f64, background: Color, proxy: EventLoopProxy<Message>) {
+    // X11 events are waited for in a blocking way, away from the event loop.
+    thread::spawn(move || {
+        if let Err(e) = run(parent, scale_factor, background, &proxy) {
+            println!("Error: {:?}", e);
+        }
+    });
+}
+
+fn run(
+    parent: Window,
+    scale_factor: f64,
+    background: Color,
+    proxy: &EventLoopProxy<Message>,
+) -> Result<(), Box<dyn Error>> {
+    let channel = |value: f32| (value * f32::from(u16::MAX)).round() as u16;
+    let background = (
+        channel(background.r),
+        channel(background.g),
+        channel(background.b),
+    );
+    let mut tray = TrayManager::new(parent, ICON_SIZE, background)?;
+    let mut last_width = None;
+
+    loop {
+        // The panel is laid out in logical pixels.
+        let width = (f64::from(tray.width()) / scale_factor).ceil() as u16;
+        if last_width != Some(width) {
+            // The tray is over once the panel exits.
+            if proxy.send_event(Message::TrayResized(width)).is_err() {
+                return Ok(());
+            }
+            last_width = Some(width);
+        }
+        tray.wait_for_change()?;
+    }
+}
@@ -0,0 +1,577 @@
+use std::error::Error;
+use x11rb::atom_manager;
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConfigureWindowAux,
+    ConnectionExt, CreateWindowAux, EventMask, PropMode, SetMode, Window, WindowClass,
+    CLIENT_MESSAGE_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::xcb_ffi::XCBConnection;
+use x11rb::{COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+/// `_NET_SYSTEM_TRAY_OPCODE` message asking for an icon to be docked.
+const SYSTEM_TRAY_REQUEST_DOCK: u32 = 0;
+/// XEmbed message telling a client it was embedded.
+const XEMBED_EMBEDDED_NOTIFY: u32 = 0;
+/// XEmbed protocol version, the first one, which later ones are compatible with.
+const XEMBED_VERSION: u32 = 0;
+/// `_XEMBED_INFO` flag of clients asking to be shown.
+const XEMBED_MAPPED: u32 = 1;
+
+atom_manager! {
+    pub Atoms: AtomsCookie {
+        MANAGER,
+        _NET_SYSTEM_TRAY_OPCODE,
+        _NET_SYSTEM_TRAY_ORIENTATION,
+        _XEMBED,
+        _XEMBED_INFO,
+    }
+}
+
+/// Direction in which the icons are lined up, as in `_NET_SYSTEM_TRAY_ORIENTATION`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Orientation {
+    Horizontal = 0,
+    Vertical = 1,
+}
+
+#[derive(Debug)]
+struct TrayIcon {
+    window: Window,
+    is_mapped: bool,
+}
+
+/// Host of the system tray following the freedesktop.org System Tray and XEmbed protocols.
+/// It owns the `_NET_SYSTEM_TRAY_S{n}` selection of the screen and embeds the icon windows
+/// of the clients asking for it in a window of its own, at the right edge of `parent`.
+#[derive(Debug)]
+pub struct TrayManager {
+    conn: XCBConnection,
+    atoms: Atoms,
+    selection: Atom,
+    parent: Window,
+    parent_size: (u16, u16),
+    // Window owning the selection and holding the icons.
+    container: Window,
+    icon_size: u16,
+    orientation: Orientation,
+    icons: Vec<TrayIcon>,
+}
+
+impl TrayManager {
+    /// Take the system tray selection, unless another system tray holds it. The icons are shown
+    /// over `background`, the color of the parent window in 16-bit red, green and blue.
+    pub fn new(
+        parent: Window,
+        icon_size: u16,
+        background: (u16, u16, u16),
+    ) -> Result<Self, Box<dyn Error>> {
+        let (conn, screen) = XCBConnection::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        let colormap = conn.setup().roots[screen].default_colormap;
+        let (red, green, blue) = background;
+        let background_pixel = conn.alloc_color(colormap, red, green, blue)?.reply()?.pixel;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        let selection = conn
+            .intern_atom(false, format!("_NET_SYSTEM_TRAY_S{}", screen).as_bytes())?
+            .reply()?
+            .atom;
+        if conn.get_selection_owner(selection)?.reply()?.owner != NONE {
+            return Err("another system tray is running".into());
+        }
+
+        let geometry = conn.get_geometry(parent)?.reply()?;
+        conn.change_window_attributes(
+            parent,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY),
+        )?;
+        let container = conn.generate_id()?;
+        // Icons asking to be moved or resized are kept in their place.
+        conn.create_window(
+            COPY_FROM_PARENT as u8,
+            container,
+            parent,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new()
+                .background_pixel(background_pixel)
+                .event_mask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT),
+        )?;
+        conn.set_selection_owner(container, selection, CURRENT_TIME)?;
+        if conn.get_selection_owner(selection)?.reply()?.owner != container {
+            return Err("cannot own the system tray selection".into());
+        }
+
+        let tray = Self {
+            conn,
+            atoms,
+            selection,
+            parent,
+            parent_size: (geometry.width, geometry.height),
+            container,
+            icon_size,
+            orientation: Orientation::Horizontal,
+            icons: Vec::new(),
+        };
+        tray.set_orientation_property()?;
+        // Clients waiting for a system tray learn about it.
+        tray.send_message(
+            root,
+            root,
+            atoms.MANAGER,
+            [CURRENT_TIME, selection, container, 0, 0],
+            EventMask::STRUCTURE_NOTIFY,
+        )?;
+        tray.layout()?;
+        Ok(tray)
+    }
+
+    /// Window holding the icons.
+    pub fn container(&self) -> Window {
+        self.container
+    }
+
+    /// Icon windows shown in the tray, in their order.
+    pub fn icons(&self) -> Vec<Window> {
+        self.icons
+            .iter()
+            .filter(|icon| icon.is_mapped)
+            .map(|icon| icon.window)
+            .collect()
+    }
+
+    /// Width the tray takes at the right edge of the parent window.
+    pub fn width(&self) -> u16 {
+        let count = self.icons().len() as u16;
+        match self.orientation {
+            _ if count == 0 => 0,
+            Orientation::Horizontal => count * self.icon_size,
+            Orientation::Vertical => self.icon_size,
+        }
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) -> Result<(), Box<dyn Error>> {
+        self.orientation = orientation;
+        self.set_orientation_property()?;
+        self.layout()
+    }
+
+    /// Block until the icons shown change.
+    pub fn wait_for_change(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut event = Some(self.conn.wait_for_event()?);
+        let mut has_changed = false;
+        // Changes often come together, they are all handled at once.
+        while let Some(current) = event {
+            has_changed |= self.handle_event(current)?;
+            event = if has_changed {
+                self.conn.poll_for_event()?
+            } else {
+                Some(self.conn.wait_for_event()?)
+            };
+        }
+        self.layout()
+    }
+
+    /// Whether `event` changes the icons or their place.
+    fn handle_event(&mut self, event: Event) -> Result<bool, Box<dyn Error>> {
+        match event {
+            Event::ClientMessage(event)
+                if event.window == self.container
+                    && event.type_ == self.atoms._NET_SYSTEM_TRAY_OPCODE =>
+            {
+                let data = event.data.as_data32();
+                if data[1] == SYSTEM_TRAY_REQUEST_DOCK {
+                    self.dock(data[2])?;
+                    return Ok(true);
+                }
+                // Balloon messages are not shown.
+                Ok(false)
+            }
+            Event::SelectionClear(event) if event.selection == self.selection => {
+                Err("another system tray took over".into())
+            }
+            Event::ConfigureNotify(event) if event.window == self.parent => {
+                let size = (event.width, event.height);
+                Ok(std::mem::replace(&mut self.parent_size, size) != size)
+            }
+            // Icons are given the size of the tray's slots whatever they ask for.
+            Event::ConfigureRequest(event) => Ok(self.is_icon(event.window)),
+            Event::MapRequest(event) => {
+                self.set_mapped(event.window, true);
+                Ok(self.is_icon(event.window))
+            }
+            // Icons hide by unmapping themselves once in the tray. The unmapping of those
+            // being reparented into it happens before.
+            Event::UnmapNotify(event) if event.event == self.container => {
+                Ok(self.set_mapped(event.window, false))
+            }
+            Event::PropertyNotify(event) if event.atom == self.atoms._XEMBED_INFO => {
+                let is_mapped = self.is_xembed_mapped(event.window);
+                Ok(self.set_mapped(event.window, is_mapped))
+            }
+            Event::DestroyNotify(event) => Ok(self.remove(event.window)),
+            Event::ReparentNotify(event) if event.parent != self.container => {
+                Ok(self.remove(event.window))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn dock(&mut self, window: Window) -> Result<(), Box<dyn Error>> {
+        if self.is_icon(window) {
+            return Ok(());
+        }
+        // Selected first so that no change is missed. A window destroyed before is not docked,
+        // one destroyed after is removed on its `DestroyNotify`.
+        let selected = self.conn.change_window_attributes(
+            window,
+            &ChangeWindowAttributesAux::new()
+                .event_mask(EventMask::STRUCTURE_NOTIFY | EventMask::PROPERTY_CHANGE),
+        )?;
+        match selected.check() {
+            Err(ReplyError::X11Error(_)) => return Ok(()),
+            result => result?,
+        }
+        let is_mapped = self.is_xembed_mapped(window);
+        // The icon outlives the panel if it exits.
+        self.conn.change_save_set(SetMode::INSERT, window)?;
+        self.conn.reparent_window(window, self.container, 0, 0)?;
+        self.send_message(
+            window,
+            window,
+            self.atoms._XEMBED,
+            [
+                CURRENT_TIME,
+                XEMBED_EMBEDDED_NOTIFY,
+                0,
+                self.container,
+                XEMBED_VERSION,
+            ],
+            EventMask::NO_EVENT,
+        )?;
+        self.icons.push(TrayIcon { window, is_mapped });
+        Ok(())
+    }
+
+    fn is_icon(&self, window: Window) -> bool {
+        self.icons.iter().any(|icon| icon.window == window)
+    }
+
+    /// Whether the icon was shown or hidden.
+    fn set_mapped(&mut self, window: Window, is_mapped: bool) -> bool {
+        match self.icons.iter_mut().find(|icon| icon.window == window) {
+            Some(icon) if icon.is_mapped != is_mapped => {
+                icon.is_mapped = is_mapped;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the window was an icon of the tray.
+    fn remove(&mut self, window: Window) -> bool {
+        let count = self.icons.len();
+        self.icons.retain(|icon| icon.window != window);
+        self.icons.len() != count
+    }
+
+    /// Whether the client asks to be shown, as clients without `_XEMBED_INFO` do.
+    fn is_xembed_mapped(&self, window: Window) -> bool {
+        let info: Vec<u32> = self
+            .conn
+            .get_property(
+                false,
+                window,
+                self.atoms._XEMBED_INFO,
+                self.atoms._XEMBED_INFO,
+                0,
+                2,
+            )
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| Some(reply.value32()?.collect()))
+            .unwrap_or_default();
+        match info.as_slice() {
+            [_, flags] => flags & XEMBED_MAPPED != 0,
+            _ => true,
+        }
+    }
+
+    /// Line up the shown icons and fit the container around them.
+    fn layout(&self) -> Result<(), Box<dyn Error>> {
+        let size = self.icon_size;
+        let mut pos = 0;
+        for icon in &self.icons {
+            if !icon.is_mapped {
+                self.conn.unmap_window(icon.window)?;
+                continue;
+            }
+            let (x, y) = match self.orientation {
+                Orientation::Horizontal => (pos, 0),
+                Orientation::Vertical => (0, pos),
+            };
+            self.conn.configure_window(
+                icon.window,
+                &ConfigureWindowAux::new()
+                    .x(i32::from(x))
+                    .y(i32::from(y))
+                    .width(u32::from(size))
+                    .height(u32::from(size)),
+            )?;
+            self.conn.map_window(icon.window)?;
+            pos += size;
+        }
+
+        let (parent_width, parent_height) = self.parent_size;
+        let (width, height) = match self.orientation {
+            Orientation::Horizontal => (pos, size),
+            Orientation::Vertical => (size, pos),
+        };
+        if pos == 0 {
+            self.conn.unmap_window(self.container)?;
+        } else {
+            self.conn.configure_window(
+                self.container,
+                &ConfigureWindowAux::new()
+                    .x(i32::from(parent_width) - i32::from(width))
+                    .y((i32::from(parent_height) - i32::from(height)).max(0) / 2)
+                    .width(u32::from(width))
+                    .height(u32::from(height)),
+            )?;
+            self.conn.map_window(self.container)?;
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn set_orientation_property(&self) -> Result<(), Box<dyn Error>> {
+        self.conn.change_property32(
+            PropMode::REPLACE,
+            self.container,
+            self.atoms._NET_SYSTEM_TRAY_ORIENTATION,
+            AtomEnum::CARDINAL,
+            &[self.orientation as u32],
+        )?;
+        Ok(())
+    }
+
+    fn send_message(
+        &self,
+        destination: Window,
+        window: Window,
+        type_: Atom,
+        data: [u32; 5],
+        event_mask: EventMask,
+    ) -> Result<(), Box<dyn Error>> {
+        let event = ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            format: 32,
+            sequence: 0,
+            window,
+            type_,
+            data: data.into(),
+        };
+        self.conn
+            .send_event(false, destination, event_mask, event)?;
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+/// These tests need an X server and are ignored otherwise, run them with
+/// `xvfb-run cargo test -- --ignored`. A dummy tray client docks its icon the way applications do.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::GetGeometryReply;
+
+    const ICON_SIZE: u16 = 24;
+    const PARENT_WIDTH: u16 = 400;
+    const BACKGROUND: (u16, u16, u16) = (0xffff, 0xffff, 0xffff);
+
+    struct TrayClient {
+        conn: XCBConnection,
+        root: Window,
+        atoms: Atoms,
+    }
+
+    impl TrayClient {
+        fn connect() -> Self {
+            let (conn, screen) = XCBConnection::connect(None).unwrap();
+            let root = conn.setup().roots[screen].root;
+            let atoms = Atoms::new(&conn).unwrap().reply().unwrap();
+            Self { conn, root, atoms }
+        }
+
+        fn create_window(&self, width: u16, height: u16) -> Window {
+            let window = self.conn.generate_id().unwrap();
+            self.conn
+                .create_window(
+                    COPY_FROM_PARENT as u8,
+                    window,
+                    self.root,
+                    0,
+                    0,
+                    width,
+                    height,
+                    0,
+                    WindowClass::INPUT_OUTPUT,
+                    COPY_FROM_PARENT,
+                    &CreateWindowAux::new(),
+                )
+                .unwrap();
+            self.conn.flush().unwrap();
+            window
+        }
+
+        fn set_xembed_flags(&self, icon: Window, flags: u32) {
+            self.conn
+                .change_property32(
+                    PropMode::REPLACE,
+                    icon,
+                    self.atoms._XEMBED_INFO,
+                    self.atoms._XEMBED_INFO,
+                    &[XEMBED_VERSION, flags],
+                )
+                .unwrap();
+            self.conn.flush().unwrap();
+        }
+
+        /// Ask the owner of `selection` to dock `icon`.
+        fn dock(&self, selection: Atom, icon: Window) {
+            let tray = self
+                .conn
+                .get_selection_owner(selection)
+                .unwrap()
+                .reply()
+                .unwrap()
+                .owner;
+            let event = ClientMessageEvent {
+                response_type: CLIENT_MESSAGE_EVENT,
+                format: 32,
+                sequence: 0,
+                window: tray,
+                type_: self.atoms._NET_SYSTEM_TRAY_OPCODE,
+                data: [CURRENT_TIME, SYSTEM_TRAY_REQUEST_DOCK, icon, 0, 0].into(),
+            };
+            self.conn
+                .send_event(false, tray, EventMask::NO_EVENT, event)
+                .unwrap();
+            self.conn.flush().unwrap();
+        }
+
+        fn geometry(&self, window: Window) -> GetGeometryReply {
+            self.conn.get_geometry(window).unwrap().reply().unwrap()
+        }
+    }
+
+    /// Wait for the X server to handle the requests of the tray.
+    fn sync(tray: &TrayManager) {
+        tray.conn.get_input_focus().unwrap().reply().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn docks_and_lays_out_icons() {
+        let client = TrayClient::connect();
+        // The client also plays the panel.
+        let panel = client.create_window(PARENT_WIDTH, 32);
+        let mut tray = TrayManager::new(panel, ICON_SIZE, BACKGROUND).unwrap();
+        let container = tray.container();
+        assert!(TrayManager::new(panel, ICON_SIZE, BACKGROUND).is_err());
+        let orientation = client
+            .conn
+            .get_property(
+                false,
+                container,
+                client.atoms._NET_SYSTEM_TRAY_ORIENTATION,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )
+            .unwrap()
+            .reply()
+            .unwrap();
+        assert_eq!(orientation.value32().unwrap().next(), Some(0));
+
+        let icon = client.create_window(16, 16);
+        client.set_xembed_flags(icon, XEMBED_MAPPED);
+        client.dock(tray.selection, icon);
+        tray.wait_for_change().unwrap();
+        sync(&tray);
+        assert_eq!(tray.icons(), vec![icon]);
+        assert_eq!(tray.width(), ICON_SIZE);
+        let tree = client.conn.query_tree(icon).unwrap().reply().unwrap();
+        assert_eq!(tree.parent, container);
+        let geometry = client.geometry(icon);
+        assert_eq!((geometry.width, geometry.height), (ICON_SIZE, ICON_SIZE));
+        let geometry = client.geometry(container);
+        assert_eq!(
+            (geometry.x, geometry.width),
+            ((PARENT_WIDTH - ICON_SIZE) as i16, ICON_SIZE)
+        );
+        // The client is told it was embedded.
+        let embedded = loop {
+            if let Event::ClientMessage(event) = client.conn.wait_for_event().unwrap() {
+                break event;
+            }
+        };
+        assert_eq!(embedded.type_, client.atoms._XEMBED);
+        assert_eq!(
+            &embedded.data.as_data32()[1..4],
+            &[XEMBED_EMBEDDED_NOTIFY, 0, container]
+        );
+
+        // Icons keep the size of the slots.
+        client
+            .conn
+            .configure_window(icon, &ConfigureWindowAux::new().width(48))
+            .unwrap();
+        client.conn.flush().unwrap();
+        tray.wait_for_change().unwrap();
+        sync(&tray);
+        assert_eq!(client.geometry(icon).width, ICON_SIZE);
+
+        let other_icon = client.create_window(16, 16);
+        client.dock(tray.selection, other_icon);
+        tray.wait_for_change().unwrap();
+        sync(&tray);
+        assert_eq!(tray.icons(), vec![icon, other_icon]);
+        assert_eq!(client.geometry(other_icon).x, ICON_SIZE as i16);
+        assert_eq!(client.geometry(container).width, 2 * ICON_SIZE);
+
+        tray.set_orientation(Orientation::Vertical).unwrap();
+        sync(&tray);
+        assert_eq!(client.geometry(other_icon).y, ICON_SIZE as i16);
+        assert_eq!(tray.width(), ICON_SIZE);
+        tray.set_orientation(Orientation::Horizontal).unwrap();
+
+        client.set_xembed_flags(icon, 0);
+        tray.wait_for_change().unwrap();
+        sync(&tray);
+        assert_eq!(tray.icons(), vec![other_icon]);
+        assert_eq!(client.geometry(other_icon).x, 0);
+
+        client.conn.destroy_window(other_icon).unwrap();
+        client.conn.flush().unwrap();
+        tray.wait_for_change().unwrap();
+        sync(&tray);
+        assert!(tray.icons().is_empty());
+        assert_eq!(tray.width(), 0);
+
+        // Icons destroyed before they are docked are left out.
+        let gone_icon = client.create_window(16, 16);
+        client.conn.destroy_window(gone_icon).unwrap();
+        client.dock(tray.selection, gone_icon);
+        tray.wait_for_change().unwrap();
+        assert!(tray.icons.is_empty());
+    }
+}
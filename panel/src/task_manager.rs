@@ -1 +1,5 @@
+pub mod app_matcher;
+mod task_watcher;
 pub mod taskmanager;
+
+pub use task_watcher::tasks;
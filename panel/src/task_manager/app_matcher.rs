@@ -0,0 +1,58 @@
+const DESKTOP_SUFFIX: &str = ".desktop";
+
+/// Index of the application a window belongs to, among applications given by their desktop
+/// file ID and `StartupWMClass=` value, from the instance and class names of the window's
+/// `WM_CLASS`. Applications declaring the window's class are preferred to those merely named
+/// after it, e.g. `firefox.desktop` or `org.gnome.Nautilus.desktop` for the `Nautilus` class.
+pub fn find_app<'a, I>(apps: I, instance: &str, class: &str) -> Option<usize>
+where
+    I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    I::IntoIter: Clone,
+{
+    let mut apps = apps.into_iter();
+    let is_window = |name: &str| {
+        !name.is_empty()
+            && (name.eq_ignore_ascii_case(class) || name.eq_ignore_ascii_case(instance))
+    };
+
+    apps.clone()
+        .position(|(_, startup_wm_class)| startup_wm_class.map(is_window).unwrap_or(false))
+        .or_else(|| {
+            apps.position(|(id, startup_wm_class)| {
+                let name = id.strip_suffix(DESKTOP_SUFFIX).unwrap_or(id);
+                startup_wm_class.is_none()
+                    && (is_window(name) || name.rsplit('.').next().map(is_window).unwrap_or(false))
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APPS: [(&str, Option<&str>); 4] = [
+        ("org.gnome.Nautilus.desktop", None),
+        ("firefox.desktop", None),
+        ("code.desktop", Some("Code")),
+        ("code-insiders.desktop", Some("Code - Insiders")),
+    ];
+
+    fn find(instance: &str, class: &str) -> Option<usize> {
+        find_app(APPS.iter().copied(), instance, class)
+    }
+
+    #[test]
+    fn prefers_the_declared_class() {
+        assert_eq!(find("code", "Code"), Some(2));
+        assert_eq!(find("code - insiders", "Code - Insiders"), Some(3));
+    }
+
+    #[test]
+    fn falls_back_to_the_desktop_file_id() {
+        assert_eq!(find("Navigator", "firefox"), Some(1));
+        assert_eq!(find("org.gnome.Nautilus", "Org.gnome.Nautilus"), Some(0));
+        assert_eq!(find("nautilus", "Nautilus"), Some(0));
+        assert_eq!(find("xterm", "XTerm"), None);
+        assert_eq!(find("", ""), None);
+    }
+}
@@ -0,0 +1,53 @@
+use super::taskmanager::{TaskManager, Tasks};
+use iced_winit::futures::{
+    channel::mpsc::{self, UnboundedSender},
+    stream::BoxStream,
+    StreamExt,
+};
+use iced_winit::{subscription::Recipe, Subscription};
+use std::error::Error;
+use std::hash::{Hash, Hasher};
+use std::thread;
+
+/// Open windows of the taskbar, sent again whenever one of them changes.
+pub fn tasks() -> Subscription<Tasks> {
+    Subscription::from_recipe(TaskWatcher)
+}
+
+struct TaskWatcher;
+
+impl<H: Hasher, I> Recipe<H, I> for TaskWatcher {
+    type Output = Tasks;
+
+    fn hash(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, I>) -> BoxStream<'static, Tasks> {
+        let (sender, receiver) = mpsc::unbounded();
+        // X11 events are waited for in a blocking way, away from the executor.
+        thread::spawn(move || {
+            if let Err(e) = watch(&sender) {
+                println!("Error: {:?}", e);
+            }
+        });
+        receiver.boxed()
+    }
+}
+
+fn watch(sender: &UnboundedSender<Tasks>) -> Result<(), Box<dyn Error>> {
+    let mut task_manager = TaskManager::new()?;
+    let mut last_tasks = None;
+
+    loop {
+        let tasks = task_manager.tasks()?;
+        if last_tasks.as_ref() != Some(&tasks) {
+            // The subscription is over once nobody listens.
+            if sender.unbounded_send(tasks.to_owned()).is_err() {
+                return Ok(());
+            }
+            last_tasks = Some(tasks);
+        }
+        task_manager.wait_for_change()?;
+    }
+}
@@ -1,108 +1,659 @@
-use std::any::Any;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::error::Error;
+use x11rb::atom_manager;
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt, GetPropertyReply, Window};
-use x11rb::x11_utils::TryParse;
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConnectionExt, EventMask,
+    GetPropertyReply, Window, CLIENT_MESSAGE_EVENT,
+};
+use x11rb::protocol::Event;
 use x11rb::xcb_ffi::XCBConnection;
+use x11rb::CURRENT_TIME;
 
-pub struct TaskManager {
-    window_id: Window,
-    window_class: Option<String>,
-    window_instance: Option<String>,
-    window_name: Option<String>,
+/// Size of the `_NET_WM_ICON` image picked when a window has several.
+const ICON_SIZE: u32 = 24;
+/// ICCCM `WM_CHANGE_STATE` value asking for a window to be iconified.
+const ICONIC_STATE: u32 = 3;
+/// `_NET_ACTIVE_WINDOW` source indication of pagers and taskbars.
+const SOURCE_PAGER: u32 = 2;
+/// `_NET_WM_DESKTOP` value of windows shown on all workspaces.
+const ALL_WORKSPACES: u32 = 0xFFFF_FFFF;
+
+atom_manager! {
+    pub Atoms: AtomsCookie {
+        _NET_CLIENT_LIST,
+        _NET_ACTIVE_WINDOW,
+        _NET_NUMBER_OF_DESKTOPS,
+        _NET_CURRENT_DESKTOP,
+        _NET_DESKTOP_NAMES,
+        _NET_WM_NAME,
+        _NET_WM_DESKTOP,
+        _NET_WM_ICON,
+        _NET_WM_STATE,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_SKIP_TASKBAR,
+        _NET_WM_STATE_DEMANDS_ATTENTION,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_NORMAL,
+        _NET_WM_WINDOW_TYPE_DIALOG,
+        WM_CHANGE_STATE,
+        UTF8_STRING,
+    }
 }
-fn find_active_window(
-    conn: &impl Connection,
+
+/// Windows the window manager lists in `_NET_CLIENT_LIST`, kept up to date by following
+/// the changes of the root window and client window properties.
+#[derive(Debug)]
+pub struct TaskManager {
+    conn: XCBConnection,
     root: Window,
-    net_active_window: Atom,
-    hash_map: &mut HashMap<&str, Option<Window>>,
-) -> Result<(Window, bool), Box<dyn Error>> {
-    let window: Window = AtomEnum::ANY.into();
-    let active_window = conn
-        .get_property(false, root, net_active_window, window, 0, 1)?
-        .reply()?;
-    if active_window.format == 32 && active_window.length == 1 {
-        // Things will be so much easier with the next release:
-        let widnow_id = u32::try_parse(&active_window.value)?.0;
-        let focus_changed = widnow_id != hash_map["xid"].unwrap();
-        hash_map.insert("xid", Some(widnow_id));
-        Ok((u32::try_parse(&active_window.value)?.0, focus_changed))
-    } else {
-        // Query the input focus
-        Ok((conn.get_input_focus()?.reply()?.focus, false))
-    }
+    atoms: Atoms,
+    // Client windows whose property changes are selected.
+    watched: HashSet<Window>,
+}
+
+/// Open windows shown in the taskbar, in the order the window manager lists them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tasks {
+    pub windows: Vec<TaskWindow>,
+    pub active: Option<Window>,
+    pub workspaces: Workspaces,
+}
+
+/// Virtual desktops of the window manager.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Workspaces {
+    /// Name of each workspace, empty when the window manager names none.
+    pub names: Vec<String>,
+    pub current: u32,
 }
-fn parse_string_property(property: &GetPropertyReply) -> &str {
-    std::str::from_utf8(&property.value).unwrap_or("Invalid utf8")
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskWindow {
+    pub id: Window,
+    pub name: String,
+    pub instance: String,
+    pub class: String,
+    pub icon: Option<WindowIcon>,
+    pub is_minimized: bool,
+    pub demands_attention: bool,
+    /// Workspace of the window, none when it is shown on all of them.
+    pub desktop: Option<u32>,
 }
-fn parse_wm_class(property: &GetPropertyReply) -> (&str, &str) {
+
+/// Image of `_NET_WM_ICON` as BGRA pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowIcon {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+fn parse_string_property(property: &GetPropertyReply) -> String {
+    String::from_utf8_lossy(&property.value).into_owned()
+}
+
+/// Strings of a property holding a list of null-terminated strings.
+fn parse_string_list(property: &GetPropertyReply) -> Vec<String> {
+    let mut strings: Vec<String> = property
+        .value
+        .split(|&b| b == 0)
+        .map(|string| String::from_utf8_lossy(string).into_owned())
+        .collect();
+    // The last string is terminated too.
+    if strings.last().map(String::is_empty) == Some(true) {
+        strings.pop();
+    }
+    strings
+}
+
+/// Instance and class names of a `WM_CLASS` property.
+pub fn parse_wm_class(property: &GetPropertyReply) -> Option<(&str, &str)> {
     if property.format != 8 {
-        return (
-            "Malformed property: wrong format",
-            "Malformed property: wrong format",
-        );
+        return None;
     }
     let value = &property.value;
     // The property should contain two null-terminated strings. Find them.
-    if let Some(middle) = value.iter().position(|&b| b == 0) {
-        let (instance, class) = value.split_at(middle);
-        // Skip the null byte at the beginning
-        let mut class = &class[1..];
-        // Remove the last null byte from the class, if it is there.
-        if class.last() == Some(&0) {
-            class = &class[..class.len() - 1];
-        }
-        let instance = std::str::from_utf8(instance);
-        let class = std::str::from_utf8(class);
-        (
-            instance.unwrap_or("Invalid utf8"),
-            class.unwrap_or("Invalid utf8"),
-        )
-    } else {
-        ("Missing null byte", "Missing null byte")
+    let middle = value.iter().position(|&b| b == 0)?;
+    let (instance, class) = value.split_at(middle);
+    // Skip the null byte at the beginning
+    let mut class = &class[1..];
+    // Remove the last null byte from the class, if it is there.
+    if class.last() == Some(&0) {
+        class = &class[..class.len() - 1];
+    }
+    Some((
+        std::str::from_utf8(instance).ok()?,
+        std::str::from_utf8(class).ok()?,
+    ))
+}
+
+/// The `_NET_WM_ICON` image closest to `ICON_SIZE`, the smallest one at least as large
+/// when there is one. The property holds the width, height and ARGB pixels of each image.
+fn parse_icon(property: &GetPropertyReply) -> Option<WindowIcon> {
+    let data: Vec<u32> = property.value32()?.collect();
+    let mut images = Vec::new();
+    let mut pos = 0;
+    while let (Some(&width), Some(&height)) = (data.get(pos), data.get(pos + 1)) {
+        let len = (width as usize).checked_mul(height as usize)?;
+        let pixels = data.get(pos + 2..pos + 2 + len)?;
+        images.push((width, height, pixels));
+        pos += 2 + len;
     }
+    let (width, height, pixels) = images
+        .iter()
+        .filter(|(width, _, _)| *width >= ICON_SIZE)
+        .min_by_key(|(width, _, _)| *width)
+        .or_else(|| images.iter().max_by_key(|(width, _, _)| *width))?;
+
+    Some(WindowIcon {
+        width: *width,
+        height: *height,
+        // Native-endian ARGB words are BGRA bytes on little-endian machines.
+        pixels: pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_le_bytes())
+            .collect(),
+    })
 }
+
 impl TaskManager {
-    pub fn new() -> Result<(), Box<dyn Error>> {
-        let mut last_seen = HashMap::new();
-        last_seen.insert("xid", Some(10000000));
-        // Set up our state
+    /// Task manager following the changes of the windows with `wait_for_change`.
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let task_manager = Self::requests_only()?;
+        task_manager
+            .conn
+            .change_window_attributes(
+                task_manager.root,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+            )?
+            .check()?;
+
+        Ok(task_manager)
+    }
+
+    /// Task manager only sending requests to the window manager. No event is selected, as
+    /// nothing would read them off the connection.
+    pub fn requests_only() -> Result<Self, Box<dyn Error>> {
         let (conn, screen) = XCBConnection::connect(None)?;
         let root = conn.setup().roots[screen].root;
-        let net_activate_win = conn.intern_atom(false, b"_NET_ACTIVE_WINDOW").unwrap();
-        let net_wm_name = conn.intern_atom(false, b"_NET_WM_NAME").unwrap();
-        let utf8_string = conn.intern_atom(false, b"UTF8_STRING").unwrap();
-        let net_activate_win = net_activate_win.reply().unwrap().atom;
-        let net_wm_name = net_wm_name.reply().unwrap().atom;
-        let utf8_string = utf8_string.reply().unwrap().atom;
-        let (focus, _) = find_active_window(&conn, root, net_activate_win, &mut last_seen)?;
-        println!("XID {:?}", focus);
-        // Collect the replies to the atoms
-        let (net_wm_name, utf8_string) = (net_wm_name, utf8_string);
-        let (wm_class, string): (AtomEnum, AtomEnum) =
-            (AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into());
-        // Get the property from the window that we need
-        let name =
-            conn.get_property(false, focus, net_wm_name, utf8_string, 0, u32::max_value())?;
-        let class = conn.get_property(false, focus, wm_class, string, 0, u32::max_value())?;
-        let (name, class) = (name.reply()?, class.reply()?);7j
-        println!("Window name: {:?}", parse_string_property(&name));
-        let (instance, class) = parse_wm_class(&class);
-        println!("Window instance: {:?}", instance);
-        println!("Window class: {:?}", class);
-        // Print out the result
-        // loop {
-        //     let (win, changed) = find_active_window(&conn, root, net_activate_win, &mut last_seen)?;
-        //     if changed {
-        //         println!("Window name: {:?}", parse_string_property(&name));
-        //         let (instance, class) = parse_wm_class(&class);
-        //         println!("Window instance: {:?}", instance);
-        //         println!("Window class: {:?}", class);
-        //     } else {
-        //         loop {}
-        //     }
-        // }
+        let atoms = Atoms::new(&conn)?.reply()?;
+
+        Ok(Self {
+            conn,
+            root,
+            atoms,
+            watched: HashSet::new(),
+        })
+    }
+
+    /// Windows to show in the taskbar and the active one.
+    pub fn tasks(&mut self) -> Result<Tasks, Box<dyn Error>> {
+        let client_list = self.window_list(self.root, self.atoms._NET_CLIENT_LIST)?;
+        // Windows closed since the last time are no longer watched.
+        self.watched.retain(|window| client_list.contains(window));
+        for window in &client_list {
+            if self.watched.insert(*window) {
+                // Selected before reading the properties so that no change is missed. The
+                // window may already be gone, which the reads below find out.
+                self.conn.change_window_attributes(
+                    *window,
+                    &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+                )?;
+            }
+        }
+
+        Ok(Tasks {
+            windows: client_list
+                .into_iter()
+                .filter_map(|window| self.task_window(window))
+                .collect(),
+            active: self
+                .window_list(self.root, self.atoms._NET_ACTIVE_WINDOW)?
+                .first()
+                .copied()
+                .filter(|window| *window != x11rb::NONE),
+            workspaces: self.workspaces()?,
+        })
+    }
+
+    fn workspaces(&self) -> Result<Workspaces, Box<dyn Error>> {
+        let atoms = &self.atoms;
+        let count = self
+            .window_list(self.root, atoms._NET_NUMBER_OF_DESKTOPS)?
+            .first()
+            .copied()
+            .unwrap_or_default();
+        let mut names = self
+            .property(self.root, atoms._NET_DESKTOP_NAMES, atoms.UTF8_STRING)
+            .map(|names| parse_string_list(&names))
+            .unwrap_or_default();
+        names.resize(count as usize, String::new());
+
+        Ok(Workspaces {
+            names,
+            current: self
+                .window_list(self.root, atoms._NET_CURRENT_DESKTOP)?
+                .first()
+                .copied()
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Block until a property the taskbar shows changes.
+    pub fn wait_for_change(&self) -> Result<(), Box<dyn Error>> {
+        let mut event = Some(self.conn.wait_for_event()?);
+        let mut has_changed = false;
+        // Changes often come together, they are all handled at once.
+        while let Some(current) = event {
+            has_changed |= self.is_relevant(&current);
+            event = if has_changed {
+                self.conn.poll_for_event()?
+            } else {
+                Some(self.conn.wait_for_event()?)
+            };
+        }
         Ok(())
     }
+
+    fn is_relevant(&self, event: &Event) -> bool {
+        let atoms = &self.atoms;
+        match event {
+            Event::PropertyNotify(event) if event.window == self.root => [
+                atoms._NET_CLIENT_LIST,
+                atoms._NET_ACTIVE_WINDOW,
+                atoms._NET_NUMBER_OF_DESKTOPS,
+                atoms._NET_CURRENT_DESKTOP,
+                atoms._NET_DESKTOP_NAMES,
+            ]
+            .contains(&event.atom),
+            Event::PropertyNotify(event) => [
+                atoms._NET_WM_NAME,
+                AtomEnum::WM_NAME.into(),
+                atoms._NET_WM_ICON,
+                AtomEnum::WM_CLASS.into(),
+                atoms._NET_WM_STATE,
+                atoms._NET_WM_DESKTOP,
+            ]
+            .contains(&event.atom),
+            _ => false,
+        }
+    }
+
+    /// Ask the window manager to raise and focus `window`, restoring it when minimized.
+    pub fn activate(&self, window: Window) -> Result<(), Box<dyn Error>> {
+        self.send_message(
+            window,
+            self.atoms._NET_ACTIVE_WINDOW,
+            [SOURCE_PAGER, CURRENT_TIME, 0, 0, 0],
+        )
+    }
+
+    /// Ask the window manager to minimize `window`.
+    pub fn minimize(&self, window: Window) -> Result<(), Box<dyn Error>> {
+        self.send_message(
+            window,
+            self.atoms.WM_CHANGE_STATE,
+            [ICONIC_STATE, 0, 0, 0, 0],
+        )
+    }
+
+    /// Ask the window manager to show the workspace at `index`.
+    pub fn switch_workspace(&self, index: u32) -> Result<(), Box<dyn Error>> {
+        self.send_message(
+            self.root,
+            self.atoms._NET_CURRENT_DESKTOP,
+            [index, CURRENT_TIME, 0, 0, 0],
+        )
+    }
+
+    /// Ask the window manager for `count` workspaces, windows of removed workspaces being
+    /// moved to the remaining ones.
+    pub fn set_workspace_count(&self, count: u32) -> Result<(), Box<dyn Error>> {
+        self.send_message(
+            self.root,
+            self.atoms._NET_NUMBER_OF_DESKTOPS,
+            [count, 0, 0, 0, 0],
+        )
+    }
+
+    fn send_message(
+        &self,
+        window: Window,
+        type_: Atom,
+        data: [u32; 5],
+    ) -> Result<(), Box<dyn Error>> {
+        let event = ClientMessageEvent {
+            response_type: CLIENT_MESSAGE_EVENT,
+            format: 32,
+            sequence: 0,
+            window,
+            type_,
+            data: data.into(),
+        };
+        self.conn.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    /// The window, unless it is gone or meant to stay out of taskbars.
+    fn task_window(&self, window: Window) -> Option<TaskWindow> {
+        let atoms = &self.atoms;
+        let states = self.window_list(window, atoms._NET_WM_STATE).ok()?;
+        let types = self.window_list(window, atoms._NET_WM_WINDOW_TYPE).ok()?;
+        // Docks, menus and the like are left out, a window without a type is a normal one.
+        let is_app_window = types.is_empty()
+            || types.contains(&atoms._NET_WM_WINDOW_TYPE_NORMAL)
+            || types.contains(&atoms._NET_WM_WINDOW_TYPE_DIALOG);
+        if !is_app_window || states.contains(&atoms._NET_WM_STATE_SKIP_TASKBAR) {
+            return None;
+        }
+        let name = self
+            .property(window, atoms._NET_WM_NAME, atoms.UTF8_STRING)
+            .filter(|name| !name.value.is_empty())
+            .or_else(|| self.property(window, AtomEnum::WM_NAME.into(), AtomEnum::ANY.into()))
+            .map(|name| parse_string_property(&name))
+            .unwrap_or_default();
+        let wm_class = self.property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into());
+        let (instance, class) = wm_class
+            .as_ref()
+            .and_then(parse_wm_class)
+            .unwrap_or_default();
+
+        Some(TaskWindow {
+            id: window,
+            name,
+            instance: instance.to_string(),
+            class: class.to_string(),
+            icon: self
+                .property(window, atoms._NET_WM_ICON, AtomEnum::CARDINAL.into())
+                .and_then(|icon| parse_icon(&icon)),
+            is_minimized: states.contains(&atoms._NET_WM_STATE_HIDDEN),
+            demands_attention: states.contains(&atoms._NET_WM_STATE_DEMANDS_ATTENTION),
+            desktop: self
+                .window_list(window, atoms._NET_WM_DESKTOP)
+                .ok()?
+                .first()
+                .copied()
+                .filter(|desktop| *desktop != ALL_WORKSPACES),
+        })
+    }
+
+    fn property(&self, window: Window, property: Atom, type_: Atom) -> Option<GetPropertyReply> {
+        self.conn
+            .get_property(false, window, property, type_, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()
+    }
+
+    /// Values of a property holding a list of windows or atoms, empty when it is not set.
+    fn window_list(&self, window: Window, property: Atom) -> Result<Vec<u32>, Box<dyn Error>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, AtomEnum::ANY, 0, u32::MAX)?
+            .reply()?;
+        Ok(reply
+            .value32()
+            .map(|values| values.collect())
+            .unwrap_or_default())
+    }
+}
+
+/// These tests need an X server without a window manager and are ignored otherwise, run them
+/// with `xvfb-run cargo test -- --ignored`. A scripted client stands in for the applications
+/// and the window manager.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    struct TestClient {
+        conn: XCBConnection,
+        root: Window,
+        atoms: Atoms,
+    }
+
+    impl TestClient {
+        fn connect() -> Self {
+            let (conn, screen) = XCBConnection::connect(None).unwrap();
+            let root = conn.setup().roots[screen].root;
+            let atoms = Atoms::new(&conn).unwrap().reply().unwrap();
+            Self { conn, root, atoms }
+        }
+
+        fn create_window(&self, name: &str, wm_class: &[u8]) -> Window {
+            let window = self.conn.generate_id().unwrap();
+            self.conn
+                .create_window(
+                    0,
+                    window,
+                    self.root,
+                    0,
+                    0,
+                    100,
+                    100,
+                    0,
+                    WindowClass::INPUT_OUTPUT,
+                    0,
+                    &CreateWindowAux::new(),
+                )
+                .unwrap();
+            self.set_name(window, name);
+            self.conn
+                .change_property8(
+                    PropMode::REPLACE,
+                    window,
+                    AtomEnum::WM_CLASS,
+                    AtomEnum::STRING,
+                    wm_class,
+                )
+                .unwrap();
+            window
+        }
+
+        fn set_name(&self, window: Window, name: &str) {
+            self.conn
+                .change_property8(
+                    PropMode::REPLACE,
+                    window,
+                    self.atoms._NET_WM_NAME,
+                    self.atoms.UTF8_STRING,
+                    name.as_bytes(),
+                )
+                .unwrap();
+            self.conn.flush().unwrap();
+        }
+
+        fn set_list(&self, window: Window, property: Atom, type_: AtomEnum, values: &[u32]) {
+            self.conn
+                .change_property32(PropMode::REPLACE, window, property, type_, values)
+                .unwrap();
+            self.conn.flush().unwrap();
+        }
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn follows_client_windows() {
+        let client = TestClient::connect();
+        let mut task_manager = TaskManager::new().unwrap();
+        let atoms = client.atoms;
+        let editor = client.create_window("Notes - Editor", b"editor\0Editor\0");
+        let player = client.create_window("Player", b"player\0Player\0");
+        let dock = client.create_window("Dock", b"dock\0Dock\0");
+        // A 16x1 opaque black image followed by a 1x1 one.
+        let mut icon = vec![16, 1];
+        icon.extend(vec![0xff00_0000; 16]);
+        icon.extend(&[1, 1, 0x8011_2233]);
+        client.set_list(editor, atoms._NET_WM_ICON, AtomEnum::CARDINAL, &icon);
+        let dock_type = client
+            .conn
+            .intern_atom(false, b"_NET_WM_WINDOW_TYPE_DOCK")
+            .unwrap()
+            .reply()
+            .unwrap()
+            .atom;
+        client.set_list(
+            dock,
+            atoms._NET_WM_WINDOW_TYPE,
+            AtomEnum::ATOM,
+            &[dock_type],
+        );
+        client.set_list(
+            client.root,
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            &[editor, player, dock],
+        );
+        client.set_list(
+            client.root,
+            atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW,
+            &[player],
+        );
+        client.set_list(
+            client.root,
+            atoms._NET_NUMBER_OF_DESKTOPS,
+            AtomEnum::CARDINAL,
+            &[3],
+        );
+        client.set_list(
+            client.root,
+            atoms._NET_CURRENT_DESKTOP,
+            AtomEnum::CARDINAL,
+            &[1],
+        );
+        client
+            .conn
+            .change_property8(
+                PropMode::REPLACE,
+                client.root,
+                atoms._NET_DESKTOP_NAMES,
+                atoms.UTF8_STRING,
+                b"Web\0Code\0",
+            )
+            .unwrap();
+        client.set_list(editor, atoms._NET_WM_DESKTOP, AtomEnum::CARDINAL, &[1]);
+        client.set_list(
+            player,
+            atoms._NET_WM_DESKTOP,
+            AtomEnum::CARDINAL,
+            &[ALL_WORKSPACES],
+        );
+
+        let tasks = task_manager.tasks().unwrap();
+        let ids: Vec<Window> = tasks.windows.iter().map(|window| window.id).collect();
+        assert_eq!(ids, vec![editor, player]);
+        assert_eq!(tasks.active, Some(player));
+        assert_eq!(tasks.windows[0].name, "Notes - Editor");
+        assert_eq!(
+            (
+                tasks.windows[0].instance.as_str(),
+                tasks.windows[0].class.as_str()
+            ),
+            ("editor", "Editor")
+        );
+        // The largest icon is picked when none is as large as the taskbar's.
+        let icon = tasks.windows[0].icon.as_ref().unwrap();
+        assert_eq!((icon.width, icon.height, icon.pixels.len()), (16, 1, 64));
+        assert!(tasks.windows[1].icon.is_none());
+        // Workspaces the window manager leaves unnamed get an empty name.
+        assert_eq!(tasks.workspaces.names, vec!["Web", "Code", ""]);
+        assert_eq!(tasks.workspaces.current, 1);
+        assert_eq!(tasks.windows[0].desktop, Some(1));
+        assert_eq!(tasks.windows[1].desktop, None);
+
+        client.set_name(editor, "Draft - Editor");
+        task_manager.wait_for_change().unwrap();
+        assert_eq!(
+            task_manager.tasks().unwrap().windows[0].name,
+            "Draft - Editor"
+        );
+
+        client.set_list(
+            player,
+            atoms._NET_WM_STATE,
+            AtomEnum::ATOM,
+            &[atoms._NET_WM_STATE_HIDDEN],
+        );
+        task_manager.wait_for_change().unwrap();
+        assert!(task_manager.tasks().unwrap().windows[1].is_minimized);
+
+        client.set_list(
+            client.root,
+            atoms._NET_CURRENT_DESKTOP,
+            AtomEnum::CARDINAL,
+            &[2],
+        );
+        task_manager.wait_for_change().unwrap();
+        assert_eq!(task_manager.tasks().unwrap().workspaces.current, 2);
+
+        client.set_list(
+            client.root,
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            &[player],
+        );
+        task_manager.wait_for_change().unwrap();
+        let ids: Vec<Window> = task_manager
+            .tasks()
+            .unwrap()
+            .windows
+            .iter()
+            .map(|window| window.id)
+            .collect();
+        assert_eq!(ids, vec![player]);
+        client.set_list(client.root, atoms._NET_CLIENT_LIST, AtomEnum::WINDOW, &[]);
+    }
+
+    #[test]
+    #[ignore = "needs Xvfb"]
+    fn sends_requests_to_the_window_manager() {
+        let client = TestClient::connect();
+        let task_manager = TaskManager::requests_only().unwrap();
+        let window = client.create_window("Editor", b"editor\0Editor\0");
+        // The client plays the window manager, to which the requests are redirected.
+        client
+            .conn
+            .change_window_attributes(
+                client.root,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::SUBSTRUCTURE_REDIRECT),
+            )
+            .unwrap()
+            .check()
+            .unwrap();
+
+        let next_message = || loop {
+            if let Event::ClientMessage(event) = client.conn.wait_for_event().unwrap() {
+                return (event.window, event.type_, event.data.as_data32()[0]);
+            }
+        };
+        task_manager.activate(window).unwrap();
+        assert_eq!(
+            next_message(),
+            (window, client.atoms._NET_ACTIVE_WINDOW, SOURCE_PAGER)
+        );
+        task_manager.minimize(window).unwrap();
+        assert_eq!(
+            next_message(),
+            (window, client.atoms.WM_CHANGE_STATE, ICONIC_STATE)
+        );
+        task_manager.switch_workspace(2).unwrap();
+        assert_eq!(
+            next_message(),
+            (client.root, client.atoms._NET_CURRENT_DESKTOP, 2)
+        );
+        task_manager.set_workspace_count(4).unwrap();
+        assert_eq!(
+            next_message(),
+            (client.root, client.atoms._NET_NUMBER_OF_DESKTOPS, 4)
+        );
+    }
 }
@@ -1,3 +1,4 @@
+pub mod app_launcher;
 pub mod applets;
 pub mod battery;
 pub mod common;
@@ -5,4 +6,6 @@ pub mod context_menu;
 pub mod monitor;
 pub mod panel;
 pub mod sound;
+pub mod taskbar;
 pub mod wireless;
+pub mod workspaces;
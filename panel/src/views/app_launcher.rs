@@ -0,0 +1,393 @@
+mod category;
+mod fuzzy;
+
+use super::common::{icon, search};
+use super::panel::Message;
+use crate::config::PanelConfig;
+use crate::styles::{
+    buttonstyle::buttons::ButtonStyle,
+    containers::{CustomContainer, ACCENT, HOVERED},
+};
+use category::Category;
+use freedesktop::desktop_entry::{self, DesktopEntry};
+use freedesktop::icon_theme;
+use iced::{image, svg::Svg};
+use iced_wgpu::Renderer;
+use iced_winit::{
+    button, scrollable, text_input, winit::event_loop::EventLoopProxy, Align, Application, Button,
+    Color, Column, Command, Container, Element, Length, Program, Row, Scrollable, Space, Text,
+    TextInput,
+};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+const ICON_SIZE: u16 = 32;
+const SIDEBAR_WIDTH: u16 = 160;
+
+/// List of applications shown while nothing is searched.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Section {
+    Favorites,
+    Recent,
+    All,
+    Category(Category),
+}
+
+impl Section {
+    fn name(&self) -> &'static str {
+        match self {
+            Section::Favorites => "Favorites",
+            Section::Recent => "Recently Used",
+            Section::All => "All Applications",
+            Section::Category(category) => category.name(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LauncherApp {
+    id: String,
+    entry: DesktopEntry,
+    category: Category,
+    icon_path: Option<PathBuf>,
+    launch_state: button::State,
+    favorite_state: button::State,
+}
+
+/// Application launcher opened from the KOOMPI button of the panel.
+#[derive(Debug)]
+pub struct AppLauncher {
+    apps: Vec<LauncherApp>,
+    // Indices of the applications listed, in the order they are shown.
+    shown: Vec<usize>,
+    sections: Vec<(Section, button::State)>,
+    section: Section,
+    query: String,
+    search_state: text_input::State,
+    sections_scroll: scrollable::State,
+    apps_scroll: scrollable::State,
+    config: PanelConfig,
+    proxy: EventLoopProxy<Message>,
+}
+
+#[derive(Debug, Clone)]
+pub enum AppLauncherMsg {
+    Refresh,
+    SearchChanged(String),
+    SearchSubmitted,
+    SectionSelected(Section),
+    Launch(usize),
+    ToggleFavorite(usize),
+}
+
+impl AppLauncher {
+    /// Index the installed applications again and start a new search.
+    fn refresh(&mut self) {
+        let mut apps: Vec<LauncherApp> = desktop_entry::applications()
+            .into_iter()
+            .map(|(id, entry)| LauncherApp {
+                category: Category::from_categories(entry.categories()),
+                icon_path: entry
+                    .icon()
+                    .and_then(|icon| icon_theme::lookup_icon(icon, ICON_SIZE, 1)),
+                id,
+                entry,
+                launch_state: button::State::new(),
+                favorite_state: button::State::new(),
+            })
+            .collect();
+        apps.sort_by_cached_key(|app| app.entry.name().unwrap_or(&app.id).to_lowercase());
+        let categories: BTreeSet<Category> = apps.iter().map(|app| app.category).collect();
+
+        self.sections = vec![Section::Favorites, Section::Recent, Section::All]
+            .into_iter()
+            .chain(categories.into_iter().map(Section::Category))
+            .map(|section| (section, button::State::new()))
+            .collect();
+        if !self
+            .sections
+            .iter()
+            .any(|(section, _)| *section == self.section)
+        {
+            self.section = Section::All;
+        }
+        self.apps = apps;
+        self.config = PanelConfig::load().unwrap_or_else(|e| {
+            println!("Error: {:?}", e);
+            PanelConfig::default()
+        });
+        self.query.clear();
+        self.search_state = text_input::State::focused();
+        self.apps_scroll = scrollable::State::new();
+        self.update_shown();
+    }
+
+    /// Applications matching the search, the best matches first, or else those of the
+    /// selected section.
+    fn update_shown(&mut self) {
+        let query = self.query.trim();
+
+        self.shown = if !query.is_empty() {
+            let mut scores: Vec<(i32, usize)> = self
+                .apps
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, app)| Some((fuzzy::app_score(&app.entry, query)?, idx)))
+                .collect();
+            scores.sort_by(|(score, idx), (other_score, other_idx)| {
+                other_score.cmp(score).then(idx.cmp(other_idx))
+            });
+            scores.into_iter().map(|(_, idx)| idx).collect()
+        } else {
+            match self.section {
+                Section::Favorites => self.indices_of(&self.config.favorites),
+                Section::Recent => self.indices_of(&self.config.recent),
+                Section::All => (0..self.apps.len()).collect(),
+                Section::Category(category) => (0..self.apps.len())
+                    .filter(|idx| self.apps[*idx].category == category)
+                    .collect(),
+            }
+        };
+    }
+
+    /// Indices of the installed applications among `ids`, in their order.
+    fn indices_of(&self, ids: &[String]) -> Vec<usize> {
+        ids.iter()
+            .filter_map(|id| self.apps.iter().position(|app| app.id == *id))
+            .collect()
+    }
+}
+
+impl Application for AppLauncher {
+    type Flags = EventLoopProxy<Message>;
+
+    fn new(flags: Self::Flags) -> (Self, Command<AppLauncherMsg>) {
+        let mut launcher = Self {
+            apps: Vec::new(),
+            shown: Vec::new(),
+            sections: Vec::new(),
+            section: Section::All,
+            query: String::new(),
+            search_state: text_input::State::new(),
+            sections_scroll: scrollable::State::new(),
+            apps_scroll: scrollable::State::new(),
+            config: PanelConfig::default(),
+            proxy: flags,
+        };
+        launcher.refresh();
+
+        (launcher, Command::none())
+    }
+
+    fn title(&self) -> String {
+        String::from("Applications")
+    }
+}
+
+impl Program for AppLauncher {
+    type Renderer = Renderer;
+    type Message = AppLauncherMsg;
+
+    fn update(&mut self, msg: AppLauncherMsg) -> Command<AppLauncherMsg> {
+        match msg {
+            AppLauncherMsg::Refresh => self.refresh(),
+            AppLauncherMsg::SearchChanged(query) => {
+                self.query = query;
+                self.apps_scroll = scrollable::State::new();
+                self.update_shown();
+            }
+            // Enter launches the best match.
+            AppLauncherMsg::SearchSubmitted => {
+                if let Some(idx) = self.shown.first().copied() {
+                    return self.update(AppLauncherMsg::Launch(idx));
+                }
+            }
+            AppLauncherMsg::SectionSelected(section) => {
+                self.section = section;
+                self.query.clear();
+                self.apps_scroll = scrollable::State::new();
+                self.update_shown();
+            }
+            AppLauncherMsg::Launch(idx) => {
+                if let Some(app) = self.apps.get(idx) {
+                    match app.entry.handle_exec(&[], None) {
+                        Ok(()) => {
+                            if let Err(e) = self.config.add_recent(&app.id) {
+                                println!("Error: {:?}", e);
+                            }
+                            self.proxy.send_event(Message::HideMenu).ok();
+                        }
+                        Err(e) => println!("Error: {:?}", e),
+                    }
+                }
+            }
+            AppLauncherMsg::ToggleFavorite(idx) => {
+                if let Some(app) = self.apps.get(idx) {
+                    if let Err(e) = self.config.toggle_favorite(&app.id) {
+                        println!("Error: {:?}", e);
+                    }
+                    if self.section == Section::Favorites {
+                        self.update_shown();
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view(&mut self) -> Element<AppLauncherMsg, Renderer> {
+        let Self {
+            apps,
+            shown,
+            sections,
+            section,
+            query,
+            search_state,
+            sections_scroll,
+            apps_scroll,
+            config,
+            ..
+        } = self;
+        let is_searching = !query.trim().is_empty();
+
+        let search_bar = Row::new()
+            .spacing(8)
+            .align_items(Align::Center)
+            .push(search())
+            .push(
+                TextInput::new(
+                    search_state,
+                    "Search applications...",
+                    query,
+                    AppLauncherMsg::SearchChanged,
+                )
+                .on_submit(AppLauncherMsg::SearchSubmitted)
+                .padding(8)
+                .width(Length::Fill),
+            );
+
+        let section_list =
+            sections
+                .iter_mut()
+                .fold(Column::new().spacing(2), |column, (item, state)| {
+                    let style = if !is_searching && item == section {
+                        ButtonStyle::CircleRadius(15, 85, 179, 1.0, 4.0, Color::WHITE)
+                    } else {
+                        ButtonStyle::Transparent
+                    };
+                    column.push(
+                        Button::new(state, Text::new(item.name()))
+                            .width(Length::Fill)
+                            .padding(6)
+                            .on_press(AppLauncherMsg::SectionSelected(*item))
+                            .style(style),
+                    )
+                });
+
+        // Each shown application is borrowed once, in the order it is shown.
+        let mut slots: Vec<Option<&mut LauncherApp>> = apps.iter_mut().map(Some).collect();
+        let app_list = shown
+            .iter()
+            .filter_map(|idx| Some((*idx, slots.get_mut(*idx)?.take()?)))
+            .fold(Column::new().spacing(2), |column, (idx, app)| {
+                let is_favorite = config.is_favorite(&app.id);
+                column.push(app_row(idx, app, is_favorite))
+            });
+        let app_list: Element<_, _> = if !shown.is_empty() {
+            app_list.into()
+        } else {
+            let hint = match section {
+                _ if is_searching => "No applications found",
+                Section::Favorites => "Star an application to add it to your favorites",
+                Section::Recent => "Applications you launch will be listed here",
+                _ => "No applications",
+            };
+            Container::new(Text::new(hint).color(HOVERED))
+                .width(Length::Fill)
+                .padding(20)
+                .center_x()
+                .into()
+        };
+
+        let content = Row::new()
+            .spacing(10)
+            .height(Length::Fill)
+            .push(
+                Scrollable::new(sections_scroll)
+                    .width(Length::Units(SIDEBAR_WIDTH))
+                    .height(Length::Fill)
+                    .push(section_list),
+            )
+            .push(
+                Scrollable::new(apps_scroll)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .push(app_list),
+            );
+
+        Container::new(
+            Column::new()
+                .padding(10)
+                .spacing(10)
+                .push(search_bar)
+                .push(content),
+        )
+        .style(CustomContainer::Background)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+}
+
+/// Button launching an application followed by the one adding it to the favorites.
+fn app_row(idx: usize, app: &mut LauncherApp, is_favorite: bool) -> Row<AppLauncherMsg, Renderer> {
+    let LauncherApp {
+        entry,
+        icon_path,
+        launch_state,
+        favorite_state,
+        ..
+    } = app;
+
+    let mut details = Column::new().push(Text::new(entry.name().unwrap_or_default()));
+    if let Some(generic_name) = entry.generic_name() {
+        details = details.push(Text::new(generic_name).size(12).color(HOVERED));
+    }
+    let content = Row::new()
+        .spacing(10)
+        .align_items(Align::Center)
+        .push(app_icon(icon_path.as_deref()))
+        .push(details);
+    let star = icon('\u{f005}').color(if is_favorite { ACCENT } else { HOVERED });
+
+    Row::new()
+        .align_items(Align::Center)
+        .push(
+            Button::new(launch_state, content)
+                .width(Length::Fill)
+                .padding(6)
+                .on_press(AppLauncherMsg::Launch(idx))
+                .style(ButtonStyle::Transparent),
+        )
+        .push(
+            Button::new(favorite_state, star)
+                .padding(6)
+                .on_press(AppLauncherMsg::ToggleFavorite(idx))
+                .style(ButtonStyle::Transparent),
+        )
+}
+
+fn app_icon<'a>(icon_path: Option<&Path>) -> Element<'a, AppLauncherMsg, Renderer> {
+    let size = Length::Units(ICON_SIZE);
+
+    match icon_path {
+        Some(path) if path.extension() == Some("svg".as_ref()) => {
+            Svg::from_path(path).width(size).height(size).into()
+        }
+        Some(path) => image::Image::new(image::Handle::from_path(path))
+            .width(size)
+            .height(size)
+            .into(),
+        None => Space::new(size, size).into(),
+    }
+}
@@ -0,0 +1,63 @@
+/// Main categories of the Desktop Menu Specification an application is listed under.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Accessories,
+    Development,
+    Education,
+    Games,
+    Graphics,
+    Internet,
+    Multimedia,
+    Office,
+    Science,
+    Settings,
+    System,
+    Other,
+}
+
+impl Category {
+    /// Category of an application from the values of its `Categories` key, the first main
+    /// category it names.
+    pub fn from_categories(categories: &[String]) -> Self {
+        categories
+            .iter()
+            .find_map(|category| Self::from_main_category(category))
+            .unwrap_or(Category::Other)
+    }
+
+    fn from_main_category(category: &str) -> Option<Self> {
+        use Category::*;
+        Some(match category {
+            "Utility" => Accessories,
+            "Development" => Development,
+            "Education" => Education,
+            "Game" => Games,
+            "Graphics" => Graphics,
+            "Network" => Internet,
+            "AudioVideo" | "Audio" | "Video" => Multimedia,
+            "Office" => Office,
+            "Science" => Science,
+            "Settings" => Settings,
+            "System" => System,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        use Category::*;
+        match self {
+            Accessories => "Accessories",
+            Development => "Development",
+            Education => "Education",
+            Games => "Games",
+            Graphics => "Graphics",
+            Internet => "Internet",
+            Multimedia => "Multimedia",
+            Office => "Office",
+            Science => "Science",
+            Settings => "Settings",
+            System => "System",
+            Other => "Other",
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use freedesktop::desktop_entry::DesktopEntry;
+
+const CONSECUTIVE_BONUS: i32 = 8;
+const WORD_START_BONUS: i32 = 6;
+const PREFIX_BONUS: i32 = 12;
+// Matches in the name count more than in the generic name, and those more than in keywords.
+const NAME_WEIGHT: i32 = 3;
+const GENERIC_NAME_WEIGHT: i32 = 2;
+const KEYWORD_WEIGHT: i32 = 1;
+
+/// Score of an application for `query`, higher being better. Every word of the query must
+/// match its name, generic name or one of its keywords.
+pub fn app_score(entry: &DesktopEntry, query: &str) -> Option<i32> {
+    let query = query.to_lowercase();
+    let fields = entry
+        .name()
+        .map(|name| (name, NAME_WEIGHT))
+        .into_iter()
+        .chain(entry.generic_name().map(|name| (name, GENERIC_NAME_WEIGHT)))
+        .chain(
+            entry
+                .keywords()
+                .iter()
+                .map(|keyword| (keyword.as_str(), KEYWORD_WEIGHT)),
+        );
+    let fields: Vec<(&str, i32)> = fields.collect();
+
+    query.split_whitespace().try_fold(0, |total, word| {
+        let best = fields
+            .iter()
+            .filter_map(|(text, weight)| Some(score(word, text)? * weight))
+            .max()?;
+        Some(total + best)
+    })
+}
+
+/// Score of `query`, in lowercase, matched against `text`. The characters of the query must
+/// appear in the text in order; those following each other or starting words score more, and
+/// shorter texts are preferred.
+pub fn score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last = None;
+
+    for c in query.chars() {
+        let idx = pos + text.get(pos..)?.iter().position(|t| *t == c)?;
+        score += 1;
+        if idx > 0 && last == Some(idx - 1) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        last = Some(idx);
+        pos = idx + 1;
+    }
+    if text
+        .iter()
+        .copied()
+        .take(query.chars().count())
+        .eq(query.chars())
+    {
+        score += PREFIX_BONUS;
+    }
+
+    Some(score - (text.len() - query.chars().count()) as i32 / 8)
+}
@@ -1,6 +1,12 @@
 use super::applets::ControlType;
 use super::common::*;
+use super::taskbar::{Taskbar, TaskbarMsg, WindowGroup};
+use super::workspaces::{WorkspaceSwitcher, WorkspacesMsg};
 use crate::styles::buttonstyle::buttons::ButtonStyle;
+use crate::task_manager::{
+    self,
+    taskmanager::{TaskManager, Tasks},
+};
 use chrono::Timelike;
 use iced::time;
 use iced::{svg::Svg, Text};
@@ -10,7 +16,11 @@ use iced_winit::{
     Length, Program, Row, Space, Subscription,
 };
 // use std::{cell::RefCell, rc::Rc};
+use std::rc::Rc;
 use winit::event_loop::EventLoopProxy;
+/// Width of the KOOMPI button, left of the workspace switcher and the taskbar.
+pub const MENU_BUTTON_WIDTH: u16 = 46;
+
 #[derive(Debug)]
 pub struct DesktopPanel {
     pub background_color: Color,
@@ -26,11 +36,25 @@ pub struct DesktopPanel {
     battery_visible: bool,
     wifi_visible: bool,
     battery_level: f32,
+    taskbar: Taskbar,
+    workspaces: WorkspaceSwitcher,
+    // Position of the cursor from the left of the panel, if it is over it.
+    cursor_x: Option<f32>,
+    // Width of the icons of the system tray, hosted at the right edge of the panel.
+    tray_width: u16,
 }
 
 impl Application for DesktopPanel {
     type Flags = EventLoopProxy<Message>;
     fn new(flags: Self::Flags) -> (Self, Command<Message>) {
+        // Shared by the applets asking the window manager for changes.
+        let task_manager = match TaskManager::requests_only() {
+            Ok(task_manager) => Some(Rc::new(task_manager)),
+            Err(e) => {
+                println!("Error: {:?}", e);
+                None
+            }
+        };
         (
             Self {
                 background_color: Color::from_rgb8(255, 255, 255),
@@ -40,12 +64,16 @@ impl Application for DesktopPanel {
                 pre_kind: ControlType::Monitor,
                 kind: ControlType::Monitor,
                 now: chrono::Local::now(),
-                proxy: flags,
+                proxy: flags.clone(),
                 monitor_visible: false,
                 sound_visible: false,
                 battery_visible: false,
                 wifi_visible: false,
                 battery_level: 0.0,
+                taskbar: Taskbar::new(flags, task_manager.clone()),
+                workspaces: WorkspaceSwitcher::new(task_manager),
+                cursor_x: None,
+                tray_width: 0,
             },
             Command::none(),
         )
@@ -54,8 +82,11 @@ impl Application for DesktopPanel {
         String::from("Title ")
     }
     fn subscription(&self) -> Subscription<Message> {
-        time::every(std::time::Duration::from_millis(500))
-            .map(|_| Message::Tick(chrono::Local::now()))
+        Subscription::batch(vec![
+            time::every(std::time::Duration::from_millis(500))
+                .map(|_| Message::Tick(chrono::Local::now())),
+            task_manager::tasks().map(Message::TasksChanged),
+        ])
     }
 }
 
@@ -64,11 +95,21 @@ pub enum Message {
     BackgroundColorChanged(Color),
     ShowAction,
     ShowMenu,
+    HideMenu,
+    ShowWindowList(Option<WindowGroup>),
     MonitorShow(bool),
     Battery(bool),
     SoundShow(bool),
     WifiShow(bool),
     Tick(chrono::DateTime<chrono::Local>),
+    TasksChanged(Tasks),
+    Taskbar(TaskbarMsg),
+    Workspaces(WorkspacesMsg),
+    CursorMoved(Option<f32>),
+    /// Lines scrolled with the mouse wheel over the panel, positive upward.
+    Scrolled(f32),
+    /// Width taken by the icons of the system tray.
+    TrayResized(u16),
     BatteryUpdate(f32),
     ShowPwdDialog(String),
     RequestExit,
@@ -88,6 +129,7 @@ impl Program for DesktopPanel {
             Message::ShowMenu => {
                 self.proxy.send_event(Message::ShowMenu).ok();
             }
+            Message::HideMenu | Message::ShowWindowList(_) => {}
             Message::RequestExit => {
                 self.proxy.send_event(Message::RequestExit).ok();
             }
@@ -137,8 +179,37 @@ impl Program for DesktopPanel {
             Message::Timer => {
                 self.now = chrono::Local::now();
             }
+            Message::TasksChanged(tasks) => {
+                self.workspaces
+                    .update(WorkspacesMsg::TasksChanged(tasks.clone()));
+                // The taskbar follows the switcher, whose width depends on the workspaces.
+                self.taskbar.update(TaskbarMsg::Moved(
+                    MENU_BUTTON_WIDTH + self.workspaces.width(),
+                ));
+                self.taskbar.update(TaskbarMsg::TasksChanged(tasks));
+            }
+            Message::Taskbar(msg) => {
+                self.taskbar.update(msg);
+            }
+            Message::Workspaces(msg) => {
+                self.workspaces.update(msg);
+            }
+            Message::CursorMoved(x) => {
+                self.cursor_x = x;
+                self.taskbar.update(TaskbarMsg::CursorMoved(x));
+            }
+            // Scrolling over the workspace switcher switches workspaces.
+            Message::Scrolled(lines) => {
+                let start = f32::from(MENU_BUTTON_WIDTH);
+                let end = start + f32::from(self.workspaces.width());
+                if self.cursor_x.map(|x| x >= start && x < end) == Some(true) {
+                    self.workspaces.update(WorkspacesMsg::Scrolled(lines));
+                }
+            }
+            Message::TrayResized(width) => {
+                self.tray_width = width;
+            }
             Message::Tick(local_time) => {
-                println!("Tick ");
                 let now = local_time;
 
                 if now != self.now {
@@ -160,7 +231,7 @@ impl Program for DesktopPanel {
         .height(Length::Units(36));
         let menu = Button::new(b1, svg)
             .on_press(Message::ShowMenu)
-            .width(Length::Shrink)
+            .width(Length::Units(MENU_BUTTON_WIDTH))
             .height(Length::Shrink)
             .style(ButtonStyle::Transparent);
         let system_tray = Row::new()
@@ -199,8 +270,11 @@ impl Program for DesktopPanel {
             .height(Length::Fill)
             .align_items(Align::End)
             .push(menu)
-            .push(Space::with_width(Length::Fill))
-            .push(system_tray);
+            .push(self.workspaces.view().map(Message::Workspaces))
+            .push(self.taskbar.view().map(Message::Taskbar))
+            .push(system_tray)
+            // Room for the tray icons, which are embedded over it.
+            .push(Space::with_width(Length::Units(self.tray_width)));
         Container::new(row)
             .width(Length::Fill)
             .height(Length::Fill)
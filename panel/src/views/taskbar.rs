@@ -0,0 +1,511 @@
+mod window_list;
+
+use super::common::icon;
+use super::panel::{Message, MENU_BUTTON_WIDTH};
+use crate::config::PanelConfig;
+use crate::styles::{
+    buttonstyle::buttons::ButtonStyle,
+    containers::{CustomContainer, HOVERED},
+};
+use crate::task_manager::{
+    app_matcher,
+    taskmanager::{TaskManager, TaskWindow, Tasks},
+};
+use freedesktop::desktop_entry::{self, DesktopEntry};
+use freedesktop::icon_theme;
+use iced::{image, svg::Svg};
+use iced_wgpu::Renderer;
+use iced_winit::{
+    button, winit::event_loop::EventLoopProxy, Align, Button, Color, Command, Container, Element,
+    Length, Program, Row, Text,
+};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use x11rb::protocol::xproto::Window;
+
+pub use window_list::{ListedWindow, WindowGroup, WindowList, WindowListMsg};
+
+const BUTTON_WIDTH: u16 = 180;
+/// Width of the buttons of pinned applications that are not running.
+const LAUNCHER_WIDTH: u16 = 36;
+const SPACING: u16 = 4;
+const ICON_SIZE: u16 = 20;
+const MAX_NAME_LEN: usize = 20;
+
+#[derive(Debug)]
+struct TaskbarApp {
+    id: String,
+    entry: DesktopEntry,
+    icon_path: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct TaskItem {
+    window: TaskWindow,
+    icon: Option<image::Handle>,
+}
+
+/// What the windows of a group have in common.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum GroupKey {
+    App(usize),
+    Class(String),
+    Window(Window),
+}
+
+#[derive(Debug)]
+struct TaskGroup {
+    key: GroupKey,
+    // Indices of the windows of the group among the tasks.
+    windows: Vec<usize>,
+    state: button::State,
+}
+
+impl TaskGroup {
+    fn new(key: GroupKey) -> Self {
+        Self {
+            key,
+            windows: Vec::new(),
+            state: button::State::new(),
+        }
+    }
+
+    fn width(&self) -> u16 {
+        if self.windows.is_empty() {
+            LAUNCHER_WIDTH
+        } else {
+            BUTTON_WIDTH
+        }
+    }
+}
+
+/// Buttons of the pinned applications and of the open windows, grouped by application.
+#[derive(Debug)]
+pub struct Taskbar {
+    apps: Vec<TaskbarApp>,
+    tasks: Vec<TaskItem>,
+    groups: Vec<TaskGroup>,
+    active: Option<Window>,
+    hovered: Option<usize>,
+    // Position of the taskbar from the left of the panel.
+    x: u16,
+    config: PanelConfig,
+    task_manager: Option<Rc<TaskManager>>,
+    proxy: EventLoopProxy<Message>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TaskbarMsg {
+    TasksChanged(Tasks),
+    GroupClicked(usize),
+    WindowClicked(Window),
+    PinToggled(String),
+    Moved(u16),
+    /// Position of the cursor from the left of the panel, if it is over it.
+    CursorMoved(Option<f32>),
+}
+
+impl Taskbar {
+    pub fn new(proxy: EventLoopProxy<Message>, task_manager: Option<Rc<TaskManager>>) -> Self {
+        let config = PanelConfig::load().unwrap_or_else(|e| {
+            println!("Error: {:?}", e);
+            PanelConfig::default()
+        });
+        let mut taskbar = Self {
+            apps: load_apps(),
+            tasks: Vec::new(),
+            groups: Vec::new(),
+            active: None,
+            hovered: None,
+            x: MENU_BUTTON_WIDTH,
+            config,
+            task_manager,
+            proxy,
+        };
+        taskbar.update_groups();
+        taskbar
+    }
+
+    fn update_tasks(&mut self, tasks: Tasks) {
+        let mut old_tasks = std::mem::take(&mut self.tasks);
+
+        // A new window without an application may come from one installed since the start.
+        let is_app_missing = tasks.windows.iter().any(|window| {
+            !old_tasks.iter().any(|task| task.window.id == window.id)
+                && find_app(&self.apps, window).is_none()
+        });
+        if is_app_missing {
+            self.apps = load_apps();
+        }
+
+        self.tasks = tasks
+            .windows
+            .into_iter()
+            .map(|window| {
+                let old_task = old_tasks
+                    .iter()
+                    .position(|task| task.window.id == window.id)
+                    .map(|idx| old_tasks.swap_remove(idx));
+                match old_task {
+                    // Icons are only decoded again when they change.
+                    Some(task) if task.window.icon == window.icon => TaskItem { window, ..task },
+                    _ => TaskItem {
+                        icon: window.icon.as_ref().map(|icon| {
+                            image::Handle::from_pixels(icon.width, icon.height, icon.pixels.clone())
+                        }),
+                        window,
+                    },
+                }
+            })
+            .collect();
+        self.active = tasks.active;
+        self.update_groups();
+
+        // The list of the hovered group follows its windows.
+        if self.hovered.map(|idx| idx >= self.groups.len()) == Some(true) {
+            self.hovered = None;
+        }
+        self.show_window_list();
+    }
+
+    /// Group the windows by application, after the pinned applications in their order.
+    fn update_groups(&mut self) {
+        let mut old_groups = std::mem::take(&mut self.groups);
+        let apps = &self.apps;
+        let mut groups: Vec<TaskGroup> = self
+            .config
+            .pinned
+            .iter()
+            .filter_map(|id| apps.iter().position(|app| app.id == *id))
+            .map(|app| TaskGroup::new(GroupKey::App(app)))
+            .collect();
+
+        for (idx, task) in self.tasks.iter().enumerate() {
+            let window = &task.window;
+            let key = match find_app(apps, window) {
+                Some(app) => GroupKey::App(app),
+                None if !window.class.is_empty() => GroupKey::Class(window.class.to_owned()),
+                None => GroupKey::Window(window.id),
+            };
+            match groups.iter_mut().find(|group| group.key == key) {
+                Some(group) => group.windows.push(idx),
+                None => {
+                    let mut group = TaskGroup::new(key);
+                    group.windows.push(idx);
+                    groups.push(group);
+                }
+            }
+        }
+
+        // Buttons keep their state while their group is shown.
+        for group in &mut groups {
+            if let Some(old_group) = old_groups.iter_mut().find(|old| old.key == group.key) {
+                group.state = std::mem::take(&mut old_group.state);
+            }
+        }
+        self.groups = groups;
+    }
+
+    fn group_windows(&self, group: &TaskGroup) -> Vec<&TaskWindow> {
+        group
+            .windows
+            .iter()
+            .map(|idx| &self.tasks[*idx].window)
+            .collect()
+    }
+
+    fn group_app(&self, group: &TaskGroup) -> Option<&TaskbarApp> {
+        match group.key {
+            GroupKey::App(app) => self.apps.get(app),
+            _ => None,
+        }
+    }
+
+    /// Activate a window of the group, cycling through them, or minimize its only window if
+    /// it is the active one. Pinned applications without windows are launched.
+    fn group_clicked(&self, idx: usize) {
+        let group = match self.groups.get(idx) {
+            Some(group) => group,
+            None => return,
+        };
+        let windows = self.group_windows(group);
+        let active = windows
+            .iter()
+            .position(|window| self.active == Some(window.id) && !window.is_minimized);
+
+        match (active, windows.first()) {
+            (None, Some(window)) => self.activate(window.id),
+            (None, None) => {
+                if let Some(app) = self.group_app(group) {
+                    if let Err(e) = app.entry.handle_exec(&[], None) {
+                        println!("Error: {:?}", e);
+                    }
+                }
+            }
+            (Some(_), Some(window)) if windows.len() == 1 => self.minimize(window.id),
+            (Some(pos), _) => self.activate(windows[(pos + 1) % windows.len()].id),
+        }
+    }
+
+    fn window_clicked(&self, window: Window) {
+        let is_minimized = self
+            .tasks
+            .iter()
+            .any(|task| task.window.id == window && task.window.is_minimized);
+        if self.active == Some(window) && !is_minimized {
+            self.minimize(window);
+        } else {
+            self.activate(window);
+        }
+    }
+
+    fn activate(&self, window: Window) {
+        if let Some(task_manager) = &self.task_manager {
+            if let Err(e) = task_manager.activate(window) {
+                println!("Error: {:?}", e);
+            }
+        }
+    }
+
+    fn minimize(&self, window: Window) {
+        if let Some(task_manager) = &self.task_manager {
+            if let Err(e) = task_manager.minimize(window) {
+                println!("Error: {:?}", e);
+            }
+        }
+    }
+
+    /// Group under `x`, from the left of the taskbar.
+    fn group_at(&self, x: f32) -> Option<usize> {
+        let mut start = 0.0;
+        for (idx, group) in self.groups.iter().enumerate() {
+            let end = start + f32::from(group.width());
+            if x >= start && x < end {
+                return Some(idx);
+            }
+            start = end + f32::from(SPACING);
+        }
+        None
+    }
+
+    /// Position of the group at `idx`, from the left of the panel.
+    fn group_x(&self, idx: usize) -> f32 {
+        let offset: u16 = self.groups[..idx]
+            .iter()
+            .map(|group| group.width() + SPACING)
+            .sum();
+        f32::from(self.x + offset)
+    }
+
+    fn hover(&mut self, x: Option<f32>) {
+        let hovered = x.and_then(|x| self.group_at(x - f32::from(self.x)));
+        if hovered != self.hovered {
+            self.hovered = hovered;
+            self.show_window_list();
+        }
+    }
+
+    /// Ask for the list of the hovered group's windows to be shown, or hidden.
+    fn show_window_list(&self) {
+        let window_group = self.hovered.map(|idx| {
+            let group = &self.groups[idx];
+            let app = self.group_app(group);
+            let windows = self.group_windows(group);
+            let title = match (app, windows.first()) {
+                (Some(app), _) => app.entry.name().unwrap_or(&app.id).to_string(),
+                (None, Some(window)) if windows.len() > 1 => window.class.to_owned(),
+                (None, Some(window)) => window.name.to_owned(),
+                (None, None) => String::new(),
+            };
+
+            WindowGroup {
+                x: self.group_x(idx),
+                title,
+                app_id: app.map(|app| app.id.to_owned()),
+                is_pinned: app
+                    .map(|app| self.config.is_pinned(&app.id))
+                    .unwrap_or(false),
+                windows: group
+                    .windows
+                    .iter()
+                    .map(|idx| {
+                        let task = &self.tasks[*idx];
+                        ListedWindow {
+                            id: task.window.id,
+                            name: task.window.name.to_owned(),
+                            icon: task.icon.clone(),
+                            is_active: self.active == Some(task.window.id),
+                        }
+                    })
+                    .collect(),
+            }
+        });
+        self.proxy
+            .send_event(Message::ShowWindowList(window_group))
+            .ok();
+    }
+}
+
+impl Program for Taskbar {
+    type Renderer = Renderer;
+    type Message = TaskbarMsg;
+
+    fn update(&mut self, msg: TaskbarMsg) -> Command<TaskbarMsg> {
+        match msg {
+            TaskbarMsg::TasksChanged(tasks) => self.update_tasks(tasks),
+            TaskbarMsg::GroupClicked(idx) => self.group_clicked(idx),
+            TaskbarMsg::WindowClicked(window) => self.window_clicked(window),
+            TaskbarMsg::PinToggled(id) => {
+                if let Err(e) = self.config.toggle_pinned(&id) {
+                    println!("Error: {:?}", e);
+                }
+                self.update_groups();
+                // The groups moved, so the list is closed until another one is hovered.
+                self.hovered = None;
+                self.show_window_list();
+            }
+            TaskbarMsg::Moved(x) => self.x = x,
+            TaskbarMsg::CursorMoved(x) => self.hover(x),
+        }
+        Command::none()
+    }
+
+    fn view(&mut self) -> Element<TaskbarMsg, Renderer> {
+        let Self {
+            apps,
+            tasks,
+            groups,
+            active,
+            ..
+        } = self;
+
+        groups
+            .iter_mut()
+            .enumerate()
+            .fold(
+                Row::new()
+                    .spacing(SPACING)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .align_items(Align::Center),
+                |row, (idx, group)| {
+                    let app = match group.key {
+                        GroupKey::App(app) => apps.get(app),
+                        _ => None,
+                    };
+                    let windows: Vec<&TaskItem> =
+                        group.windows.iter().map(|idx| &tasks[*idx]).collect();
+                    let is_active = windows
+                        .iter()
+                        .any(|task| *active == Some(task.window.id) && !task.window.is_minimized);
+                    let style = if windows.iter().any(|task| task.window.demands_attention) {
+                        ButtonStyle::CircleRadius(212, 176, 17, 0.6, 4.0, Color::BLACK)
+                    } else if is_active {
+                        ButtonStyle::CircleRadius(15, 85, 179, 0.25, 4.0, Color::BLACK)
+                    } else {
+                        ButtonStyle::Transparent
+                    };
+
+                    let app_icon = app.and_then(|app| app.icon_path.as_deref());
+                    let window_icon = windows.first().and_then(|task| task.icon.as_ref());
+                    let group_icon = match (app_icon, window_icon) {
+                        (Some(path), _) => path_icon(path),
+                        (None, Some(handle)) => handle_icon(handle),
+                        (None, None) => icon('\u{f2d0}').into(),
+                    };
+                    let mut content = Row::new()
+                        .spacing(6)
+                        .align_items(Align::Center)
+                        .push(group_icon);
+                    if let Some(first) = windows.first() {
+                        let name = match app {
+                            Some(app) if windows.len() > 1 => {
+                                app.entry.name().unwrap_or(&app.id).to_string()
+                            }
+                            _ => first.window.name.to_owned(),
+                        };
+                        let mut name = Text::new(truncate(&name)).size(14).width(Length::Fill);
+                        if windows.iter().all(|task| task.window.is_minimized) {
+                            name = name.color(HOVERED);
+                        }
+                        content = content.push(name);
+                    }
+                    if windows.len() > 1 {
+                        content = content.push(
+                            Container::new(
+                                Text::new(windows.len().to_string())
+                                    .size(11)
+                                    .color(Color::WHITE),
+                            )
+                            .padding(2)
+                            .style(CustomContainer::Primary),
+                        );
+                    }
+
+                    let width = Length::Units(group.width());
+                    row.push(
+                        Button::new(&mut group.state, content)
+                            .width(width)
+                            .padding(4)
+                            .on_press(TaskbarMsg::GroupClicked(idx))
+                            .style(style),
+                    )
+                },
+            )
+            .into()
+    }
+}
+
+/// Applications of the desktop entries with their icon.
+fn load_apps() -> Vec<TaskbarApp> {
+    desktop_entry::applications()
+        .into_iter()
+        .map(|(id, entry)| TaskbarApp {
+            icon_path: entry
+                .icon()
+                .and_then(|icon| icon_theme::lookup_icon(icon, ICON_SIZE, 1)),
+            id,
+            entry,
+        })
+        .collect()
+}
+
+fn find_app(apps: &[TaskbarApp], window: &TaskWindow) -> Option<usize> {
+    app_matcher::find_app(
+        apps.iter()
+            .map(|app| (app.id.as_str(), app.entry.startup_wm_class())),
+        &window.instance,
+        &window.class,
+    )
+}
+
+fn path_icon<'a>(path: &Path) -> Element<'a, TaskbarMsg, Renderer> {
+    let size = Length::Units(ICON_SIZE);
+
+    if path.extension() == Some("svg".as_ref()) {
+        Svg::from_path(path).width(size).height(size).into()
+    } else {
+        image::Image::new(image::Handle::from_path(path))
+            .width(size)
+            .height(size)
+            .into()
+    }
+}
+
+fn handle_icon<'a, M: 'a>(handle: &image::Handle) -> Element<'a, M, Renderer> {
+    let size = Length::Units(ICON_SIZE);
+    image::Image::new(handle.clone())
+        .width(size)
+        .height(size)
+        .into()
+}
+
+fn truncate(name: &str) -> String {
+    if name.chars().count() > MAX_NAME_LEN {
+        let mut name: String = name.chars().take(MAX_NAME_LEN - 3).collect();
+        name.push_str("...");
+        name
+    } else {
+        name.to_string()
+    }
+}
@@ -0,0 +1,176 @@
+use super::{handle_icon, truncate, TaskbarMsg};
+use crate::styles::{buttonstyle::buttons::ButtonStyle, containers::CustomContainer};
+use crate::views::{common::icon, panel::Message};
+use iced::image;
+use iced_wgpu::Renderer;
+use iced_winit::{
+    button, winit::event_loop::EventLoopProxy, Align, Application, Button, Color, Column, Command,
+    Container, Element, Length, Program, Row, Text,
+};
+use x11rb::protocol::xproto::Window;
+
+const PADDING: u16 = 6;
+const TITLE_HEIGHT: u16 = 24;
+const ROW_HEIGHT: u16 = 32;
+
+/// Windows of a taskbar group, listed while its button is hovered.
+#[derive(Debug, Clone)]
+pub struct WindowGroup {
+    /// Position of the group's button from the left of the panel.
+    pub x: f32,
+    pub title: String,
+    /// Desktop file ID of the application of the group, which can then be pinned.
+    pub app_id: Option<String>,
+    pub is_pinned: bool,
+    pub windows: Vec<ListedWindow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListedWindow {
+    pub id: Window,
+    pub name: String,
+    pub icon: Option<image::Handle>,
+    pub is_active: bool,
+}
+
+impl WindowGroup {
+    /// Height of the list showing the group.
+    pub fn height(&self) -> u16 {
+        let rows = self.windows.len() as u16 + self.app_id.is_some() as u16;
+        2 * PADDING + TITLE_HEIGHT + rows * ROW_HEIGHT
+    }
+}
+
+/// Popup listing the windows of a taskbar group, to activate one of them or pin the group's
+/// application.
+#[derive(Debug)]
+pub struct WindowList {
+    group: Option<WindowGroup>,
+    window_states: Vec<button::State>,
+    pin_state: button::State,
+    proxy: EventLoopProxy<Message>,
+}
+
+#[derive(Debug, Clone)]
+pub enum WindowListMsg {
+    Show(WindowGroup),
+    WindowClicked(Window),
+    PinToggled,
+}
+
+impl WindowList {
+    fn send(&self, msg: TaskbarMsg) {
+        self.proxy.send_event(Message::Taskbar(msg)).ok();
+    }
+}
+
+impl Application for WindowList {
+    type Flags = EventLoopProxy<Message>;
+
+    fn new(flags: Self::Flags) -> (Self, Command<WindowListMsg>) {
+        (
+            Self {
+                group: None,
+                window_states: Vec::new(),
+                pin_state: button::State::new(),
+                proxy: flags,
+            },
+            Command::none(),
+        )
+    }
+
+    fn title(&self) -> String {
+        String::from("Windows")
+    }
+}
+
+impl Program for WindowList {
+    type Renderer = Renderer;
+    type Message = WindowListMsg;
+
+    fn update(&mut self, msg: WindowListMsg) -> Command<WindowListMsg> {
+        match msg {
+            WindowListMsg::Show(group) => {
+                self.window_states
+                    .resize_with(group.windows.len(), button::State::new);
+                self.group = Some(group);
+            }
+            WindowListMsg::WindowClicked(window) => {
+                self.send(TaskbarMsg::WindowClicked(window));
+                // Closes the list.
+                self.send(TaskbarMsg::CursorMoved(None));
+            }
+            WindowListMsg::PinToggled => {
+                if let Some(id) = self.group.as_ref().and_then(|group| group.app_id.clone()) {
+                    self.send(TaskbarMsg::PinToggled(id));
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view(&mut self) -> Element<WindowListMsg, Renderer> {
+        let Self {
+            group,
+            window_states,
+            pin_state,
+            ..
+        } = self;
+        let mut content = Column::new().padding(PADDING);
+
+        if let Some(group) = group {
+            content = content.push(
+                Container::new(Text::new(truncate(&group.title)).size(14))
+                    .height(Length::Units(TITLE_HEIGHT))
+                    .center_y(),
+            );
+            for (window, state) in group.windows.iter().zip(window_states.iter_mut()) {
+                let mut row = Row::new().spacing(6).align_items(Align::Center);
+                if let Some(handle) = &window.icon {
+                    row = row.push(handle_icon(handle));
+                }
+                let style = if window.is_active {
+                    ButtonStyle::CircleRadius(15, 85, 179, 0.25, 4.0, Color::BLACK)
+                } else {
+                    ButtonStyle::Transparent
+                };
+                content = content.push(
+                    Button::new(state, row.push(Text::new(truncate(&window.name)).size(14)))
+                        .width(Length::Fill)
+                        .height(Length::Units(ROW_HEIGHT))
+                        .padding(6)
+                        .on_press(WindowListMsg::WindowClicked(window.id))
+                        .style(style),
+                );
+            }
+            if group.app_id.is_some() {
+                let label = if group.is_pinned {
+                    "Unpin from taskbar"
+                } else {
+                    "Pin to taskbar"
+                };
+                content = content.push(
+                    Button::new(
+                        pin_state,
+                        Row::new()
+                            .spacing(6)
+                            .align_items(Align::Center)
+                            .push(icon('\u{f08d}'))
+                            .push(Text::new(label).size(14)),
+                    )
+                    .width(Length::Fill)
+                    .height(Length::Units(ROW_HEIGHT))
+                    .padding(6)
+                    .on_press(WindowListMsg::PinToggled)
+                    .style(ButtonStyle::Transparent),
+                );
+            }
+        }
+
+        Container::new(content)
+            .style(CustomContainer::Background)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+}
@@ -0,0 +1,189 @@
+use super::common::icon;
+use crate::styles::{buttonstyle::buttons::ButtonStyle, containers::HOVERED};
+use crate::task_manager::taskmanager::{TaskManager, Tasks, Workspaces};
+use iced_wgpu::Renderer;
+use iced_winit::{button, Align, Button, Color, Command, Element, Length, Program, Row, Text};
+use std::error::Error;
+use std::rc::Rc;
+
+const BUTTON_WIDTH: u16 = 64;
+/// Width of the buttons adding and removing a workspace.
+const ACTION_WIDTH: u16 = 24;
+const SPACING: u16 = 2;
+const MAX_NAME_LEN: usize = 6;
+
+/// Buttons of the window manager's workspaces with the number of windows on each.
+#[derive(Debug)]
+pub struct WorkspaceSwitcher {
+    workspaces: Workspaces,
+    window_counts: Vec<usize>,
+    workspace_states: Vec<button::State>,
+    add_state: button::State,
+    remove_state: button::State,
+    task_manager: Option<Rc<TaskManager>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum WorkspacesMsg {
+    TasksChanged(Tasks),
+    WorkspaceClicked(u32),
+    /// Lines scrolled with the mouse wheel, positive upward.
+    Scrolled(f32),
+    AddWorkspace,
+    RemoveWorkspace,
+}
+
+impl WorkspaceSwitcher {
+    pub fn new(task_manager: Option<Rc<TaskManager>>) -> Self {
+        Self {
+            workspaces: Workspaces::default(),
+            window_counts: Vec::new(),
+            workspace_states: Vec::new(),
+            add_state: button::State::new(),
+            remove_state: button::State::new(),
+            task_manager,
+        }
+    }
+
+    fn count(&self) -> u32 {
+        self.workspaces.names.len() as u32
+    }
+
+    /// Width of the switcher in the panel, none when the window manager has no workspaces.
+    pub fn width(&self) -> u16 {
+        match self.count() as u16 {
+            0 => 0,
+            count => count * (BUTTON_WIDTH + SPACING) + 2 * ACTION_WIDTH + SPACING,
+        }
+    }
+
+    fn request(&self, request: impl FnOnce(&TaskManager) -> Result<(), Box<dyn Error>>) {
+        if let Some(task_manager) = &self.task_manager {
+            if let Err(e) = request(task_manager.as_ref()) {
+                println!("Error: {:?}", e);
+            }
+        }
+    }
+}
+
+impl Program for WorkspaceSwitcher {
+    type Renderer = Renderer;
+    type Message = WorkspacesMsg;
+
+    fn update(&mut self, msg: WorkspacesMsg) -> Command<WorkspacesMsg> {
+        match msg {
+            WorkspacesMsg::TasksChanged(tasks) => {
+                let count = tasks.workspaces.names.len();
+                self.window_counts = (0..count as u32)
+                    .map(|idx| {
+                        tasks
+                            .windows
+                            .iter()
+                            .filter(|window| window.desktop == Some(idx))
+                            .count()
+                    })
+                    .collect();
+                self.workspace_states.resize_with(count, button::State::new);
+                self.workspaces = tasks.workspaces;
+            }
+            WorkspacesMsg::WorkspaceClicked(idx) => {
+                self.request(|task_manager| task_manager.switch_workspace(idx))
+            }
+            WorkspacesMsg::Scrolled(lines) => {
+                let current = self.workspaces.current;
+                let next = if lines > 0.0 {
+                    current.checked_sub(1)
+                } else if lines < 0.0 {
+                    Some(current + 1).filter(|next| *next < self.count())
+                } else {
+                    None
+                };
+                if let Some(next) = next {
+                    self.request(|task_manager| task_manager.switch_workspace(next));
+                }
+            }
+            WorkspacesMsg::AddWorkspace => {
+                let count = self.count() + 1;
+                self.request(|task_manager| task_manager.set_workspace_count(count));
+            }
+            WorkspacesMsg::RemoveWorkspace => {
+                if self.count() > 1 {
+                    let count = self.count() - 1;
+                    self.request(|task_manager| task_manager.set_workspace_count(count));
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view(&mut self) -> Element<WorkspacesMsg, Renderer> {
+        let Self {
+            workspaces,
+            window_counts,
+            workspace_states,
+            add_state,
+            remove_state,
+            ..
+        } = self;
+        if workspaces.names.is_empty() {
+            return Row::new().into();
+        }
+
+        let row = workspaces
+            .names
+            .iter()
+            .zip(window_counts.iter())
+            .zip(workspace_states.iter_mut())
+            .enumerate()
+            .fold(
+                Row::new()
+                    .spacing(SPACING)
+                    .height(Length::Fill)
+                    .align_items(Align::Center),
+                |row, (idx, ((name, count), state))| {
+                    let idx = idx as u32;
+                    let label = if name.is_empty() {
+                        (idx + 1).to_string()
+                    } else {
+                        name.chars().take(MAX_NAME_LEN).collect()
+                    };
+                    let mut content = Row::new()
+                        .spacing(4)
+                        .align_items(Align::Center)
+                        .push(Text::new(label).size(13));
+                    if *count > 0 {
+                        content =
+                            content.push(Text::new(count.to_string()).size(10).color(HOVERED));
+                    }
+                    let style = if idx == workspaces.current {
+                        ButtonStyle::CircleRadius(15, 85, 179, 0.25, 4.0, Color::BLACK)
+                    } else {
+                        ButtonStyle::Transparent
+                    };
+
+                    row.push(
+                        Button::new(state, content)
+                            .width(Length::Units(BUTTON_WIDTH))
+                            .padding(4)
+                            .on_press(WorkspacesMsg::WorkspaceClicked(idx))
+                            .style(style),
+                    )
+                },
+            );
+        let mut remove = Button::new(remove_state, icon('\u{f068}').size(12))
+            .width(Length::Units(ACTION_WIDTH))
+            .style(ButtonStyle::Transparent);
+        if workspaces.names.len() > 1 {
+            remove = remove.on_press(WorkspacesMsg::RemoveWorkspace);
+        }
+
+        row.push(remove)
+            .push(
+                Button::new(add_state, icon('\u{f067}').size(12))
+                    .width(Length::Units(ACTION_WIDTH))
+                    .on_press(WorkspacesMsg::AddWorkspace)
+                    .style(ButtonStyle::Transparent),
+            )
+            .into()
+    }
+}